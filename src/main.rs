@@ -2,14 +2,24 @@
 use eframe::{egui, CreationContext};
 
 // Web server imports
-use actix_web::{get, App as ActixApp, HttpServer, HttpResponse, Result, Error};
+use actix_web::{get, post, web, App as ActixApp, HttpServer, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 // Thread imports
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::thread;
 
 // Standard file imports
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{Write, BufReader, BufRead, BufWriter};
+use std::io::{Write, BufReader, BufRead};
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -24,21 +34,400 @@ use std::env;
 // PDF Generation imports
 use printpdf::*;
 
+// Time imports
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+// Export imports
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+// QR code imports
+use qrcode::{Color as QrColor, QrCode};
+
+const DAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+// Meals the weekly planner fills for each day. Each reads from the recipe category
+// of the same name (recipes/breakfast, recipes/lunch, recipes/dinner).
+const MEAL_TYPES: [&str; 3] = ["Breakfast", "Lunch", "Dinner"];
+const DINNER: usize = 2;
+
+// A week with nothing planned: one row per day, one entry per meal type
+fn empty_week() -> Vec<Vec<String>> {
+    vec![vec![String::new(); MEAL_TYPES.len()]; 7]
+}
+
+// The days of the week in order, starting from the configured first day
+fn week_days(week_start: usize) -> [&'static str; 7] {
+    let mut days = DAYS;
+    days.rotate_left(week_start % 7);
+    days
+}
+
+const SETTINGS_PATH: &str = "settings.cfg";
+
+// How parsed durations are written back out
+#[derive(Clone, Copy, PartialEq)]
+pub enum DurationStyle {
+    // "1 hr 30 min"
+    Long,
+    // "1 h 30 min"
+    Short,
+    // "90 min"
+    Minutes,
+}
+
+impl DurationStyle {
+    const ALL: [DurationStyle; 3] = [DurationStyle::Long, DurationStyle::Short, DurationStyle::Minutes];
+
+    fn key(&self) -> &'static str {
+        match self {
+            DurationStyle::Long => "long",
+            DurationStyle::Short => "short",
+            DurationStyle::Minutes => "minutes",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|style| style.key() == key)
+    }
+
+    fn example(&self) -> &'static str {
+        match self {
+            DurationStyle::Long => "1 hr 30 min",
+            DurationStyle::Short => "1 h 30 min",
+            DurationStyle::Minutes => "90 min",
+        }
+    }
+}
+
+// How summed amounts on the shopping list are rounded
+#[derive(Clone, Copy, PartialEq)]
+pub enum RoundMode {
+    Exact,
+    // Up to the next ¼, e.g. 2.67 -> 2 3/4
+    QuarterUp,
+    // Up to the next whole number, e.g. 2.25 -> 3
+    WholeUp,
+}
+
+impl RoundMode {
+    const ALL: [RoundMode; 3] = [RoundMode::Exact, RoundMode::QuarterUp, RoundMode::WholeUp];
+
+    fn key(&self) -> &'static str {
+        match self {
+            RoundMode::Exact => "exact",
+            RoundMode::QuarterUp => "quarter",
+            RoundMode::WholeUp => "whole",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| mode.key() == key)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            RoundMode::Exact => "Exact",
+            RoundMode::QuarterUp => "Round up to nearest ¼",
+            RoundMode::WholeUp => "Round up to nearest whole",
+        }
+    }
+}
+
+// Units merged shopping list amounts are written in
+#[derive(Clone, Copy, PartialEq)]
+pub enum UnitSystem {
+    // Cups, tablespoons, teaspoons, ounces and pounds
+    Us,
+    // Millilitres, litres, grams and kilograms
+    Metric,
+}
+
+impl UnitSystem {
+    const ALL: [UnitSystem; 2] = [UnitSystem::Us, UnitSystem::Metric];
+
+    fn key(&self) -> &'static str {
+        match self {
+            UnitSystem::Us => "us",
+            UnitSystem::Metric => "metric",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|system| system.key() == key)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            UnitSystem::Us => "US (cups, oz)",
+            UnitSystem::Metric => "Metric (ml, g)",
+        }
+    }
+}
+
+// Paper the recipe PDFs are laid out for
+#[derive(Clone, Copy, PartialEq)]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    const ALL: [PageSize; 2] = [PageSize::A4, PageSize::Letter];
+
+    fn key(&self) -> &'static str {
+        match self {
+            PageSize::A4 => "a4",
+            PageSize::Letter => "letter",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|size| size.key() == key)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            PageSize::A4 => "A4 (210 × 297 mm)",
+            PageSize::Letter => "Letter (8.5 × 11 in)",
+        }
+    }
+
+    // Width and height in mm
+    fn dimensions(&self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+// Rounds up rather than to the nearest step so the shopping list never comes up
+// short. The small tolerance keeps float noise like 2.0000001 from becoming 2 1/4.
+fn round_quantity(q: f64, mode: RoundMode) -> f64 {
+    let step = match mode {
+        RoundMode::Exact => return q,
+        RoundMode::QuarterUp => 0.25,
+        RoundMode::WholeUp => 1.0,
+    };
+    ((q / step) - 1e-6).ceil() * step
+}
+
+// User preferences stored as simple key=value lines
+#[derive(Clone)]
+pub struct Settings {
+    pub decimal_comma: bool,
+    pub sort_shopping_list: bool,
+    // Index into DAYS of the first day of the week (0 = Monday)
+    pub week_start: usize,
+    // How often the manual entry screen saves a draft; 0 turns drafts off
+    pub draft_autosave_secs: u64,
+    // People to feed at each meal; 0 hides the planner's servings badges
+    pub household_size: u32,
+    // Randomize All avoids recipes from this many of the most recent processed weeks
+    pub avoid_recent_weeks: usize,
+    // Preview the shopping list under each day's recipe instead of as one list
+    pub group_shopping_by_recipe: bool,
+    pub duration_style: DurationStyle,
+    // Print each day's cooking time and the week's total on the week-at-a-glance sheet
+    pub show_times_on_cover: bool,
+    // Render instructions as "•" bullets instead of "1." numbers
+    pub bulleted_steps: bool,
+    // Extension (without the dot) of recipe files in scans and saves
+    pub recipe_extension: String,
+    pub shopping_rounding: RoundMode,
+    pub unit_system: UnitSystem,
+    // TTF file under fonts/ used for recipe PDFs; blank means the built-in Helvetica
+    pub pdf_font: String,
+    pub page_size: PageSize,
+    // Branch pulled from origin by Update and Restart
+    pub update_branch: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            decimal_comma: false,
+            sort_shopping_list: false,
+            week_start: 0,
+            draft_autosave_secs: 30,
+            household_size: 0,
+            avoid_recent_weeks: 2,
+            group_shopping_by_recipe: false,
+            duration_style: DurationStyle::Long,
+            show_times_on_cover: true,
+            bulleted_steps: false,
+            recipe_extension: DEFAULT_RECIPE_EXTENSION.to_string(),
+            shopping_rounding: RoundMode::Exact,
+            unit_system: UnitSystem::Us,
+            pdf_font: DEFAULT_PDF_FONT.to_string(),
+            page_size: PageSize::A4,
+            update_branch: DEFAULT_UPDATE_BRANCH.to_string(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings = Settings::default();
+        if let Ok(contents) = fs::read_to_string(SETTINGS_PATH) {
+            for line in contents.lines() {
+                if let Some((key, value)) = line.split_once('=') {
+                    match key.trim() {
+                        "decimal_comma" => settings.decimal_comma = value.trim() == "true",
+                        "sort_shopping_list" => settings.sort_shopping_list = value.trim() == "true",
+                        "week_start" => settings.week_start = value.trim().parse::<usize>().unwrap_or(0) % 7,
+                        "group_shopping_by_recipe" => settings.group_shopping_by_recipe = value.trim() == "true",
+                        "duration_style" => {
+                            if let Some(style) = DurationStyle::from_key(value.trim()) {
+                                settings.duration_style = style;
+                            }
+                        }
+                        "shopping_rounding" => {
+                            if let Some(mode) = RoundMode::from_key(value.trim()) {
+                                settings.shopping_rounding = mode;
+                            }
+                        }
+                        "unit_system" => {
+                            if let Some(system) = UnitSystem::from_key(value.trim()) {
+                                settings.unit_system = system;
+                            }
+                        }
+                        "show_times_on_cover" => settings.show_times_on_cover = value.trim() == "true",
+                        "bulleted_steps" => settings.bulleted_steps = value.trim() == "true",
+                        "recipe_extension" => settings.recipe_extension = normalize_extension(value),
+                        "pdf_font" => settings.pdf_font = value.trim().to_string(),
+                        "page_size" => {
+                            if let Some(size) = PageSize::from_key(value.trim()) {
+                                settings.page_size = size;
+                            }
+                        }
+                        "update_branch" if !value.trim().is_empty() => {
+                            settings.update_branch = value.trim().to_string();
+                        }
+                        "household_size" => settings.household_size = value.trim().parse::<u32>().unwrap_or(0),
+                        "avoid_recent_weeks" => {
+                            if let Ok(weeks) = value.trim().parse::<usize>() {
+                                settings.avoid_recent_weeks = weeks;
+                            }
+                        }
+                        "draft_autosave_secs" => {
+                            if let Ok(secs) = value.trim().parse::<u64>() {
+                                settings.draft_autosave_secs = secs;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut file = File::create(SETTINGS_PATH)?;
+        writeln!(file, "decimal_comma={}", self.decimal_comma)?;
+        writeln!(file, "sort_shopping_list={}", self.sort_shopping_list)?;
+        writeln!(file, "week_start={}", self.week_start)?;
+        writeln!(file, "draft_autosave_secs={}", self.draft_autosave_secs)?;
+        writeln!(file, "household_size={}", self.household_size)?;
+        writeln!(file, "avoid_recent_weeks={}", self.avoid_recent_weeks)?;
+        writeln!(file, "group_shopping_by_recipe={}", self.group_shopping_by_recipe)?;
+        writeln!(file, "duration_style={}", self.duration_style.key())?;
+        writeln!(file, "show_times_on_cover={}", self.show_times_on_cover)?;
+        writeln!(file, "bulleted_steps={}", self.bulleted_steps)?;
+        writeln!(file, "recipe_extension={}", self.recipe_extension)?;
+        writeln!(file, "shopping_rounding={}", self.shopping_rounding.key())?;
+        writeln!(file, "unit_system={}", self.unit_system.key())?;
+        writeln!(file, "pdf_font={}", self.pdf_font)?;
+        writeln!(file, "page_size={}", self.page_size.key())?;
+        writeln!(file, "update_branch={}", self.update_branch)?;
+        Ok(())
+    }
+}
+
+const DEFAULT_RECIPE_EXTENSION: &str = "rec";
+const DEFAULT_UPDATE_BRANCH: &str = "main";
+
+// "rec", ".rec" and " REC " all mean the same extension; blank falls back to the default
+fn normalize_extension(value: &str) -> String {
+    let extension = value.trim().trim_start_matches('.').to_lowercase();
+    if extension.is_empty() {
+        DEFAULT_RECIPE_EXTENSION.to_string()
+    } else {
+        extension
+    }
+}
+
+// The extension arguments below are Settings::recipe_extension, already normalized
+fn is_recipe_file(path: &Path, extension: &str) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+}
+
+// "<stem>.<extension>"
+fn recipe_file_name(stem: &str, extension: &str) -> String {
+    format!("{}.{}", stem, extension)
+}
+
+// Per-user config folder for Recipe Bot: %APPDATA% on Windows, Application Support
+// on macOS and $XDG_CONFIG_HOME (or ~/.config) elsewhere
+fn user_config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|dir| dir.join("recipe_bot"))
+}
+
+// The theme file holds a single "dark" or "light" line
+fn theme_path() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("theme"))
+}
+
+// Dark unless the theme file says otherwise; a missing or garbled file means dark
+fn load_dark_mode() -> bool {
+    match theme_path().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(contents) => contents.trim() != "light",
+        None => true,
+    }
+}
+
+fn save_dark_mode(is_dark_mode: bool) -> Result<(), std::io::Error> {
+    let path = theme_path().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, if is_dark_mode { "dark\n" } else { "light\n" })
+}
+
 #[derive(Default)]
 pub struct AppState {
     pub is_dark_mode: bool,
+    pub settings: Settings,
 }
 
 impl AppState {
     pub fn new() -> Self {
-        Self { is_dark_mode: true }
+        Self { is_dark_mode: load_dark_mode(), settings: Settings::load() }
     }
 
     pub fn toggle_dark_mode(&mut self) {
         self.is_dark_mode = !self.is_dark_mode;
+        if let Err(e) = save_dark_mode(self.is_dark_mode) {
+            eprintln!("Failed to save theme: {}", e);
+        }
     }
 }
 
+// Missing fields default to empty so JSON exported from other apps still loads
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct Recipe {
     title: String,
     from: String,
@@ -46,12 +435,143 @@ struct Recipe {
     prep_time: String,
     cook_time: String,
     total_time: String,
-    ingreds: Vec<String>,
+    tags: Vec<String>,
+    // Can be prepared a day or more in advance
+    make_ahead: bool,
+    // Free text such as "Easy" or "Weeknight"
+    difficulty: Option<String>,
+    // 0 to 5 stars
+    rating: Option<u8>,
+    ingreds: Vec<Ingredient>,
+    // Named ingredient groups such as "For the sauce", as (index into ingreds where
+    // the group begins, name). Ingredients before the first group have no heading.
+    // Storing start indices rather than (name, lines) keeps ingreds the only copy of
+    // each ingredient, so scaling and the shopping list rewrite one list and the groups
+    // still line up. grouped_ingredients() gives the (name, ingredients) view.
+    ingredient_groups: Vec<(usize, String)>,
+    prep_steps: Vec<String>,
     instructions: Vec<String>,
     notes: Vec<String>,
+    // Sections in the order the file lists them, so re-saving keeps the author's layout.
+    // Empty means the default order.
+    #[serde(skip)]
+    order: Vec<Section>,
+}
+
+// The "X Start" / "X End" blocks of a .rec file
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Section {
+    Ingredients,
+    Prep,
+    Instructions,
+    Notes,
+}
+
+impl Section {
+    // Also the order sections are written in when a recipe doesn't record one
+    const ALL: [Section; 4] = [Section::Ingredients, Section::Prep, Section::Instructions, Section::Notes];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Section::Ingredients => "Ingredients",
+            Section::Prep => "Prep",
+            Section::Instructions => "Instructions",
+            Section::Notes => "Notes",
+        }
+    }
+}
+
+// A line inside the ingredients section that names a group rather than an
+// ingredient, e.g. "For the sauce:"
+fn ingredient_group_heading(line: &str) -> Option<&str> {
+    let name = line.trim().strip_suffix(':')?.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
 }
 
-fn parse_recipe_file(file_path: &PathBuf) -> Result<Recipe, std::io::Error> {
+// Why a .rec file couldn't be loaded. Everything but Io carries the 1-based line
+// number to look at.
+#[derive(Debug)]
+enum RecipeParseError {
+    Io(std::io::Error),
+    // A "Key<TAB>value" header line with a key the format doesn't have
+    UnknownHeader { line: usize, key: String },
+    // The same header given twice, e.g. two Title lines
+    DuplicateHeader { line: usize, key: String },
+    // A "... Start" with no matching "... End"; line is where the section opened
+    UnterminatedSection { line: usize, section: String },
+    // A "... End" that doesn't close the section that's open
+    UnmatchedEnd { line: usize, section: String },
+    // Text outside any section that isn't a header either
+    StrayLine { line: usize, text: String },
+}
+
+impl RecipeParseError {
+    fn line(&self) -> Option<usize> {
+        match self {
+            RecipeParseError::Io(_) => None,
+            RecipeParseError::UnknownHeader { line, .. }
+            | RecipeParseError::DuplicateHeader { line, .. }
+            | RecipeParseError::UnterminatedSection { line, .. }
+            | RecipeParseError::UnmatchedEnd { line, .. }
+            | RecipeParseError::StrayLine { line, .. } => Some(*line),
+        }
+    }
+}
+
+impl fmt::Display for RecipeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            RecipeParseError::Io(e) => return write!(f, "{}", e),
+            RecipeParseError::UnknownHeader { key, .. } => format!("unknown header \"{}\"", key),
+            RecipeParseError::DuplicateHeader { key, .. } => format!("\"{}\" is given more than once", key),
+            RecipeParseError::UnterminatedSection { section, .. } => {
+                format!("\"{} Start\" has no matching \"{} End\"", section, section)
+            }
+            RecipeParseError::UnmatchedEnd { section, .. } => format!("\"{} End\" without a matching \"{} Start\"", section, section),
+            RecipeParseError::StrayLine { text, .. } => format!("\"{}\" is outside any section", text),
+        };
+        match self.line() {
+            Some(line) => write!(f, "line {}: {}", line, reason),
+            None => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RecipeParseError {}
+
+impl From<std::io::Error> for RecipeParseError {
+    fn from(e: std::io::Error) -> Self {
+        RecipeParseError::Io(e)
+    }
+}
+
+// Lets callers that only deal in io::Error keep using ? on parse_recipe_file
+impl From<RecipeParseError> for std::io::Error {
+    fn from(e: RecipeParseError) -> Self {
+        match e {
+            RecipeParseError::Io(e) => e,
+            other => std::io::Error::new(std::io::ErrorKind::InvalidData, other.to_string()),
+        }
+    }
+}
+
+// Loads a recipe for use, skipping header keys the format doesn't have and text
+// outside any section so one typo doesn't hide the whole recipe. Library Health
+// uses parse_recipe_file_strict to point those lines out.
+fn parse_recipe_file(file_path: &PathBuf) -> Result<Recipe, RecipeParseError> {
+    read_recipe_file(file_path, false)
+}
+
+// Like parse_recipe_file, but unknown headers and stray lines are errors too
+fn parse_recipe_file_strict(file_path: &PathBuf) -> Result<Recipe, RecipeParseError> {
+    read_recipe_file(file_path, true)
+}
+
+fn read_recipe_file(file_path: &PathBuf, strict: bool) -> Result<Recipe, RecipeParseError> {
     let file = File::open(file_path)?;
     let reader = BufReader::new(file);
 
@@ -62,1191 +582,8154 @@ fn parse_recipe_file(file_path: &PathBuf) -> Result<Recipe, std::io::Error> {
         prep_time: String::new(),
         cook_time: String::new(),
         total_time: String::new(),
+        tags: Vec::new(),
+        make_ahead: false,
+        difficulty: None,
+        rating: None,
         ingreds: Vec::new(),
+        ingredient_groups: Vec::new(),
+        prep_steps: Vec::new(),
         instructions: Vec::new(),
         notes: Vec::new(),
+        order: Vec::new(),
     };
 
-    let mut current_section = "";
+    // The open section and the line it started on
+    let mut current_section: Option<(Section, usize)> = None;
+    let mut seen_headers: HashSet<String> = HashSet::new();
 
-    for line in reader.lines() {
+    for (idx, line) in reader.lines().enumerate() {
+        let line_number = idx + 1;
         let line = line?;
-        if line.trim().is_empty() {
+        // Files saved on Windows may start with a UTF-8 BOM and end lines with "\r\n"
+        let line = if idx == 0 { line.trim_start_matches('\u{feff}') } else { line.as_str() };
+        let line = line.trim_end_matches('\r');
+        // Blank lines and "#" comments are skipped anywhere, even inside a section.
+        // Only a leading "#" counts, so "1 #10 can tomatoes" is still an ingredient.
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
             continue;
         }
 
-        if line.contains('\t') {
-            let parts: Vec<&str> = line.splitn(2, '\t').collect();
-            if parts.len() == 2 {
-                match parts[0].trim() {
-                    "Title" => recipe.title = parts[1].trim().to_string(),
-                    "From" => recipe.from = parts[1].trim().to_string(),
-                    "Servings" => recipe.servings = parts[1].trim().to_string(),
-                    "Prep Time" => recipe.prep_time = parts[1].trim().to_string(),
-                    "Cook Time" => recipe.cook_time = parts[1].trim().to_string(),
-                    "Total Time" => recipe.total_time = parts[1].trim().to_string(),
-                    _ => {}
-                }
+        // Section markers are case-insensitive too, like the header keys below
+        let trimmed = line.trim();
+        if let Some(section) = Section::ALL.iter().find(|section| trimmed.eq_ignore_ascii_case(&format!("{} Start", section.name()))) {
+            if let Some((open, start)) = current_section {
+                return Err(RecipeParseError::UnterminatedSection { line: start, section: open.name().to_string() });
             }
-        } else {
-            match line.trim() {
-                "Ingredients Start" => current_section = "Ingredients",
-                "Ingredients End" => current_section = "",
-                "Instructions Start" => current_section = "Instructions",
-                "Instructions End" => current_section = "",
-                "Notes Start" => current_section = "Notes",
-                "Notes End" => current_section = "",
-                _ => match current_section {
-                    "Ingredients" => recipe.ingreds.push(line.trim().to_string()),
-                    "Instructions" => recipe.instructions.push(line.trim().to_string()),
-                    "Notes" => recipe.notes.push(line.trim().to_string()),
+            current_section = Some((*section, line_number));
+            if !recipe.order.contains(section) {
+                recipe.order.push(*section);
+            }
+            continue;
+        }
+        if let Some(section) = Section::ALL.iter().find(|section| trimmed.eq_ignore_ascii_case(&format!("{} End", section.name()))) {
+            match current_section {
+                Some((open, _)) if open == *section => current_section = None,
+                _ => return Err(RecipeParseError::UnmatchedEnd { line: line_number, section: section.name().to_string() }),
+            }
+            continue;
+        }
+
+        match current_section {
+            Some((Section::Ingredients, _)) => match ingredient_group_heading(trimmed) {
+                Some(group) => recipe.ingredient_groups.push((recipe.ingreds.len(), group.to_string())),
+                // Files are read without the user's settings, so the parsed amount
+                // assumes decimal points; scaling and the shopping list re-read the
+                // line with the decimal_comma setting
+                None => recipe.ingreds.push(parse_ingredient(strip_list_marker(trimmed), false)),
+            },
+            Some((Section::Prep, _)) => recipe.prep_steps.push(trimmed.to_string()),
+            Some((Section::Instructions, _)) => recipe.instructions.push(trimmed.to_string()),
+            Some((Section::Notes, _)) => recipe.notes.push(trimmed.to_string()),
+            None => {
+                let (key, value) = match line.split_once('\t') {
+                    Some(parts) => parts,
+                    None if strict => return Err(RecipeParseError::StrayLine { line: line_number, text: trimmed.to_string() }),
+                    None => continue,
+                };
+                // Header keys are matched case-insensitively so hand-edited files still load
+                let normalized = key.trim().to_lowercase();
+                let canonical = if normalized == "make ahead" { "makeahead".to_string() } else { normalized };
+                if !seen_headers.insert(canonical.clone()) {
+                    return Err(RecipeParseError::DuplicateHeader { line: line_number, key: key.trim().to_string() });
+                }
+                match canonical.as_str() {
+                    "title" => recipe.title = value.trim().to_string(),
+                    "from" => recipe.from = value.trim().to_string(),
+                    "servings" => recipe.servings = value.trim().to_string(),
+                    "prep time" => recipe.prep_time = value.trim().to_string(),
+                    "cook time" => recipe.cook_time = value.trim().to_string(),
+                    "total time" => recipe.total_time = value.trim().to_string(),
+                    "tags" => recipe.tags = split_tags(value),
+                    "makeahead" => {
+                        recipe.make_ahead = matches!(value.trim().to_lowercase().as_str(), "true" | "yes" | "y" | "1")
+                    }
+                    "difficulty" => recipe.difficulty = Some(value.trim().to_string()).filter(|text| !text.is_empty()),
+                    "rating" => recipe.rating = parse_rating(value),
+                    _ if strict => {
+                        return Err(RecipeParseError::UnknownHeader { line: line_number, key: key.trim().to_string() })
+                    }
                     _ => {}
-                },
+                }
             }
         }
     }
 
+    if let Some((open, start)) = current_section {
+        return Err(RecipeParseError::UnterminatedSection { line: start, section: open.name().to_string() });
+    }
+
     Ok(recipe)
 }
 
-fn generate_recipe_pdf(recipe_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    // Parse the recipe file
-    let recipe = parse_recipe_file(recipe_path)?;
-
-    // Create a new PDF document
-    let (doc, page1, layer1) = PdfDocument::new(&recipe.title, Mm(210.0), Mm(297.0), "Layer 1");
-
-    // Use a built-in font
-    let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+// Most recipes the cache holds; well above a typical collection, so it only matters
+// when files are renamed or deleted over a long session
+const RECIPE_CACHE_CAPACITY: usize = 1000;
+
+// Parsed recipes by path, each with the file's modified time when it was read. An
+// entry is reused until the file changes on disk. Once full, the path cached
+// longest ago is dropped to make room.
+struct RecipeCache {
+    entries: HashMap<PathBuf, (SystemTime, Recipe)>,
+    // Cached paths, oldest first
+    order: VecDeque<PathBuf>,
+    capacity: usize,
+}
 
-    // Create a struct to hold the mutable state
-    struct State {
-        y_position: f32,
-        current_page: PdfPageIndex,
-        current_layer: PdfLayerIndex,
+impl Default for RecipeCache {
+    fn default() -> Self {
+        Self::with_capacity(RECIPE_CACHE_CAPACITY)
     }
+}
 
-    let mut state = State {
-        y_position: 280.0,
-        current_page: page1,
-        current_layer: layer1,
-    };
+impl RecipeCache {
+    fn with_capacity(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), order: VecDeque::new(), capacity: capacity.max(1) }
+    }
 
-    // Function to wrap text
-    fn wrap_text(text: &str, font_size: f32, max_width: f32) -> Vec<String> {
-        let words: Vec<&str> = text.split_whitespace().collect();
-        let mut lines = Vec::new();
-        let mut current_line = String::new();
-        let space_width = font_size * 0.3; // Approximate space width
-
-        for word in words {
-            let word_width = word.len() as f32 * font_size * 0.6; // Approximate word width
-            if current_line.is_empty() {
-                current_line = word.to_string();
-            } else if current_line.len() as f32 * font_size * 0.6 + space_width + word_width <= max_width {
-                current_line.push(' ');
-                current_line.push_str(word);
-            } else {
-                lines.push(current_line);
-                current_line = word.to_string();
+    fn get(&mut self, path: &Path) -> Result<Recipe, RecipeParseError> {
+        let modified = fs::metadata(path)?.modified()?;
+        if let Some((cached_at, recipe)) = self.entries.get(path) {
+            if *cached_at == modified {
+                return Ok(recipe.clone());
             }
         }
-        if !current_line.is_empty() {
-            lines.push(current_line);
+        let recipe = parse_recipe_file(&path.to_path_buf())?;
+        if self.entries.insert(path.to_path_buf(), (modified, recipe.clone())).is_none() {
+            self.order.push_back(path.to_path_buf());
+            while self.order.len() > self.capacity {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.entries.remove(&oldest);
+                }
+            }
         }
-        lines
+        Ok(recipe)
     }
+}
 
-    // Helper function to add text
-    let add_text = |text: &str, size: f32, x: f32, state: &mut State| {
-        let max_width = 680.0; // Page width minus margins
-        let wrapped_lines = wrap_text(text, size, max_width);
+// Shared between the UI and the PDF worker threads
+static RECIPE_CACHE: Mutex<Option<RecipeCache>> = Mutex::new(None);
 
-        for line in wrapped_lines {
-            if state.y_position < 20.0 {
-                // Create a new page
-                let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
-                state.current_page = new_page;
-                state.current_layer = new_layer;
-                state.y_position = 280.0;
-            }
-            let layer = doc.get_page(state.current_page).get_layer(state.current_layer);
-            layer.use_text(&line, size, Mm(x), Mm(state.y_position), &font);
-            state.y_position -= size as f32 + 2.0; // Move down by font size plus a small gap
-        }
-    };
+// parse_recipe_file, skipping the parse when the file hasn't changed since last time
+fn parse_recipe_cached(path: &Path) -> Result<Recipe, RecipeParseError> {
+    let mut cache = RECIPE_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.get_or_insert_with(RecipeCache::default).get(path)
+}
 
-    // Add recipe details
-    add_text(&recipe.title, 20.0, 10.0, &mut state);
-    add_text(&format!("From: {}", recipe.from), 14.0, 10.0, &mut state);
-    add_text(&format!("Servings: {}", recipe.servings), 14.0, 10.0, &mut state);
-    add_text(&format!("Prep Time: {}", recipe.prep_time), 14.0, 10.0, &mut state);
-    add_text(&format!("Cook Time: {}", recipe.cook_time), 14.0, 10.0, &mut state);
-    add_text(&format!("Total Time: {}", recipe.total_time), 14.0, 10.0, &mut state);
+// A whole number of stars, clamped to 0..=5; anything that isn't a number is no rating
+fn parse_rating(value: &str) -> Option<u8> {
+    value.trim().parse::<i64>().ok().map(|stars| stars.clamp(0, 5) as u8)
+}
 
-    state.y_position -= 10.0; // Add some space
+// "★★★☆☆" for a 3 star rating
+fn rating_stars(rating: u8) -> String {
+    let filled = rating.min(5) as usize;
+    format!("{}{}", "★".repeat(filled), "☆".repeat(5 - filled))
+}
 
-    // Add ingredients
-    add_text("Ingredients:", 16.0, 10.0, &mut state);
-    for ingredient in &recipe.ingreds {
-        add_text(&format!("• {}", ingredient), 12.0, 15.0, &mut state);
+// Drops a leading "- ", "* " or "• " so hand-edited lists don't end up with double bullets
+fn strip_list_marker(line: &str) -> &str {
+    for marker in ["- ", "* ", "• "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return rest.trim_start();
+        }
     }
+    line
+}
 
-    state.y_position -= 10.0; // Add some space
-
-    // Add instructions
-    add_text("Instructions:", 16.0, 10.0, &mut state);
-    for (_idx, instruction) in recipe.instructions.iter().enumerate() {
-        add_text(&format!("{}", instruction), 12.0, 15.0, &mut state);
+fn format_duration(duration: Duration, style: DurationStyle) -> String {
+    let total_minutes = duration.as_secs() / 60;
+    let (hours, minutes) = (total_minutes / 60, total_minutes % 60);
+    let hour_label = match style {
+        DurationStyle::Minutes => return format!("{} min", total_minutes),
+        DurationStyle::Long => "hr",
+        DurationStyle::Short => "h",
+    };
+    match (hours, minutes) {
+        (0, minutes) => format!("{} min", minutes),
+        (hours, 0) => format!("{} {}", hours, hour_label),
+        (hours, minutes) => format!("{} {} {} min", hours, hour_label, minutes),
     }
+}
 
-    state.y_position -= 10.0; // Add some space
-
-    // Add notes if any
-    if !recipe.notes.is_empty() {
-        add_text("Notes:", 16.0, 10.0, &mut state);
-        for note in &recipe.notes {
-            add_text(&format!("{}", note), 12.0, 15.0, &mut state);
+// Total Time when it parses, otherwise Prep Time plus Cook Time
+fn recipe_total_time(recipe: &Recipe) -> Option<Duration> {
+    parse_duration(&recipe.total_time).or_else(|| {
+        match (parse_duration(&recipe.prep_time), parse_duration(&recipe.cook_time)) {
+            (None, None) => None,
+            (prep, cook) => Some(prep.unwrap_or_default() + cook.unwrap_or_default()),
         }
-    }
-
-    // Save the PDF to a file
-    let output_filename = format!("{}.pdf", recipe.title.replace(" ", "_"));
-    let output_path = env::current_dir()?.join(&output_filename);
-    let mut output_file = BufWriter::new(File::create(&output_path)?);
-    doc.save(&mut output_file)?;
-
-    println!("PDF saved to: {:?}", output_path);
+    })
+}
 
-    Ok(())
+// Shows a recipe's time field in the chosen style, or as written if it can't be parsed
+fn display_time(text: &str, style: DurationStyle) -> String {
+    match parse_duration(text) {
+        Some(duration) => format_duration(duration, style),
+        None => text.to_string(),
+    }
 }
 
-fn open_pdf(pdf_path: &Path) -> std::io::Result<()> {
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("cmd")
-            .args(&["/C", "start", "", pdf_path.to_str().unwrap()])
-            .spawn()?;
+// Parses durations like "10 min", "1 hr 10 min" or "20-25 minutes" out of free text.
+// Ranges use their upper bound so timelines err on the side of starting early.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let lowered = text.to_lowercase();
+    let tokens: Vec<&str> = lowered
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .filter(|token| !token.is_empty())
+        .collect();
+    let mut total_minutes = 0.0;
+    let mut found = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        // Split "25min" style tokens into the number and the unit
+        let split_at = token.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-')).unwrap_or(token.len());
+        let (number, attached_unit) = token.split_at(split_at);
+        let value = match number.rsplit('-').next().and_then(|n| n.parse::<f64>().ok()) {
+            Some(value) => value,
+            None => continue,
+        };
+        let unit = if attached_unit.is_empty() {
+            match tokens.get(i + 1) {
+                Some(unit) => *unit,
+                None => continue,
+            }
+        } else {
+            attached_unit
+        };
+        let unit = unit.trim_end_matches(['.', ';', ':']);
+        match unit {
+            "h" | "hr" | "hrs" | "hour" | "hours" => {
+                total_minutes += value * 60.0;
+                found = true;
+            }
+            "m" | "min" | "mins" | "minute" | "minutes" => {
+                total_minutes += value;
+                found = true;
+            }
+            _ => {}
+        }
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        Command::new("xdg-open")
-            .arg(pdf_path)
-            .spawn()?;
+
+    if found {
+        Some(Duration::from_secs((total_minutes * 60.0).round() as u64))
+    } else {
+        None
     }
-    Ok(())
 }
 
-struct MainScreen {
-    app_state: AppState,
-    current_screen: Option<Box<dyn Screen>>,
+struct TimelineEntry {
+    start: NaiveTime,
+    minutes_before: i64,
+    step: String,
 }
 
-impl Default for MainScreen {
-    fn default() -> Self {
-        Self {
-            app_state: AppState::new(),
-            current_screen: None,
+// Works backward from the serve time, running timed steps one after another.
+// Steps without a duration can't be placed on the timeline and are skipped.
+fn build_timeline(recipe: &Recipe, serve_at: NaiveTime) -> Vec<TimelineEntry> {
+    let mut entries = Vec::new();
+    let mut minutes_before = 0;
+
+    for step in recipe.instructions.iter().rev() {
+        if let Some(duration) = parse_duration(step) {
+            minutes_before += (duration.as_secs() / 60) as i64;
+            entries.push(TimelineEntry {
+                start: serve_at - chrono::Duration::minutes(minutes_before),
+                minutes_before,
+                step: step.clone(),
+            });
         }
     }
+
+    entries.reverse();
+    entries
 }
 
-impl MainScreen {
-    fn name() -> &'static str {
-        "Recipe Bot"
+// Serializes a recipe into the canonical .rec format read by parse_recipe_file
+fn recipe_to_rec(recipe: &Recipe) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("Title\t{}\n", recipe.title));
+    out.push_str(&format!("From\t{}\n", recipe.from));
+    out.push_str(&format!("Servings\t{}\n", recipe.servings));
+    out.push_str(&format!("Prep Time\t{}\n", recipe.prep_time));
+    out.push_str(&format!("Cook Time\t{}\n", recipe.cook_time));
+    out.push_str(&format!("Total Time\t{}\n", recipe.total_time));
+    // Tags are optional, so older files stay byte-for-byte the same when re-saved
+    if !recipe.tags.is_empty() {
+        out.push_str(&format!("Tags\t{}\n", recipe.tags.join(", ")));
     }
-
-    fn handle_dark_mode_toggle(&mut self) {
-        self.app_state.toggle_dark_mode();
+    if recipe.make_ahead {
+        out.push_str("MakeAhead\ttrue\n");
     }
-
-    fn update(&mut self, ctx: &egui::Context) {
-        ctx.set_pixels_per_point(3.0);
-        let is_dark_mode = self.app_state.is_dark_mode;
-        let background_color = if is_dark_mode {
-            egui::Color32::from_rgb(30, 30, 30)
-        } else {
-            egui::Color32::WHITE
-        };
-        if let Some(screen) = &mut self.current_screen {
-            if screen.wants_to_exit() {
-                self.current_screen = None;
-            } else {
-                if let Some(new_screen) = screen.update(ctx, &mut self.app_state) {
-                    self.current_screen = Some(new_screen);
+    if let Some(difficulty) = &recipe.difficulty {
+        out.push_str(&format!("Difficulty\t{}\n", difficulty));
+    }
+    if let Some(rating) = recipe.rating {
+        out.push_str(&format!("Rating\t{}\n", rating));
+    }
+    // Sections the file listed keep their order; any it didn't follow in the default order
+    let mut order = recipe.order.clone();
+    order.extend(Section::ALL.iter().filter(|section| !recipe.order.contains(section)));
+    for section in order {
+        let lines: Vec<String> = match section {
+            Section::Ingredients => {
+                let mut lines = Vec::new();
+                for (group, ingredients) in recipe.grouped_ingredients() {
+                    if !group.is_empty() {
+                        lines.push(format!("{}:", group));
+                    }
+                    lines.extend(ingredients.iter().map(|ingredient| ingredient.to_string()));
                 }
-                return;
+                lines
             }
+            // The prep section is optional, so only write it when there is something in it
+            Section::Prep if recipe.prep_steps.is_empty() => continue,
+            Section::Prep => recipe.prep_steps.clone(),
+            Section::Instructions => recipe.instructions.clone(),
+            Section::Notes => recipe.notes.clone(),
+        };
+        out.push_str(&format!("{} Start\n", section.name()));
+        for line in lines {
+            out.push_str(&format!("{}\n", line));
         }
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
-            ui.vertical_centered(|ui| {
-                ui.heading("Recipe Bot");
+        out.push_str(&format!("{} End\n", section.name()));
+    }
+    out
+}
 
-                if ui.button("Create Weekly Recipes").clicked() {
-                    self.current_screen = Some(Box::new(CreateWeeklyRecipesScreen::default()));
-                }
+// Whether a recipe should be listed when the "make-ahead only" filter may be on
+fn passes_make_ahead_filter(recipe: &Recipe, make_ahead_only: bool) -> bool {
+    !make_ahead_only || recipe.make_ahead
+}
 
-                if ui.button("Update and Restart").clicked() {
-                    if let Err(e) = self.update_and_restart() {
-                        eprintln!("Failed to update and restart: {}", e);
-                    }
-                }
+// Whether the query appears as a whole word or phrase, not just inside a longer word
+fn contains_word(text: &str, query: &str) -> bool {
+    text.match_indices(query).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + query.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
 
-                if ui.button("Create New Recipe - Manual Entry").clicked() {
-                    self.current_screen = Some(Box::new(CreateRecipeManuallyScreen::default()));
-                }
+// Best match of the query among one field's texts: base + 2 for a whole-word
+// match, base + 1 for a match inside a word, 0 for none
+fn field_match_score<'a>(base: f32, texts: impl Iterator<Item = &'a str>, query: &str) -> f32 {
+    texts
+        .map(|text| text.to_lowercase())
+        .filter(|text| text.contains(query))
+        .map(|text| if contains_word(&text, query) { base + 2.0 } else { base + 1.0 })
+        .fold(0.0, f32::max)
+}
 
-                if ui.button("Light/Dark Mode Toggle").clicked() {
-                    self.handle_dark_mode_toggle();
-                }
+// Ranks how well a recipe matches a search: title matches beat ingredient matches,
+// which beat instruction matches, and whole-word matches beat matches inside a word.
+// 0.0 means no match.
+fn score_match(recipe: &Recipe, query: &str) -> f32 {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return 0.0;
+    }
+    let title = field_match_score(4.0, std::iter::once(recipe.title.as_str()), &query);
+    let ingredients = field_match_score(2.0, recipe.ingreds.iter().map(|ingredient| ingredient.text.as_str()), &query);
+    let steps = recipe.prep_steps.iter().chain(recipe.instructions.iter()).map(|step| step.as_str());
+    let instructions = field_match_score(0.0, steps, &query);
+    title.max(ingredients).max(instructions)
+}
 
-                if ui.button("View Recipe").clicked() {
-                    self.current_screen = Some(Box::new(RecipeSelectionScreen::default()));
-                }
+fn split_tags(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
 
-                // Update text color based on dark mode
-                if is_dark_mode {
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
-                } else {
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
-                }
-            });
-        });
+// Ingredient words that hint at a tag. A keyword also matches with an "s" added;
+// other plurals ("anchovies") are listed as they're spelled. Anything in MEAT_TAGS
+// also rules out "vegetarian".
+const TAG_KEYWORDS: [(&str, &[&str]); 9] = [
+    ("poultry", &["chicken", "turkey", "duck", "goose", "quail", "geese"]),
+    ("beef", &["beef", "steak", "brisket", "veal", "oxtail"]),
+    ("pork", &["pork", "bacon", "ham", "sausage", "prosciutto", "pancetta", "chorizo", "pepperoni", "salami", "guanciale"]),
+    ("lamb", &["lamb", "mutton"]),
+    ("game", &["venison", "rabbit", "boar", "elk", "bison"]),
+    ("seafood", &[
+        "fish", "salmon", "shrimp", "tuna", "cod", "crab", "prawn", "anchovy", "anchovies", "bass", "trout",
+        "sardine", "halibut", "haddock", "tilapia", "mussel", "clam", "scallop", "oyster", "squid", "octopus", "lobster",
+    ]),
+    ("meat", &["meat", "meatball", "mince"]),
+    ("pasta", &["pasta", "spaghetti", "noodle", "penne", "macaroni", "lasagna"]),
+    ("baking", &["flour", "yeast", "baking"]),
+];
+const MEAT_TAGS: [&str; 7] = ["poultry", "beef", "pork", "lamb", "game", "seafood", "meat"];
+
+// Guesses tags from the ingredient list by whole-word matches, so "graham" isn't
+// mistaken for ham and "hummus" is left as it is. Recipes with no meat or fish get
+// "vegetarian" as a candidate.
+fn suggest_tags(recipe: &Recipe) -> Vec<String> {
+    let words: HashSet<String> = recipe
+        .ingreds
+        .iter()
+        .flat_map(|ingredient| {
+            ingredient.name.split(|c: char| !c.is_alphanumeric())
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<String>>()
+        })
+        .collect();
+    let mentions = |keyword: &str| words.contains(keyword) || words.contains(&format!("{}s", keyword));
+
+    let mut tags: Vec<String> = TAG_KEYWORDS
+        .iter()
+        .filter(|(_, keywords)| keywords.iter().any(|keyword| mentions(keyword)))
+        .map(|(tag, _)| tag.to_string())
+        .collect();
+    if !recipe.ingreds.is_empty() && !tags.iter().any(|tag| MEAT_TAGS.contains(&tag.as_str())) {
+        tags.push("vegetarian".to_string());
     }
-    fn update_and_restart(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let current_exe = env::current_exe()?;
+    tags
+}
 
-        // Pull from git
-        Command::new("git")
-            .args(&["pull", "origin", "main"]) // Adjust branch name if necessary
-            .status()?;
+fn write_recipe_file(recipe: &Recipe, path: &Path) -> Result<(), std::io::Error> {
+    let mut file = File::create(path)?;
+    file.write_all(recipe_to_rec(recipe).as_bytes())?;
+    Ok(())
+}
 
-        // Recompile the program
-        Command::new("cargo")
-            .args(&["build", "--release"])
-            .status()?;
+// Renders the instructions for display, renumbering them or swapping the numbers
+// for bullets; the stored file keeps its own numbering either way
+fn format_steps(instructions: &[String], bulleted: bool) -> Vec<String> {
+    instructions
+        .iter()
+        .enumerate()
+        .map(|(idx, step)| {
+            let text = strip_step_number(step);
+            if bulleted {
+                format!("• {}", text)
+            } else {
+                format!("{}. {}", idx + 1, text)
+            }
+        })
+        .collect()
+}
 
-        // Restart the program
-        Command::new(current_exe)
-            .spawn()?;
+// True when a line opens a numbered step such as "3." or "3)"
+fn starts_numbered_step(line: &str) -> bool {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && matches!(line[digits..].chars().next(), Some('.') | Some(')'))
+}
 
-        // Exit the current instance
-        std::process::exit(0);
+// Removes a leading "3." or "3)" step number
+fn strip_step_number(line: &str) -> &str {
+    if starts_numbered_step(line) {
+        let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+        line[digits + 1..].trim_start()
+    } else {
+        line
     }
 }
 
-impl eframe::App for MainScreen {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame){
-        self.update(ctx);
+// Imported text often wraps one numbered step across several lines.
+// Lines that don't start a new number are appended to the step before them.
+fn merge_wrapped_steps(lines: &[String]) -> Vec<String> {
+    let lines: Vec<&str> = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+    // Without any numbering there is nothing to tell wrapped lines apart
+    if !lines.iter().any(|line| starts_numbered_step(line)) {
+        return lines.iter().map(|line| line.to_string()).collect();
     }
-}
-trait Screen {
-    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>>;
-    fn wants_to_exit(&self) -> bool;
+
+    let mut steps: Vec<String> = Vec::new();
+    for line in lines {
+        match steps.last_mut() {
+            Some(previous) if !starts_numbered_step(line) => {
+                previous.push(' ');
+                previous.push_str(line);
+            }
+            _ => steps.push(line.to_string()),
+        }
+    }
+    steps
 }
 
-struct CreateWeeklyRecipesScreen{
-    wants_to_exit: bool,
-    recipes: Vec<String>,
-    selected_recipes: Vec<String>,
-    processing_message: String,
+struct ServingsRange {
+    min: f32,
+    max: f32,
+    unit: Option<String>,
 }
 
-impl CreateWeeklyRecipesScreen {
-    fn load_recipes() -> Vec<String> {
-        let recipes_dir = Path::new("recipes/dinner");
-        fs::read_dir(recipes_dir)
-            .unwrap_or_else(|_| panic!("Failed to read recipes directory"))
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                if path.extension()? == "rec" {
-                    Some(path.file_stem()?.to_string_lossy().into_owned())
-                } 
-                else {
-                    None
-                }
-            })
-            .collect()
+// Pulls the numbers out of free-form servings text like "4", "Serves 4-6" or "makes a dozen",
+// along with an optional unit label such as "bowls" in "4 bowls". Two numbers only make
+// a range when joined by "-", "–" or "to", and anything in parentheses is an aside, so
+// "Serves 4 (about 2 cups)" is just 4.
+fn parse_servings(servings: &str) -> Option<ServingsRange> {
+    fn leading_number(text: &str) -> Option<(f32, &str)> {
+        let end = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+        text[..end].parse::<f32>().ok().map(|value| (value, &text[end..]))
     }
-    fn randomize_all(&mut self) {
-        let mut rng = thread_rng();
-        for recipe in &mut self.selected_recipes {
-            *recipe = self.recipes.choose(&mut rng).unwrap_or(&String::new()).clone();
-        }
+    fn unit_label(rest: &str) -> Option<String> {
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        if words.is_empty() { None } else { Some(words.join(" ")) }
     }
-    fn randomize_single(&mut self, idx: usize) {
-        let mut rng = thread_rng();
-        if let Some(recipe) = self.selected_recipes.get_mut(idx) {
-            *recipe = self.recipes.choose(&mut rng).unwrap_or(&String::new()).clone();
+
+    let (text, _) = split_prep_note(servings);
+    let lowered = text.to_lowercase();
+    let (min, max, rest) = match text.find(|c: char| c.is_ascii_digit()) {
+        Some(start) => {
+            let (first, rest) = leading_number(&text[start..])?;
+            let after = rest.trim_start();
+            let joined = after
+                .strip_prefix('-')
+                .or_else(|| after.strip_prefix('–'))
+                .or_else(|| after.get(..3).filter(|word| word.eq_ignore_ascii_case("to ")).map(|_| &after[3..]));
+            match joined.and_then(|joined| leading_number(joined.trim_start())) {
+                Some((second, rest)) => (first.min(second), first.max(second), rest),
+                None => (first, first, rest),
+            }
         }
+        // "makes a dozen" has no digits at all
+        None => match lowered.find("dozen") {
+            Some(idx) => (1.0, 1.0, &lowered[idx..]),
+            None => return None,
+        },
+    };
+    if max <= 0.0 {
+        return None;
     }
-    fn process_selected_recipes(&self) -> Result<(), std::io::Error> {
-        fs::create_dir_all("schedule")?;
-        let mut process_ingredients = String::new();
-        let mut process_schedule = String::new();
-        let days = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
 
-        for (i, recipe_name) in self.selected_recipes.iter().enumerate() {
-            if recipe_name.is_empty(){
-                continue;
+    let rest = rest.trim_start();
+    match rest.get(..5).filter(|word| word.eq_ignore_ascii_case("dozen")) {
+        Some(_) => Some(ServingsRange { min: min * 12.0, max: max * 12.0, unit: unit_label(&rest[5..]) }),
+        None => Some(ServingsRange { min, max, unit: unit_label(rest) }),
+    }
+}
+
+// Phrases the servings after scaling, e.g. "scaled to 6 bowls"
+fn describe_scaled_servings(servings: &str, factor: f32) -> Option<String> {
+    let range = parse_servings(servings)?;
+    let unit = range.unit.unwrap_or_else(|| "servings".to_string());
+    let (min, max) = (range.min * factor, range.max * factor);
+    if min == max {
+        Some(format!("scaled to {} {}", format_quantity(min), unit))
+    } else {
+        Some(format!("scaled to {}-{} {}", format_quantity(min), format_quantity(max), unit))
+    }
+}
+
+// The active multiplier relative to the recipe as written, e.g. "1x → 1.5x"
+fn describe_scale_factor(factor: f32) -> String {
+    format!("1x → {}x", format_quantity(factor))
+}
+
+// Picks any recipe from the collection that isn't excluded from randomizing
+fn pick_surprise_recipe(names: &[String], excluded: &HashSet<String>) -> Option<String> {
+    let pool: Vec<&String> = names.iter().filter(|name| !excluded.contains(*name)).collect();
+    pool.choose(&mut thread_rng()).map(|name| name.to_string())
+}
+
+// Whether a recipe's servings feed the household without scaling. None when the
+// household size isn't set or the servings can't be parsed.
+fn servings_cover_household(servings: &str, household_size: u32) -> Option<bool> {
+    if household_size == 0 {
+        return None;
+    }
+    let range = parse_servings(servings)?;
+    Some(range.min >= household_size as f32)
+}
+
+enum IssueKind {
+    Unreadable(String),
+    MissingTitle,
+    EmptySection(&'static str),
+    UnparseableTime(&'static str, String),
+    DuplicateFilename(Vec<PathBuf>),
+}
+
+// A problem found in one recipe file
+struct Issue {
+    path: PathBuf,
+    kind: IssueKind,
+}
+
+impl Issue {
+    fn describe(&self) -> String {
+        let detail = match &self.kind {
+            IssueKind::Unreadable(e) => format!("could not be read: {}", e),
+            IssueKind::MissingTitle => "has no title".to_string(),
+            IssueKind::EmptySection(section) => format!("has no {}", section),
+            IssueKind::UnparseableTime(field, value) => format!("{} \"{}\" isn't a recognisable duration", field, value),
+            IssueKind::DuplicateFilename(others) => format!(
+                "shares its name with {}",
+                others.iter().map(|other| other.display().to_string()).collect::<Vec<String>>().join(", ")
+            ),
+        };
+        format!("{}: {}", self.path.display(), detail)
+    }
+}
+
+// Checks every recipe in the given folders for problems worth fixing by hand.
+// Recipes don't reference images, so there are no image paths to check.
+fn audit_library(dirs: &[String], extension: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut paths_by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    for dir in dirs {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| is_recipe_file(path, extension))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            if let Some(stem) = path.file_stem() {
+                paths_by_name.entry(stem.to_string_lossy().to_lowercase()).or_default().push(path.clone());
             }
-            let recipe_path = Path::new("recipes/dinner").join(format!("{}.rec",recipe_name));
-            let dest_path = Path::new("schedule").join(format!("{}.rec", days[i]));
-            fs::copy(&recipe_path, &dest_path)?;
-            process_schedule.push_str(&format!("{}: {}\n", days[i], recipe_name));
-            let file = File::open(&recipe_path)?;
-            let reader = BufReader::new(file);
-            let mut in_ingredients = false;
-            for line in reader.lines() {
-                let line = line?;
-                if line.trim() == "Ingredients Start" {
-                    in_ingredients = true;
+            let recipe = match parse_recipe_file_strict(&path) {
+                Ok(recipe) => recipe,
+                Err(e) => {
+                    issues.push(Issue { path, kind: IssueKind::Unreadable(e.to_string()) });
+                    continue;
                 }
-                else if line.trim() == "Ingredients End" {
-                    in_ingredients = false;
-                }
-                else if in_ingredients{
-                    process_ingredients.push_str(&line);
-                    process_ingredients.push('\n');
+            };
+            if recipe.title.trim().is_empty() {
+                issues.push(Issue { path: path.clone(), kind: IssueKind::MissingTitle });
+            }
+            if recipe.ingreds.is_empty() {
+                issues.push(Issue { path: path.clone(), kind: IssueKind::EmptySection("ingredients") });
+            }
+            if recipe.instructions.is_empty() {
+                issues.push(Issue { path: path.clone(), kind: IssueKind::EmptySection("instructions") });
+            }
+            for (field, value) in [("Prep Time", &recipe.prep_time), ("Cook Time", &recipe.cook_time), ("Total Time", &recipe.total_time)] {
+                if !value.trim().is_empty() && parse_duration(value).is_none() {
+                    issues.push(Issue { path: path.clone(), kind: IssueKind::UnparseableTime(field, value.clone()) });
                 }
             }
         }
-        let mut ingredients_file = File::create("schedule/ingredients.sup")?;
-        ingredients_file.write_all(process_ingredients.as_bytes())?;
-        let mut schedule_file = File::create("schedule/schedule.txt")?;
-        schedule_file.write_all(process_schedule.as_bytes())?;
-
-        Ok(())
-    }
-    fn clear_processing_message(&mut self) {
-        self.processing_message.clear();
     }
-}
 
-impl Default for CreateWeeklyRecipesScreen {
-    fn default() -> Self {
-        let recipes = Self::load_recipes();
-        Self {
-            wants_to_exit: false,
-            recipes: recipes.clone(),
-            selected_recipes: vec![String::new(); 7],
-            processing_message: String::new(),
+    let mut duplicates: Vec<Vec<PathBuf>> = paths_by_name.into_values().filter(|paths| paths.len() > 1).collect();
+    duplicates.sort();
+    for paths in duplicates {
+        for path in &paths {
+            let others = paths.iter().filter(|other| *other != path).cloned().collect();
+            issues.push(Issue { path: path.clone(), kind: IssueKind::DuplicateFilename(others) });
         }
     }
+    issues
 }
 
-impl Screen for CreateWeeklyRecipesScreen {
-    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
-        ctx.set_pixels_per_point(3.0);
+// Display name for a category folder, e.g. "Dinner" for recipes/dinner. The
+// original dessert folder was created as "desert", so it's shown spelled correctly.
+fn category_label(dir: &str) -> String {
+    let folder = Path::new(dir).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    if folder.eq_ignore_ascii_case("desert") {
+        return "Dessert".to_string();
+    }
+    let mut category: Vec<char> = folder.chars().collect();
+    if let Some(first) = category.first_mut() {
+        *first = first.to_ascii_uppercase();
+    }
+    category.into_iter().collect()
+}
 
-        let is_dark_mode = app_state.is_dark_mode;
-        let background_color = if is_dark_mode {
-            egui::Color32::from_rgb(30, 30, 30)
-        } else {
-            egui::Color32::WHITE
+// Buckets recipe names by the folder they live in, e.g. "Dinner" for recipes/dinner.
+// Folders that are missing or empty are left out, and folders that share a label
+// (recipes/desert and recipes/dessert) are merged.
+fn group_recipes_by_category(dirs: &[String], extension: &str) -> Vec<(String, Vec<String>)> {
+    let mut categories: Vec<(String, Vec<String>)> = Vec::new();
+    for dir in dirs {
+        let mut recipes: Vec<String> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| is_recipe_file(path, extension))
+                .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+                .collect(),
+            Err(_) => continue,
         };
+        if recipes.is_empty() {
+            continue;
+        }
 
+        let label = category_label(dir);
+        match categories.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, existing)) => {
+                existing.append(&mut recipes);
+                existing.sort();
+                existing.dedup();
+            }
+            None => {
+                recipes.sort();
+                categories.push((label, recipes));
+            }
+        }
+    }
+    categories
+}
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
-            ui.vertical_centered(|ui| {
-                ui.heading("Create Weekly Recipes Screen");
+// Scales a dish so there's enough for a crowd: returns the scale factor and the
+// scaled recipe. Ranges like "4-6" use the lower bound so nobody goes without.
+fn portions_for(recipe: &Recipe, guests: u32, per_guest: f64, decimal_comma: bool) -> Result<(f64, Recipe), String> {
+    let range = parse_servings(&recipe.servings)
+        .ok_or_else(|| format!("Can't work out how many servings \"{}\" makes", recipe.servings))?;
+    let factor = guests as f64 * per_guest / range.min as f64;
+    Ok((factor, recipe.scale(factor as f32, decimal_comma)))
+}
 
-                let days = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+const RECIPES_ROOT: &str = "recipes";
 
-                for (i, day) in days.iter().enumerate() {
-                    ui.horizontal(|ui| {
-                        ui.add_space(ui.available_width() / 4.0);
-                        ui.label(*day);
-                        egui::ComboBox::from_id_source(format!("recipe_combo_{}", i))
-                            .selected_text(&self.selected_recipes[i])
-                            .show_ui(ui, |ui| {
-                                for recipe in &self.recipes {
-                                    ui.selectable_value(&mut self.selected_recipes[i], recipe.clone(), recipe);
-                                }
-                            });
-                        if ui.button("🎲").clicked() {
-                            self.randomize_single(i);
-                        }
-                    });
-                }
-                
-                ui.add_space(10.0);
+// Every folder under recipes/ is a category, so adding recipes/breakfast is enough
+// for the app to pick it up. Hidden folders such as .history are skipped.
+fn recipe_directories() -> Vec<String> {
+    let entries = match fs::read_dir(RECIPES_ROOT) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter(|path| !path.file_name().is_none_or(|name| name.to_string_lossy().starts_with('.')))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    dirs.sort();
+    dirs
+}
 
-                ui.vertical_centered(|ui| {
-                    if ui.button("Randomize All").clicked() {
-                        self.randomize_all();
-                    }
-                });
+// Finds the file for a recipe name in whichever category folder holds it
+fn find_recipe_path(recipe_name: &str, extension: &str) -> Option<PathBuf> {
+    recipe_directories()
+        .iter()
+        .map(|dir| Path::new(dir).join(recipe_file_name(recipe_name, extension)))
+        .find(|path| path.exists())
+}
 
-                ui.vertical_centered(|ui| {
-                    if ui.button("Process Selected Recipes").clicked() {
-                        self.clear_processing_message();
-                        match self.process_selected_recipes() {
-                            Ok(_) => self.processing_message = "Processing completed successfully.".to_string(),
-                            Err(e) => self.processing_message = format!("Error during processing: {}", e),
-                        }
-                    }
-                });
+// True when none of the category folders holds a single recipe file
+fn library_is_empty(dirs: &[String], extension: &str) -> bool {
+    !dirs.iter().any(|dir| {
+        fs::read_dir(dir).is_ok_and(|entries| entries.flatten().any(|entry| is_recipe_file(&entry.path(), extension)))
+    })
+}
 
-                ui.vertical_centered(|ui| {
-                    if ui.button("Back to Main Screen").clicked() {
-                        self.clear_processing_message();
-                        self.wants_to_exit = true;
-                    }
-                });
-                ui.vertical_centered(|ui|{
-                    if !self.processing_message.is_empty() {
-                        ui.colored_label(
-                            if self.processing_message.starts_with("Error") { egui::Color32::RED } else { egui::Color32::GREEN},
-                            &self.processing_message
-                        );
+// Parses every recipe in the collection, skipping files that fail to load
+fn load_all_recipes(extension: &str) -> Vec<Recipe> {
+    let mut recipes = Vec::new();
+    for dir in &recipe_directories() {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if is_recipe_file(&path, extension) {
+                    if let Ok(recipe) = parse_recipe_cached(&path) {
+                        recipes.push(recipe);
                     }
-                });
-
-                // Update text color based on dark mode
-                if is_dark_mode {
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
-                } else {
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
                 }
-            });
-        });
+            }
+        }
+    }
+    recipes
+}
 
-        None
+// Parses a single quantity token such as "2", "1/2", "0.5" or, when the user
+// writes decimals with a comma, "0,5". Ingredients are one per line in .rec
+// files, so a comma inside a number can't be mistaken for a list separator there.
+fn parse_quantity(token: &str, decimal_comma: bool) -> Option<f32> {
+    let token = token.trim();
+    if let Some((numerator, denominator)) = token.split_once('/') {
+        let numerator = numerator.trim().parse::<f32>().ok()?;
+        let denominator = denominator.trim().parse::<f32>().ok()?;
+        if denominator == 0.0 {
+            return None;
+        }
+        return Some(numerator / denominator);
+    }
+    match token {
+        "½" => return Some(0.5),
+        "¼" => return Some(0.25),
+        "¾" => return Some(0.75),
+        "⅓" => return Some(1.0 / 3.0),
+        "⅔" => return Some(2.0 / 3.0),
+        _ => {}
+    }
+    if decimal_comma {
+        token.replace(',', ".").parse::<f32>().ok()
+    } else {
+        token.parse::<f32>().ok()
     }
+}
 
-    fn wants_to_exit(&self) -> bool {
-        self.wants_to_exit
+// Splits the leading quantity off an ingredient line, summing mixed numbers
+// like "1 1/2". Returns the quantity (if any) and the rest of the line.
+fn split_quantity(line: &str, decimal_comma: bool) -> (Option<f32>, &str) {
+    let mut rest = line.trim_start();
+    let mut total: Option<f32> = None;
+    while let Some(token) = rest.split_whitespace().next() {
+        match parse_quantity(token, decimal_comma) {
+            Some(value) => {
+                total = Some(total.unwrap_or(0.0) + value);
+                rest = rest[token.len()..].trim_start();
+            }
+            None => break,
+        }
     }
+    (total, rest)
 }
 
-struct CreateRecipeManuallyScreen {
-    wants_to_exit: bool,
-    title: String,
-    from: String,
-    servings: String,
-    prep_time: String,
-    cook_time: String,
-    total_time: String,
-    ingredients: String,
-    instructions: Vec<String>,
-    notes: Vec<String>,
-    processing_message: String,
+// Formats a scaled quantity without trailing zeros, e.g. 1.5 or 2
+fn format_quantity(quantity: f32) -> String {
+    let rounded = (quantity * 100.0).round() / 100.0;
+    let text = format!("{:.2}", rounded);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
 }
 
-impl Default for CreateRecipeManuallyScreen {
-    fn default() -> Self {
-        Self {
-            wants_to_exit: false,
-            title: String::new(),
-            from: String::new(),
-            servings: String::new(),
-            prep_time: String::new(),
-            cook_time: String::new(),
-            total_time: String::new(),
-            ingredients: String::new(),
-            instructions: vec![String::new()],
-            notes: vec![String::new()],
-            processing_message: String::new(),
-        }
+// Writes an ingredient amount the way a cook would, e.g. 0.75 -> "3/4" and
+// 1.5 -> "1 1/2", falling back to decimals for amounts like 0.6
+fn format_amount(quantity: f32) -> String {
+    const FRACTIONS: [(f32, &str); 9] = [
+        (0.125, "1/8"), (0.25, "1/4"), (1.0 / 3.0, "1/3"), (0.375, "3/8"), (0.5, "1/2"), (0.625, "5/8"), (2.0 / 3.0, "2/3"),
+        (0.75, "3/4"), (0.875, "7/8"),
+    ];
+    let whole = quantity.trunc();
+    let fraction = quantity - whole;
+    if fraction.abs() < 0.01 {
+        return format_quantity(whole);
+    }
+    match FRACTIONS.iter().find(|(value, _)| (fraction - value).abs() < 0.01) {
+        Some((_, label)) if whole == 0.0 => label.to_string(),
+        Some((_, label)) => format!("{} {}", whole, label),
+        None => format_quantity(quantity),
     }
 }
 
-impl Screen for CreateRecipeManuallyScreen {
-    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
-        ctx.set_pixels_per_point(3.0);
+// Swaps the decimal point for a comma when the user writes decimals that way
+fn localize_decimal(text: String, decimal_comma: bool) -> String {
+    if decimal_comma {
+        text.replace('.', ",")
+    } else {
+        text
+    }
+}
 
-        let is_dark_mode = app_state.is_dark_mode;
-        let background_color = if is_dark_mode {
-            egui::Color32::from_rgb(30, 30, 30)
-        } else {
-            egui::Color32::WHITE
-        };
+// Multiplies the leading quantity of an ingredient line, leaving lines without one
+// untouched. At factor 1 the line is kept exactly as written, so "½" or "0.6" isn't
+// rewritten just by viewing the recipe.
+fn scale_ingredient_line(line: &str, factor: f32, decimal_comma: bool) -> String {
+    if factor == 1.0 {
+        return line.to_string();
+    }
+    match split_quantity(line, decimal_comma) {
+        (Some(quantity), rest) => format!("{} {}", localize_decimal(format_amount(quantity * factor), decimal_comma), rest),
+        (None, _) => line.to_string(),
+    }
+}
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
-            
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                ui.vertical_centered(|ui| {
-                    ui.heading("Create Recipe Manually");
+impl Recipe {
+    // The ingredients split into their groups, in order. Ungrouped ingredients come
+    // first under an empty name; a recipe without groups is a single unnamed group.
+    fn grouped_ingredients(&self) -> Vec<(String, Vec<&Ingredient>)> {
+        let len = self.ingreds.len();
+        let mut groups = Vec::new();
+        let first_start = self.ingredient_groups.first().map_or(len, |(start, _)| (*start).min(len));
+        if first_start > 0 || self.ingredient_groups.is_empty() {
+            groups.push((String::new(), self.ingreds[..first_start].iter().collect()));
+        }
+        for (idx, (start, name)) in self.ingredient_groups.iter().enumerate() {
+            let start = (*start).min(len);
+            let end = self.ingredient_groups.get(idx + 1).map_or(len, |(next, _)| (*next).clamp(start, len));
+            groups.push((name.clone(), self.ingreds[start..end].iter().collect()));
+        }
+        groups
+    }
 
-                    ui.add_space(10.0);
+    // Multiplies every ingredient quantity; lines without one ("salt to taste") stay as
+    // written. Quantities are read from the line itself so "1,5" scales too when the
+    // user writes decimals with a comma.
+    fn scale(&self, factor: f32, decimal_comma: bool) -> Recipe {
+        let mut scaled = self.clone();
+        scaled.ingreds = self
+            .ingreds
+            .iter()
+            .map(|ingredient| parse_ingredient(&scale_ingredient_line(&ingredient.text, factor, decimal_comma), decimal_comma))
+            .collect();
+        scaled
+    }
 
-                    ui.horizontal(|ui| {
-                        ui.label("Title:");
-                        ui.text_edit_singleline(&mut self.title);
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label("From:");
-                        ui.text_edit_singleline(&mut self.from);
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label("Servings:");
-                        ui.text_edit_singleline(&mut self.servings);
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label("Prep Time:");
-                        ui.text_edit_singleline(&mut self.prep_time);
-                    });
+    // The recipe as the view shows it at this factor, for the PDF and the Markdown
+    // export: scaled amounts, with the servings noting the scaled yield
+    fn scaled_for_output(&self, factor: f32, decimal_comma: bool) -> Recipe {
+        let mut scaled = self.scale(factor, decimal_comma);
+        match describe_scaled_servings(&self.servings, factor) {
+            Some(phrase) if factor != 1.0 => scaled.servings = format!("{} ({})", self.servings, phrase),
+            _ => {}
+        }
+        scaled
+    }
+}
 
-                    ui.horizontal(|ui| {
-                        ui.label("Cook Time:");
-                        ui.text_edit_singleline(&mut self.cook_time);
-                    });
+// An ingredient line broken into amount, unit and name, plus any preparation note
+// given in parentheses, e.g. "2 cups flour (sifted)" -> 2, "cups", "flour", "sifted".
+// The original line is kept so output shows exactly what the recipe says.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "IngredientJson")]
+struct Ingredient {
+    text: String,
+    quantity: Option<f32>,
+    unit: Option<String>,
+    name: String,
+    prep: Option<String>,
+}
 
-                    ui.horizontal(|ui| {
-                        ui.label("Total Time:");
-                        ui.text_edit_singleline(&mut self.total_time);
-                    });
+// Imported JSON may list ingredients as plain strings or as the objects /api/recipes
+// returns; either way only the text is kept and re-parsed
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IngredientJson {
+    Text(String),
+    Parsed { text: String },
+}
 
-                    ui.horizontal(|ui| {
-                        ui.label("Ingredients (comma separated):");
-                        ui.text_edit_multiline(&mut self.ingredients);
-                    });
-                    ui.label("Instructions:");
-                    let mut updates = Vec::new();
-                    let mut instruction_to_remove: Option<usize> = None;
-                    let mut instruction_to_add = false;
+impl From<IngredientJson> for Ingredient {
+    fn from(json: IngredientJson) -> Self {
+        match json {
+            IngredientJson::Text(text) | IngredientJson::Parsed { text } => parse_ingredient(&text, false),
+        }
+    }
+}
 
-                    // Render instructions
-                    for (idx, instruction) in self.instructions.iter().enumerate() {
-                        ui.horizontal(|ui| {
-                            ui.label(format!("{}.", idx + 1));
-                            let mut instruction_text = instruction.clone();
-                            if ui.text_edit_singleline(&mut instruction_text).changed() {
-                                updates.push((idx, instruction_text));
-                            }
-                            if ui.button("-").clicked() && self.instructions.len() > 1 {
-                                instruction_to_remove = Some(idx);
-                            }
-                        });
-                    }
+impl fmt::Display for Ingredient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
 
-                    // Add new instruction button
-                    if ui.button("Add Instruction").clicked() {
-                        instruction_to_add = true;
-                    }
+// Handles "2 cups flour", "1/2 tsp salt", "1 1/2 cups milk" and "salt to taste",
+// plus "1,5 cups milk" when the user writes decimals with a comma
+fn parse_ingredient(line: &str, decimal_comma: bool) -> Ingredient {
+    let text = line.trim().to_string();
+    let (without_notes, prep) = split_prep_note(&text);
+    let (quantity, rest) = split_quantity(&without_notes, decimal_comma);
+
+    let lowered = rest.trim().to_lowercase();
+    let mut words: Vec<&str> = lowered.split_whitespace().collect();
+    let mut unit = None;
+    if quantity.is_some() {
+        if let Some(first) = words.first() {
+            let candidate = first.trim_end_matches('.');
+            if is_measurement_unit(candidate) {
+                unit = Some(candidate.to_string());
+                words.remove(0);
+            }
+        }
+    }
 
-                    // Apply changes
-                    for (idx, instruction_text) in updates {
-                        self.instructions[idx] = instruction_text;
-                    }
+    Ingredient {
+        name: ingredient_name(&words.join(" ")),
+        text,
+        quantity,
+        unit,
+        prep,
+    }
+}
 
-                    if let Some(idx) = instruction_to_remove {
-                        self.instructions.remove(idx);
-                    }
+// Pulls every "(...)" note out of a line, returning the rest and the notes joined by ", "
+fn split_prep_note(line: &str) -> (String, Option<String>) {
+    let mut text = String::new();
+    let mut notes = Vec::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('(') {
+        let close = match rest[open..].find(')') {
+            Some(close) => open + close,
+            None => break,
+        };
+        text.push_str(&rest[..open]);
+        let note = rest[open + 1..close].trim();
+        if !note.is_empty() {
+            notes.push(note.to_string());
+        }
+        rest = &rest[close + 1..];
+    }
+    text.push_str(rest);
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let prep = if notes.is_empty() { None } else { Some(notes.join(", ")) };
+    (text, prep)
+}
 
-                    if instruction_to_add {
-                        self.instructions.push(String::new());
-                    }
+// The unit quantities are converted into before they're added together. Volumes are
+// counted in teaspoons and weights in ounces; anything else (cans, pinches, ...) only
+// adds up with the same unit.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum CanonicalUnit {
+    Teaspoon,
+    Ounce,
+    Other(String),
+}
 
-                    ui.add_space(10.0);
+// How many teaspoons / ounces one of each unit is. Together with COUNT_UNITS these are
+// every unit parse_ingredient recognises, in the singular; unit_key maps plurals and
+// abbreviations with a "." onto them.
+const VOLUME_UNITS: [(&str, f32); 15] = [
+    ("tsp", 1.0), ("teaspoon", 1.0),
+    ("tbsp", 3.0), ("tbs", 3.0), ("tablespoon", 3.0),
+    ("cup", 48.0), ("c", 48.0),
+    ("ml", 0.202884), ("milliliter", 0.202884), ("millilitre", 0.202884),
+    ("l", 202.884), ("liter", 202.884), ("litre", 202.884),
+    ("pint", 96.0), ("quart", 192.0),
+];
+const WEIGHT_UNITS: [(&str, f32); 11] = [
+    ("oz", 1.0), ("ounce", 1.0),
+    ("lb", 16.0), ("lbs", 16.0), ("pound", 16.0),
+    ("g", 0.035274), ("gram", 0.035274), ("gramme", 0.035274),
+    ("kg", 35.274), ("kilogram", 35.274), ("kilogramme", 35.274),
+];
+// Units with no fixed size, which only add up with the same unit
+const COUNT_UNITS: [&str; 3] = ["can", "pinch", "dash"];
+
+fn is_listed_unit(unit: &str) -> bool {
+    VOLUME_UNITS.iter().chain(WEIGHT_UNITS.iter()).any(|(name, _)| *name == unit) || COUNT_UNITS.contains(&unit)
+}
 
-                    ui.label("Notes:");
-                    let mut note_updates = Vec::new();
-                    let mut note_to_remove: Option<usize> = None;
-                    let mut note_to_add = false;
+// Lowercases a unit and drops a trailing "." or plural "s"/"es" ("Cups." -> "cup",
+// "pinches" -> "pinch"), leaving abbreviations that already end in "s" ("tbs", "lbs")
+// alone. Unlisted words just lose a trailing "s".
+fn unit_key(unit: &str) -> String {
+    let unit = unit.trim().trim_end_matches('.').to_lowercase();
+    if is_listed_unit(&unit) {
+        return unit;
+    }
+    for suffix in ["s", "es"] {
+        if let Some(singular) = unit.strip_suffix(suffix) {
+            if is_listed_unit(singular) {
+                return singular.to_string();
+            }
+        }
+    }
+    match unit.strip_suffix('s') {
+        Some(singular) if singular.len() > 1 => singular.to_string(),
+        _ => unit,
+    }
+}
 
-                    // Render notes
-                    for (idx, note) in self.notes.iter().enumerate() {
-                        ui.horizontal(|ui| {
-                            ui.label(format!("{}.", idx + 1));
-                            let mut note_text = note.clone();
-                            if ui.text_edit_singleline(&mut note_text).changed() {
-                                note_updates.push((idx, note_text));
-                            }
-                            if ui.button("-").clicked() && self.notes.len() > 1 {
-                                note_to_remove = Some(idx);
-                            }
-                        });
-                    }
+// Whether a word after the amount is a unit, e.g. "cups", "Tbsp." or "grams"
+fn is_measurement_unit(word: &str) -> bool {
+    is_listed_unit(&unit_key(word))
+}
 
-                    // Add new note button
-                    if ui.button("Add Note").clicked() {
-                        note_to_add = true;
-                    }
+fn normalize_unit(unit: &str) -> CanonicalUnit {
+    let key = unit_key(unit);
+    if VOLUME_UNITS.iter().any(|(name, _)| *name == key) {
+        CanonicalUnit::Teaspoon
+    } else if WEIGHT_UNITS.iter().any(|(name, _)| *name == key) {
+        CanonicalUnit::Ounce
+    } else {
+        CanonicalUnit::Other(key)
+    }
+}
 
-                    // Apply changes to notes
-                    for (idx, note_text) in note_updates {
-                        self.notes[idx] = note_text;
-                    }
+fn unit_factor(table: &[(&str, f32)], unit: &str) -> Option<f32> {
+    let key = unit_key(unit);
+    table.iter().find(|(name, _)| *name == key).map(|(_, factor)| *factor)
+}
 
-                    if let Some(idx) = note_to_remove {
-                        self.notes.remove(idx);
-                    }
+// Converts between two volume units or two weight units, e.g. (1, "cup", "ml") -> 236.6.
+// Returns None if either unit is unknown or one is a volume and the other a weight.
+fn convert_unit(quantity: f32, from: &str, to: &str) -> Option<f32> {
+    [&VOLUME_UNITS[..], &WEIGHT_UNITS[..]]
+        .iter()
+        .find_map(|table| match (unit_factor(table, from), unit_factor(table, to)) {
+            (Some(from_factor), Some(to_factor)) => Some(quantity * from_factor / to_factor),
+            _ => None,
+        })
+}
 
-                    if note_to_add {
-                        self.notes.push(String::new());
-                    }
+// Converts an amount into its canonical unit, e.g. (2, "tbsp") -> (6, Teaspoon)
+fn to_canonical(quantity: f32, unit: &str) -> (f32, CanonicalUnit) {
+    let canonical = normalize_unit(unit);
+    let amount = match &canonical {
+        CanonicalUnit::Teaspoon => convert_unit(quantity, unit, "tsp"),
+        CanonicalUnit::Ounce => convert_unit(quantity, unit, "oz"),
+        CanonicalUnit::Other(_) => None,
+    };
+    (amount.unwrap_or(quantity), canonical)
+}
 
-                    ui.add_space(10.0);
+// Writes a canonical amount back in the largest unit that reads cleanly, as separate
+// amount and unit: 6 tsp -> ("2", "tbsp"), 24 tsp -> ("1/2", "cup"), 32 oz -> ("2", "lb").
+// In metric, volumes become whole ml (or l from a litre up) and weights g (or kg), never
+// less than 1. Either way the amount is rounded per the shopping list preference.
+fn canonical_parts(quantity: f32, unit: &CanonicalUnit, settings: &Settings) -> (String, String) {
+    let rounding = settings.shopping_rounding;
+    let round = |amount: f32| round_quantity(amount as f64, rounding) as f32;
+    let metric = match (settings.unit_system, unit) {
+        (UnitSystem::Metric, CanonicalUnit::Teaspoon) => convert_unit(quantity, "tsp", "ml").map(|amount| (amount, "ml", "l")),
+        (UnitSystem::Metric, CanonicalUnit::Ounce) => convert_unit(quantity, "oz", "g").map(|amount| (amount, "g", "kg")),
+        _ => None,
+    };
+    if let Some((amount, small, large)) = metric {
+        let (amount, unit) = if amount >= 1000.0 {
+            (format_quantity(round(amount / 1000.0)), large)
+        } else {
+            (format_quantity(round(amount).round().max(1.0)), small)
+        };
+        return (localize_decimal(amount, settings.decimal_comma), unit.to_string());
+    }
 
-                    if ui.button("Save Recipe").clicked() {
-                        if let Err(e) = self.save_recipe() {
-                            self.processing_message = format!("Error saving recipe: {}", e);
-                        } else {
-                            self.processing_message = "Recipe saved successfully".to_string();
-                        }
-                    }
+    let reads_cleanly = |amount: f32| !format_amount(round(amount)).contains('.');
+    let (amount, unit) = match unit {
+        CanonicalUnit::Teaspoon if quantity >= 12.0 && reads_cleanly(quantity / 48.0) => (quantity / 48.0, "cup"),
+        CanonicalUnit::Teaspoon if quantity >= 3.0 && ((quantity / 3.0) * 2.0).fract().abs() < 0.01 => (quantity / 3.0, "tbsp"),
+        CanonicalUnit::Teaspoon => (quantity, "tsp"),
+        CanonicalUnit::Ounce if quantity >= 16.0 && reads_cleanly(quantity / 16.0) => (quantity / 16.0, "lb"),
+        CanonicalUnit::Ounce => (quantity, "oz"),
+        CanonicalUnit::Other(unit) => (quantity, unit.as_str()),
+    };
+    (localize_decimal(format_amount(round(amount)), settings.decimal_comma), unit.to_string())
+}
 
-                    ui.add_space(10.0);
+// One line of the aggregated shopping list, along with its parts for the CSV export
+// and the recipes that called for it
+struct ShoppingItem {
+    line: String,
+    quantity: String,
+    unit: String,
+    name: String,
+    recipes: Vec<String>,
+}
 
-                    if ui.button("Back to Main Screen").clicked() {
-                        self.wants_to_exit = true;
-                    }
+// Merges the week's ingredient lines into one shopping list: "2 eggs" and "4 Eggs"
+// become "6 eggs", "1 tbsp" and "1 tsp" of the same thing add up in teaspoons.
+// Amounts that can't be added (cups vs. ounces, or a unit vs. none) stay on separate
+// lines, and lines without any amount ("salt to taste") are listed once each.
+// Summed amounts are rounded per the shopping list preference and written in the
+// chosen unit system. Each line comes with the name of the recipe it's from.
+fn aggregate_ingredients(lines: &[(String, String)], settings: &Settings) -> Vec<ShoppingItem> {
+    struct Entry {
+        name: String,
+        unit: Option<CanonicalUnit>,
+        total: Option<f32>,
+        preps: Vec<String>,
+        lines: Vec<Ingredient>,
+        recipes: Vec<String>,
+    }
 
-                    if !self.processing_message.is_empty() {
-                        ui.colored_label(
-                            if self.processing_message.starts_with("Error") { egui::Color32::RED } else { egui::Color32::GREEN },
-                            &self.processing_message
-                        );
-                    }
+    let mut entries: Vec<Entry> = Vec::new();
+    for (line, recipe_name) in lines {
+        let ingredient = parse_ingredient(line, settings.decimal_comma);
+        if ingredient.name.is_empty() {
+            continue;
+        }
+        let (total, unit) = match (ingredient.quantity, &ingredient.unit) {
+            (Some(quantity), Some(unit)) => {
+                let (amount, canonical) = to_canonical(quantity, unit);
+                (Some(amount), Some(canonical))
+            }
+            (quantity, _) => (quantity, None),
+        };
+        let existing = entries
+            .iter()
+            .position(|entry| entry.name == ingredient.name && entry.unit == unit && entry.total.is_some() == total.is_some());
+        let idx = match existing {
+            Some(idx) => idx,
+            None => {
+                entries.push(Entry {
+                    name: ingredient.name.clone(),
+                    unit,
+                    total: None,
+                    preps: Vec::new(),
+                    lines: Vec::new(),
+                    recipes: Vec::new(),
                 });
-            });
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[idx];
+        if let Some(amount) = total {
+            entry.total = Some(entry.total.unwrap_or(0.0) + amount);
+        }
+        if let Some(prep) = &ingredient.prep {
+            if !entry.preps.contains(prep) {
+                entry.preps.push(prep.clone());
+            }
+        }
+        if !entry.recipes.contains(recipe_name) {
+            entry.recipes.push(recipe_name.clone());
+        }
+        entry.lines.push(ingredient);
+    }
 
-            if is_dark_mode {
-                ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
-            } else {
-                ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+    entries
+        .into_iter()
+        .map(|entry| {
+            if entry.lines.len() == 1 || entry.total.is_none() {
+                let first = &entry.lines[0];
+                return ShoppingItem {
+                    line: first.text.clone(),
+                    quantity: first.quantity.map(|quantity| localize_decimal(format_amount(quantity), settings.decimal_comma)).unwrap_or_default(),
+                    unit: first.unit.clone().unwrap_or_default(),
+                    name: match &first.prep {
+                        Some(prep) => format!("{} ({})", first.name, prep),
+                        None => first.name.clone(),
+                    },
+                    recipes: entry.recipes,
+                };
             }
-        });
+            let amount = entry.total.unwrap_or(0.0);
+            let (quantity, unit) = match &entry.unit {
+                Some(unit) => canonical_parts(amount, unit, settings),
+                None => {
+                    let amount = round_quantity(amount as f64, settings.shopping_rounding) as f32;
+                    (localize_decimal(format_amount(amount), settings.decimal_comma), String::new())
+                }
+            };
+            let mut name = entry.name;
+            if !entry.preps.is_empty() {
+                name.push_str(&format!(" ({})", entry.preps.join(", ")));
+            }
+            let line = if unit.is_empty() {
+                format!("{} {}", quantity, name)
+            } else {
+                format!("{} {} {}", quantity, unit, name)
+            };
+            ShoppingItem { line, quantity, unit, name, recipes: entry.recipes }
+        })
+        .collect()
+}
 
-        None
+// Quotes a CSV field when it holds a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
     }
+}
 
-    fn wants_to_exit(&self) -> bool {
-        self.wants_to_exit
+// The shopping list as CSV for spreadsheet-based shopping apps
+fn shopping_list_csv(items: &[ShoppingItem]) -> String {
+    let mut csv = String::from("quantity,unit,name,recipes\n");
+    for item in items {
+        let fields = [&item.quantity, &item.unit, &item.name, &item.recipes.join("; ")];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
     }
+    csv
 }
 
-impl CreateRecipeManuallyScreen {
-    fn save_recipe(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let file_name = format!("recipes/generated/{}.rec", self.title.replace(" ", "_"));
-        let mut file = File::create(file_name)?;
-
-        writeln!(file, "Title\t{}", self.title)?;
-        writeln!(file, "From\t{}", self.from)?;
-        writeln!(file, "Servings\t{}", self.servings)?;
-        writeln!(file, "Prep Time\t{}", self.prep_time)?;
-        writeln!(file, "Cook Time\t{}", self.cook_time)?;
-        writeln!(file, "Total Time\t{}", self.total_time)?;
-        writeln!(file, "Ingredients Start")?;
-        for ingredient in self.ingredients.split(',') {
-            writeln!(file, "{}", ingredient.trim())?;
-        }
-        writeln!(file, "Ingredients End")?;
-        writeln!(file, "Instructions Start")?;
-        for (idx, instruction) in self.instructions.iter().enumerate() { 
-            writeln!(file, "{}. {}", idx + 1, instruction)?;
-        }
-        writeln!(file, "Instructions End")?;
-        writeln!(file, "Notes Start")?;
-        for note in &self.notes {
-            writeln!(file, "{}", note)?;
-        }
-        writeln!(file, "Notes End")?;
-
-        Ok(())
+// Reduces an ingredient line like "2 lbs ground beef." to "ground beef" for grouping
+fn ingredient_name(line: &str) -> String {
+    let lowered = line.trim().to_lowercase();
+    let words: Vec<&str> = lowered.split_whitespace().collect();
+    let mut start = 0;
+    while start < words.len() && words[start].starts_with(|c: char| c.is_ascii_digit()) {
+        start += 1;
     }
+    if start > 0 && start < words.len() && is_measurement_unit(words[start]) {
+        start += 1;
+    }
+    words[start..].join(" ").trim_end_matches('.').to_string()
 }
 
-struct RecipeSelectionScreen {
-    selected_recipe: Option<String>,
-    recipes: Vec<String>,
-    wants_to_exit: bool,
-    processing_message: String,
-    pdf_generated: bool,
-    current_pdf_path: Option<PathBuf>,
+// Grocery aisles in the order the web shopping list shows them, with the words that
+// place an ingredient in each. Anything unmatched goes under "Other".
+const GROCERY_AISLES: [(&str, &[&str]); 7] = [
+    ("Produce", &[
+        "apple", "avocado", "banana", "basil", "bell pepper", "broccoli", "cabbage", "carrot", "celery",
+        "cilantro", "corn", "cucumber", "garlic", "ginger", "green beans", "kale", "lemon", "lettuce",
+        "lime", "mushroom", "onion", "parsley", "pepper", "potato", "scallion", "spinach", "squash",
+        "tomato", "zucchini",
+    ]),
+    ("Meat & Seafood", &["bacon", "beef", "chicken", "fish", "ham", "pork", "salmon", "sausage", "shrimp", "turkey"]),
+    ("Dairy & Eggs", &["butter", "cheese", "cream", "egg", "milk", "mozzarella", "parmesan", "sour cream", "yogurt"]),
+    ("Bakery", &["bagel", "bread", "bun", "pita", "roll", "tortilla"]),
+    ("Pantry", &[
+        "baking powder", "baking soda", "beans", "beef broth", "breadcrumbs", "broth", "chicken broth",
+        "chicken stock", "cornstarch", "flour", "honey", "ketchup", "mustard", "noodles", "oats", "oil",
+        "pasta", "peanut butter", "rice", "soy sauce", "stock", "sugar", "tomato paste", "tomato sauce",
+        "vinegar",
+    ]),
+    ("Spices", &[
+        "black pepper", "chili powder", "cinnamon", "cumin", "garlic powder", "nutmeg", "onion powder",
+        "oregano", "paprika", "salt", "thyme", "vanilla",
+    ]),
+    ("Frozen", &["frozen", "ice cream"]),
+];
+
+// The aisle whose keyword best matches the ingredient. The longest matching keyword
+// wins, so "green beans" is produce rather than pantry and "garlic powder" is a spice.
+fn grocery_aisle(line: &str) -> &'static str {
+    let name = ingredient_name(line);
+    GROCERY_AISLES
+        .iter()
+        .flat_map(|(aisle, keywords)| keywords.iter().map(move |keyword| (*aisle, *keyword)))
+        .filter(|(_, keyword)| name.contains(keyword))
+        .max_by_key(|(_, keyword)| keyword.len())
+        .map_or("Other", |(aisle, _)| aisle)
 }
 
-impl Default for RecipeSelectionScreen {
-    fn default() -> Self {
-        Self {
-            selected_recipe: None,
-            recipes: Vec::new(),
-            wants_to_exit: false,
-            processing_message: String::new(),
-            pdf_generated: false,
-            current_pdf_path: None,
+// Counts how many recipes use each distinct ingredient, most used first
+fn ingredient_frequencies(recipes: &[Recipe]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for recipe in recipes {
+        let names: HashSet<String> = recipe.ingreds.iter().map(|ingredient| ingredient.name.clone()).collect();
+        for name in names {
+            if !name.is_empty() {
+                *counts.entry(name).or_insert(0) += 1;
+            }
         }
     }
+    let mut frequencies: Vec<(String, usize)> = counts.into_iter().collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequencies
 }
 
-impl RecipeSelectionScreen {
-    fn load_recipes(&mut self) {
-        self.recipes.clear();
-        let directories = ["recipes/desert", "recipes/dinner", "recipes/sides"];
-        for dir in &directories {
-            if let Ok(entries) = fs::read_dir(dir) {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let path = entry.path();
-                        if path.is_file() && path.extension().map_or(false, |ext| ext == "rec") {
-                            if let Some(file_name) = path.file_stem() {
-                                self.recipes.push(file_name.to_string_lossy().to_string());
-                            }
-                        }
-                    }
-                }
-            }
+struct Source {
+    name: Option<String>,
+    url: Option<String>,
+}
+
+// Splits a "From" value such as "Grandma's Cookbook (https://...)" into its attribution and link
+fn parse_source(from: &str) -> Source {
+    let start = from.find("https://").or_else(|| from.find("http://"));
+    let (url, remainder) = match start {
+        Some(start) => {
+            let length = from[start..]
+                .find(|c: char| c.is_whitespace() || c == ')' || c == ']' || c == '>')
+                .unwrap_or(from.len() - start);
+            let url = from[start..start + length].to_string();
+            let remainder = format!("{}{}", &from[..start], &from[start + length..]);
+            (Some(url), remainder)
         }
-        self.recipes.sort();
+        None => (None, from.to_string()),
+    };
+    let name = remainder
+        .replace("()", "")
+        .replace("[]", "")
+        .replace("<>", "")
+        .trim()
+        .trim_end_matches(|c: char| c == '-' || c == ':' || c == '|' || c == '(' || c.is_whitespace())
+        .trim()
+        .to_string();
+
+    Source {
+        name: if name.is_empty() { None } else { Some(name) },
+        url,
     }
+}
 
-    fn get_recipe_path(&self, recipe_name: &str) -> PathBuf {
-        let directories = ["recipes/desert", "recipes/dinner", "recipes/sides"];
-        for dir in &directories {
-            let path = Path::new(dir).join(format!("{}.rec", recipe_name));
-            if path.exists() {
-                return path;
-            }
+const PDF_FONTS_DIR: &str = "fonts";
+const DEFAULT_PDF_FONT: &str = "recipe.ttf";
+
+// File names of the .ttf fonts available to pick for recipe PDFs
+fn list_pdf_fonts() -> Vec<String> {
+    let mut fonts: Vec<String> = fs::read_dir(PDF_FONTS_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ttf")))
+                .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    fonts.sort();
+    fonts
+}
+
+// Loads the font used for PDF text. A TTF is embedded when given and valid;
+// if that fails the built-in Helvetica is used so generation still succeeds.
+fn load_pdf_font(doc: &PdfDocumentReference, ttf_path: Option<&Path>) -> Result<IndirectFontRef, String> {
+    if let Some(path) = ttf_path {
+        let embedded = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| doc.add_external_font(file).map_err(|e| e.to_string()));
+        match embedded {
+            Ok(font) => return Ok(font),
+            Err(e) => eprintln!("Failed to embed font {:?}, falling back to Helvetica: {}", path, e),
         }
-        PathBuf::new() // Return an empty path if not found
     }
+    doc.add_builtin_font(BuiltinFont::Helvetica)
+        .map_err(|e| format!("Failed to load PDF font: {}", e))
+}
 
-    fn print_pdf(&self, pdf_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        #[cfg(target_os = "windows")]
-        {
-            use std::os::windows::process::CommandExt;
-            const CREATE_NO_WINDOW: u32 = 0x08000000;
-            Command::new("cmd")
-                .args(&["/C", "start", "", "/B", pdf_path.to_str().unwrap()])
-                .creation_flags(CREATE_NO_WINDOW)
-                .spawn()?;
-        }
-        #[cfg(target_os = "macos")]
-        {
-            Command::new("lpr")
-                .arg(pdf_path)
-                .spawn()?;
-        }
-        #[cfg(target_os = "linux")]
-        {
-            Command::new("lpr")
-                .arg(pdf_path)
-                .spawn()?;
+// Helvetica advance widths for ASCII 32 (space) to 126 (~), in thousandths of an em,
+// from the standard Helvetica AFM
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+const POINTS_TO_MM: f32 = 25.4 / 72.0;
+
+// Width of one character as a fraction of the font size, from the embedded TTF when
+// there is one and Helvetica's metrics otherwise
+fn char_advance(c: char, face: Option<&ttf_parser::Face>) -> f32 {
+    if let Some(face) = face {
+        if let Some(advance) = face.glyph_index(c).and_then(|glyph| face.glyph_hor_advance(glyph)) {
+            return advance as f32 / face.units_per_em() as f32;
         }
-        Ok(())
     }
+    let width = match c {
+        ' '..='~' => HELVETICA_WIDTHS[c as usize - 32],
+        '•' => 350,
+        _ => 556,
+    };
+    width as f32 / 1000.0
 }
 
-impl Screen for RecipeSelectionScreen {
-    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
-        ctx.set_pixels_per_point(3.0);
+// How wide a string prints, in mm, at the given font size in points
+fn text_width_mm(text: &str, font_size: f32, face: Option<&ttf_parser::Face>) -> f32 {
+    text.chars().map(|c| char_advance(c, face)).sum::<f32>() * font_size * POINTS_TO_MM
+}
 
-        let is_dark_mode = app_state.is_dark_mode;
-        let background_color = if is_dark_mode {
-            egui::Color32::from_rgb(30, 30, 30)
-        } else {
-            egui::Color32::WHITE
-        };
+// List marker for the PDF: the builtin Helvetica is written with WinAnsiEncoding, which
+// has "•" at 0x95, but a custom TTF may not include the glyph
+fn pdf_bullet(face: Option<&ttf_parser::Face>) -> &'static str {
+    match face {
+        Some(face) if face.glyph_index('•').is_none() => "- ",
+        _ => "• ",
+    }
+}
+
+// Breaks text into lines no wider than max_width mm. Words wider than a whole line
+// (long URLs, say) are split between characters rather than left to run off the page.
+fn wrap_text(text: &str, font_size: f32, max_width: f32, face: Option<&ttf_parser::Face>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current_line = String::new();
 
-        if self.recipes.is_empty() {
-            self.load_recipes();
+    for word in text.split_whitespace() {
+        let candidate = if current_line.is_empty() { word.to_string() } else { format!("{} {}", current_line, word) };
+        if text_width_mm(&candidate, font_size, face) <= max_width {
+            current_line = candidate;
+            continue;
+        }
+        if !current_line.is_empty() {
+            lines.push(std::mem::take(&mut current_line));
+        }
+        for c in word.chars() {
+            current_line.push(c);
+            if current_line.chars().count() > 1 && text_width_mm(&current_line, font_size, face) > max_width {
+                current_line.pop();
+                lines.push(std::mem::replace(&mut current_line, c.to_string()));
+            }
         }
+    }
+    if !current_line.is_empty() {
+        lines.push(current_line);
+    }
+    lines
+}
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
-            
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                ui.vertical_centered(|ui| {
-                    ui.heading("Select Recipe to View");
+const HISTORY_DIR: &str = "recipes/.history";
+
+// Where a recipe's old versions go: recipes/.history/<category>/<name>/, so recipes
+// with the same name in different categories keep separate histories
+fn recipe_history_dir(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let category = path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Path::new(HISTORY_DIR).join(category).join(stem)
+}
 
-                    ui.add_space(10.0);
+// Copies the current file into the recipe's history folder before it gets overwritten.
+// Versions are named by the time in nanoseconds, zero-padded so they sort by name, and
+// bumped past any version already saved at the same instant.
+fn archive_recipe_version(path: &Path) -> Result<(), std::io::Error> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let version_dir = recipe_history_dir(path);
+    fs::create_dir_all(&version_dir)?;
+    let mut timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+    let extension = path.extension().map(|ext| ext.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut version_path = version_dir.join(recipe_file_name(&format!("{:020}", timestamp), &extension));
+    while version_path.exists() {
+        timestamp += 1;
+        version_path = version_dir.join(recipe_file_name(&format!("{:020}", timestamp), &extension));
+    }
+    fs::copy(path, version_path)?;
+    Ok(())
+}
 
-                    // Center the combo box
-                    ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                        egui::ComboBox::from_label("Recipe")
-                            .width(200.0) // Set a fixed width for the combo box
-                            .selected_text(self.selected_recipe.clone().unwrap_or_else(|| "Select a recipe".to_string()))
-                            .show_ui(ui, |ui| {
-                                for recipe in &self.recipes {
-                                    ui.selectable_value(&mut self.selected_recipe, Some(recipe.clone()), recipe);
-                                }
-                            });
-                    });
+// Archived versions of the recipe at `path`, oldest first
+fn recipe_versions(path: &Path) -> Vec<PathBuf> {
+    let mut versions: Vec<PathBuf> = fs::read_dir(recipe_history_dir(path))
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    versions.sort();
+    versions
+}
 
-                    ui.add_space(10.0);
+struct RecipeDiff {
+    added_ingredients: Vec<String>,
+    removed_ingredients: Vec<String>,
+    added_steps: Vec<String>,
+    removed_steps: Vec<String>,
+    changed_steps: Vec<(usize, String, String)>,
+}
 
-                    if let Some(selected_recipe) = &self.selected_recipe {
-                        if ui.button("Generate PDF").clicked() {
-                            let recipe_path = self.get_recipe_path(selected_recipe);
-                            if recipe_path.exists() {
-                                match parse_recipe_file(&recipe_path) {
-                                    Ok(recipe) => {
-                                        if let Err(e) = generate_recipe_pdf(&recipe_path) {
-                                            self.processing_message = format!("Error generating PDF: {}", e);
-                                            self.pdf_generated = false;
-                                        } else {
-                                            let pdf_filename = format!("{}.pdf", recipe.title.replace(" ", "_"));
-                                            let pdf_path = env::current_dir().unwrap().join(&pdf_filename);
-                                            self.current_pdf_path = Some(pdf_path.clone());
-                                            if let Err(e) = open_pdf(&pdf_path) {
-                                                self.processing_message = format!("Error opening PDF: {}", e);
-                                            } else {
-                                                self.processing_message = "PDF generated and opened successfully".to_string();
-                                                self.pdf_generated = true;
-                                            }
-                                        }
-                                    },
-                                    Err(e) => {
-                                        self.processing_message = format!("Error parsing recipe: {}", e);
-                                        self.pdf_generated = false;
-                                    }
-                                }
-                            } else {
-                                self.processing_message = "Recipe file not found".to_string();
-                                self.pdf_generated = false;
-                            }
-                        }
+impl RecipeDiff {
+    fn is_empty(&self) -> bool {
+        self.added_ingredients.is_empty()
+            && self.removed_ingredients.is_empty()
+            && self.added_steps.is_empty()
+            && self.removed_steps.is_empty()
+            && self.changed_steps.is_empty()
+    }
 
-                        if self.pdf_generated {
-                            if ui.button("Print PDF").clicked() {
-                                if let Some(pdf_path) = &self.current_pdf_path {
-                                    match self.print_pdf(pdf_path) {
-                                        Ok(_) => self.processing_message = "PDF sent to printer successfully".to_string(),
-                                        Err(e) => self.processing_message = format!("Error printing PDF: {}", e),
-                                    }
-                                } else {
-                                    self.processing_message = "No PDF generated to print".to_string();
-                                }
-                            }
-                        }
-                    }
+    // "-" lines come from the older version and "+" lines from the newer one; a changed
+    // step shows both
+    fn describe(&self) -> String {
+        let mut lines = vec!["(- previous version, + current version)".to_string()];
+        for ingredient in &self.added_ingredients {
+            lines.push(format!("+ {}", ingredient));
+        }
+        for ingredient in &self.removed_ingredients {
+            lines.push(format!("- {}", ingredient));
+        }
+        for (idx, old, new) in &self.changed_steps {
+            lines.push(format!("- Step {}: {}", idx + 1, old));
+            lines.push(format!("+ Step {}: {}", idx + 1, new));
+        }
+        for step in &self.added_steps {
+            lines.push(format!("+ {}", step));
+        }
+        for step in &self.removed_steps {
+            lines.push(format!("- {}", step));
+        }
+        lines.join("\n")
+    }
+}
 
-                    ui.add_space(10.0);
+// Line-based comparison: ingredients are matched as a set, steps by position
+fn diff_recipes(old: &Recipe, new: &Recipe) -> RecipeDiff {
+    let added_ingredients = new
+        .ingreds
+        .iter()
+        .filter(|ingredient| !old.ingreds.contains(ingredient))
+        .map(|ingredient| ingredient.text.clone())
+        .collect();
+    let removed_ingredients = old
+        .ingreds
+        .iter()
+        .filter(|ingredient| !new.ingreds.contains(ingredient))
+        .map(|ingredient| ingredient.text.clone())
+        .collect();
+
+    let mut added_steps = Vec::new();
+    let mut removed_steps = Vec::new();
+    let mut changed_steps = Vec::new();
+    for idx in 0..old.instructions.len().max(new.instructions.len()) {
+        match (old.instructions.get(idx), new.instructions.get(idx)) {
+            (Some(old_step), Some(new_step)) if old_step != new_step => {
+                changed_steps.push((idx, old_step.clone(), new_step.clone()));
+            }
+            (None, Some(new_step)) => added_steps.push(new_step.clone()),
+            (Some(old_step), None) => removed_steps.push(old_step.clone()),
+            _ => {}
+        }
+    }
 
-                    if ui.button("Back to Main Screen").clicked() {
-                        self.wants_to_exit = true;
-                    }
+    RecipeDiff {
+        added_ingredients,
+        removed_ingredients,
+        added_steps,
+        removed_steps,
+        changed_steps,
+    }
+}
 
-                    ui.add_space(10.0);
+// Renders the shopping list as a printable checklist with a box before each item
+fn render_shopping_list_pdf(items: &[String]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (doc, page1, layer1) = PdfDocument::new("Shopping List", Mm(210.0), Mm(297.0), "Layer 1");
+    let font = load_pdf_font(&doc, None)?;
+
+    let mut layer = doc.get_page(page1).get_layer(layer1);
+    layer.use_text("Shopping List", 20.0, Mm(10.0), Mm(280.0), &font);
+    let mut y_position = 265.0;
+
+    for item in items {
+        // Long items wrap under themselves, and an item is never split across pages
+        let lines = wrap_text(item, 12.0, 210.0 - 17.0 - 10.0, None);
+        let extra_lines = lines.len().saturating_sub(1) as f32;
+        if y_position - 6.0 * extra_lines < 20.0 {
+            let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+            layer = doc.get_page(new_page).get_layer(new_layer);
+            y_position = 280.0;
+        }
+        let box_size = 4.0;
+        let checkbox = Line {
+            points: vec![
+                (Point::new(Mm(10.0), Mm(y_position)), false),
+                (Point::new(Mm(10.0 + box_size), Mm(y_position)), false),
+                (Point::new(Mm(10.0 + box_size), Mm(y_position + box_size)), false),
+                (Point::new(Mm(10.0), Mm(y_position + box_size)), false),
+            ],
+            is_closed: true,
+        };
+        layer.add_line(checkbox);
+        for line in &lines {
+            layer.use_text(line, 12.0, Mm(17.0), Mm(y_position), &font);
+            y_position -= 6.0;
+        }
+        y_position -= 2.0;
+    }
 
-                    if !self.processing_message.is_empty() {
-                        ui.label(&self.processing_message);
-                    }
-                });
-            });
+    Ok(doc.save_to_bytes()?)
+}
 
-            if is_dark_mode {
-                ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
-            } else {
-                ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
-            }
+// One landscape page with a column per day, meant to be printed and stuck on the fridge
+// When a duration style is given, each day also shows its cooking time and the
+// footer shows the total for the week.
+fn render_schedule_grid_pdf(days: &[PlannedDay], times: Option<DurationStyle>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (doc, page1, layer1) = PdfDocument::new("Week at a Glance", Mm(297.0), Mm(210.0), "Layer 1");
+    let font = load_pdf_font(&doc, None)?;
+    let layer = doc.get_page(page1).get_layer(layer1);
+    layer.use_text("Week at a Glance", 24.0, Mm(10.0), Mm(195.0), &font);
+
+    let (left, right, top, header_bottom, bottom) = (10.0, 287.0, 185.0, 173.0, 15.0);
+    let column_width = (right - left) / days.len().max(1) as f32;
+
+    let rule = |x1: f32, y1: f32, x2: f32, y2: f32| {
+        layer.add_line(Line {
+            points: vec![(Point::new(Mm(x1), Mm(y1)), false), (Point::new(Mm(x2), Mm(y2)), false)],
+            is_closed: false,
         });
+    };
+    rule(left, top, right, top);
+    rule(left, header_bottom, right, header_bottom);
+    rule(left, bottom, right, bottom);
+    for i in 0..=days.len() {
+        let x = left + column_width * i as f32;
+        rule(x, top, x, bottom);
+    }
 
-        None
+    // Text stays 2mm inside its column and above the bottom rule
+    let text_width = column_width - 4.0;
+    let floor = bottom + 3.0;
+    for (i, planned) in days.iter().enumerate() {
+        let x = left + column_width * i as f32 + 2.0;
+        layer.use_text(&planned.day, 14.0, Mm(x), Mm(top - 8.0), &font);
+
+        // Meals are wrapped to the column, shrinking the text a point at a time (down
+        // to 8pt) until the day fits; anything still left over is cut off
+        let time = match (times, planned.total_time) {
+            (Some(style), Some(total_time)) => Some(format_duration(total_time, style)),
+            _ => None,
+        };
+        let mut size = 14.0;
+        let meals = loop {
+            let meals: Vec<Vec<String>> = planned
+                .meals
+                .iter()
+                .map(|(meal, recipe_name)| wrap_text(&format!("{}: {}", meal, recipe_name), size, text_width, None))
+                .collect();
+            let line_count: usize = meals.iter().map(|lines| lines.len()).sum();
+            let height = line_count as f32 * size * 0.5 + meals.len() as f32 * 3.0 + if time.is_some() { 6.0 } else { 0.0 };
+            if size <= 8.0 || header_bottom - 10.0 - height >= floor - size * 0.5 {
+                break meals;
+            }
+            size -= 1.0;
+        };
+
+        let mut y_position = header_bottom - 10.0;
+        'meals: for lines in &meals {
+            for line in lines {
+                if y_position < floor {
+                    break 'meals;
+                }
+                layer.use_text(line, size, Mm(x), Mm(y_position), &font);
+                y_position -= size * 0.5;
+            }
+            y_position -= 3.0;
+        }
+        if let Some(time) = time {
+            if y_position - 3.0 >= floor {
+                layer.use_text(time, 11.0, Mm(x), Mm(y_position - 3.0), &font);
+            }
+        }
     }
 
-    fn wants_to_exit(&self) -> bool {
-        self.wants_to_exit
+    if let Some(style) = times {
+        let week_total: Duration = days.iter().filter_map(|planned| planned.total_time).sum();
+        let footer = format!("Cooking time this week: {}", format_duration(week_total, style));
+        layer.use_text(&footer, 12.0, Mm(left), Mm(bottom - 8.0), &font);
     }
+
+    Ok(doc.save_to_bytes()?)
 }
 
-#[get("/")]
-async fn index() -> HttpResponse {
-    HttpResponse::Ok().body(
-        r#"
-        <!DOCTYPE html>
-        <html lang="en">
-        <head>
-            <meta charset="UTF-8">
-            <meta name="viewport" content="width=device-width, initial-scale=1.0">
-            <title>Recipe Bot Web Server</title>
-            <style>
-                body {
-                    font-family: Arial, sans-serif;
-                    background-color: #f0f0f0;
-                    margin: 0;
-                    padding: 0;
-                    display: flex;
-                    justify-content: center;
-                    align-items: center;
-                    height: 100vh;
-                }
-                .container {
-                    text-align: center;
-                    background-color: #ffffff;
-                    padding: 50px;
-                    border-radius: 8px;
-                    box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
-                }
-                h1 {
-                    color: #333333;
-                }
-                .links {
-                    margin-top: 20px;
-                }
-                .link-button {
-                    display: inline-block;
-                    margin: 10px;
-                    padding: 15px 30px;
-                    font-size: 16px;
-                    color: #ffffff;
-                    background-color: #007BFF;
-                    border: none;
-                    border-radius: 5px;
-                    text-decoration: none;
-                    transition: background-color 0.3s;
-                }
-                .link-button:hover {
-                    backgorund-color: #0056B3;
-                }
-            </style>
-        </head>
-        <body>
-            <div class="container">
-                <h1>Welcome to Recipe Bot's Web Server</h1>
-                <div class="links">
-                    <a href="/schedule" class="link-button">Weekly Food Schedule</a>
-                    <a href="/ingredients" class="link-button">Ingredients Needed</a>
-                </div>
-            </div>
-        </body>
-        </html>
-        "#
-    )
+// Hash of the normalized title, ingredients and steps, used to spot duplicate recipes
+// regardless of spacing, capitalization or step numbering
+fn recipe_content_hash(recipe: &Recipe) -> u64 {
+    fn normalize(text: &str) -> String {
+        text.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+    }
+
+    let mut hasher = DefaultHasher::new();
+    normalize(&recipe.title).hash(&mut hasher);
+    for ingredient in &recipe.ingreds {
+        normalize(&ingredient.text).hash(&mut hasher);
+    }
+    for instruction in &recipe.instructions {
+        normalize(strip_step_number(instruction)).hash(&mut hasher);
+    }
+    hasher.finish()
 }
 
-#[get("/schedule")]
-async fn schedule() -> Result<HttpResponse> {
-    let path = PathBuf::from("schedule/schedule.txt");
-    if path.exists() {
-        let contents = fs::read_to_string(path)?;
-        let list_items: String = contents
-            .lines()
-            .map(|line| {
-                let parts: Vec<&str> = line.splitn(2, ": ").collect();
-                if parts.len() == 2 {
-                    format!("<div class=\"day\"><h2>{}</h2> <p class=\"meal\">{}</p></div>", parts[0], parts[1])
-                } else {
-                    let remaining: String = parts.join(" ");
-                    format!("<h2>{}</h2> <p class=\"meal\">{}</p>", parts[0], remaining)
+const IMPORT_DIR: &str = "import";
+const IMPORTED_RECIPES_DIR: &str = "recipes/generated";
+
+struct ImportSummary {
+    imported: usize,
+    duplicates: usize,
+    // Files that couldn't be read or parsed, with the reason. The rest of the batch
+    // is still imported.
+    failed: Vec<(PathBuf, String)>,
+}
+
+// Splits a Markdown document holding several recipes into one chunk per recipe.
+// Each recipe starts at a level-one "# Title" heading; anything before the first
+// heading (a file intro, say) is dropped.
+fn split_markdown_recipes(md: &str) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in md.lines() {
+        if line.starts_with("# ") {
+            sections.push(String::new());
+        }
+        if let Some(section) = sections.last_mut() {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    sections
+}
+
+// Reads one Markdown recipe: "# Title", optional "Servings: 4" style fields, then
+// "## Ingredients", "## Prep", "## Instructions" and "## Notes" lists. Returns None
+// when there's no title to file it under.
+fn parse_markdown_recipe(md: &str) -> Option<Recipe> {
+    let mut recipe = Recipe {
+        title: String::new(),
+        from: String::new(),
+        servings: String::new(),
+        prep_time: String::new(),
+        cook_time: String::new(),
+        total_time: String::new(),
+        tags: Vec::new(),
+        make_ahead: false,
+        difficulty: None,
+        rating: None,
+        ingreds: Vec::new(),
+        ingredient_groups: Vec::new(),
+        prep_steps: Vec::new(),
+        instructions: Vec::new(),
+        notes: Vec::new(),
+        order: Vec::new(),
+    };
+    let mut section = String::new();
+    let mut instruction_lines: Vec<String> = Vec::new();
+
+    for line in md.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(title) = trimmed.strip_prefix("# ") {
+            recipe.title = title.trim().to_string();
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix("## ") {
+            section = heading.trim().to_lowercase();
+            continue;
+        }
+        let item = strip_step_number(strip_list_marker(trimmed)).to_string();
+        match section.as_str() {
+            "ingredients" => recipe.ingreds.push(parse_ingredient(strip_list_marker(trimmed), false)),
+            "prep" => recipe.prep_steps.push(item),
+            "instructions" | "directions" | "method" => instruction_lines.push(trimmed.to_string()),
+            "notes" => recipe.notes.push(item),
+            _ => {
+                // Fields may be bold, e.g. "**Servings:** 4"
+                let field = trimmed.replace("**", "");
+                if let Some((key, value)) = field.split_once(':') {
+                    let value = value.trim().to_string();
+                    match key.trim().to_lowercase().as_str() {
+                        "from" | "source" => recipe.from = value,
+                        "servings" | "serves" | "yield" => recipe.servings = value,
+                        "prep time" => recipe.prep_time = value,
+                        "cook time" => recipe.cook_time = value,
+                        "total time" => recipe.total_time = value,
+                        "tags" => recipe.tags = split_tags(&value),
+                        _ => {}
+                    }
                 }
-            })
-            .collect::<Vec<String>>()
-            .join("\n");
-        Ok(HttpResponse::Ok().body(format!(
-            r#"
-            <!DOCTYPE html>
-            <html lang="en">
-            <head>
-                <meta charset="UTF-8">
-                <meta name="viewport" content="width=device-width, initial-scale=1.0">
-                <title>Meal Schedule</title>
-                <style>
-                    body {{
-                        font-family: Arial, sans-serif;
-                        background-color: #f0f0f0;
-                        margin: 0;
-                        padding: 0;
-                        display: flex;
-                        justify-content: center;
-                        align-items: center;
-                        height: 100vh;
-                    }}
-                    .container {{
-                        text-align: center;
-                        background-color: #ffffff;
-                        padding: 50px;
-                        border-radius: 8px;
-                        box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
-                        max-width: 600px;
-                        width: 100%;
-                    }}
-                    h1 {{
-                        color: #333333;
-                    }}
-                    .schedule {{
-                        margin-top: 20px;
-                    }}
-                    .day {{
-                        margin: 10px 0;
-                        padding: 15px;
-                        background-color: #e9ecef;
-                        border-radius: 5px;
-                        box-shadow: 0 0 5px rgba(0, 0, 0, 0.1);
-                    }}
-                    .day h2 {{
-                        margin: 0;
-                        color: #007BFF;
-                    }}
-                    .meal {{
-                        margin-top: 5px;
-                        color: #555555;
-                    }}
-                </style>
-            </head>
-            <body>
-                <div class="container">
-                    <h1>Weekly Meal Schedule</h1>
-                    <div class="schedule">
-                        {}
-                    </div>
-                </div>
-            </body>
-            </html>
-            "#,
-            list_items
-        )))
+            }
+        }
+    }
+    recipe.instructions = merge_wrapped_steps(&instruction_lines);
+
+    if recipe.title.is_empty() {
+        None
     } else {
-        Err(Error::from(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Schedule file not found"
-        )))
+        Some(recipe)
     }
 }
 
-#[get("/ingredients")]
-async fn ingredients() -> Result<HttpResponse> {
-    let path = PathBuf::from("schedule/ingredients.sup");
-    if path.exists() {
-        let contents = fs::read_to_string(path)?;
-        let list_items: String = contents
-            .lines()
-            .map(|line| format!("<p class=\"item\">{}</p>", line.trim()))
-            .collect::<Vec<String>>()
-            .join("\n");
+// Copies every recipe in the import directory into the collection, skipping any
+// whose content already exists (including repeats within the import itself)
+fn import_recipes(import_dir: &Path, dest_dir: &Path, extension: &str) -> Result<ImportSummary, std::io::Error> {
+    let mut known: HashSet<u64> = load_all_recipes(extension).iter().map(recipe_content_hash).collect();
+    if let Ok(entries) = fs::read_dir(dest_dir) {
+        for entry in entries.flatten() {
+            if let Ok(recipe) = parse_recipe_file(&entry.path()) {
+                known.insert(recipe_content_hash(&recipe));
+            }
+        }
+    }
 
-        Ok(HttpResponse::Ok().body(format!(
-            r#"
-            <!DOCTYPE html>
-            <html lang="en">
-            <head>
-                <meta charset="UTF-8">
-                <meta name="viewport" content="width=device-width, initial-scale=1.0">
-                <title>Ingredients</title>
-                <style>
-                    body {{
-                        font-family: Arial, sans-serif;
-                        background-color: #f0f0f0;
-                        margin: 0;
-                        padding: 0;
-                        display: flex;
-                        justify-content: center;
-                        align-items: center;
-                        height: 100vh;
-                    }}
-                    .container {{
-                        text-align: center;
-                        background-color: #ffffff;
-                        padding: 50px;
-                        border-radius: 8px;
-                        box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
-                        max-width: 600px;
-                        width: 100%;
-                    }}
-                    h1 {{
-                        color: #333333;
-                    }}
-                    .ingredients {{
-                        margin-top: 20px;
-                        text-align: left;
-                        max-height: 400px;
-                        overflow-y: auto;
-                        padding-right: 10px; /* to avoid hiding the last item */
-                    }}
-                    .item {{
-                        margin: 10px 0;
-                        padding: 15px;
-                        background-color: #e9ecef;
-                        border-radius: 5px;
-                        box-shadow: 0 0 5px rgba(0, 0, 0, 0.1);
-                    }}
-                    .copy-button {{
-                        display: inline-block;
-                        margin-top: 20px;
-                        padding: 15px 30px;
-                        font-size: 16px;
-                        color: #ffffff;
-                        background-color: #28a745;
-                        border: none;
-                        border-radius: 5px;
-                        cursor: pointer;
-                        transition: background-color 0.3s;
-                    }}
-                    .copy-button:hover {{
-                        background-color: #218838;
-                    }}
-                </style>
-            </head>
-            <body>
-                <div class="container">
-                    <h1>Ingredients List</h1>
-                    <div class="ingredients" id="ingredients-list">
-                        {}
-                    </div>
-                    <button class="copy-button" onclick="copyToClipboard()">Copy to Clipboard</button>
-                </div>
-                <script>
-                    function copyToClipboard() {{
-                        const ingredientsElement = document.getElementById('ingredients-list');
-                        const ingredientsText = Array.from(ingredientsElement.getElementsByClassName('item'))
-                            .map(item => item.innerText.trim()) // Remove extra whitespace
-                            .join('\n'); // Use actual newline character
+    fs::create_dir_all(dest_dir)?;
+    let mut summary = ImportSummary { imported: 0, duplicates: 0, failed: Vec::new() };
+    let mut candidates: Vec<Recipe> = Vec::new();
+    let mut paths: Vec<PathBuf> = fs::read_dir(import_dir)?.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        if is_recipe_file(&path, extension) {
+            match parse_recipe_file(&path) {
+                Ok(recipe) => candidates.push(recipe),
+                Err(e) => summary.failed.push((path, e.to_string())),
+            }
+        } else if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            // A Markdown file may hold several recipes, each imported on its own
+            match fs::read_to_string(&path) {
+                Ok(contents) => {
+                    candidates.extend(split_markdown_recipes(&contents).iter().filter_map(|md| parse_markdown_recipe(md)))
+                }
+                Err(e) => summary.failed.push((path, e.to_string())),
+            }
+        }
+    }
+    for recipe in candidates {
+        if !known.insert(recipe_content_hash(&recipe)) {
+            summary.duplicates += 1;
+            continue;
+        }
+        // A different recipe with the same title gets a numbered file name
+        write_recipe_file(&recipe, &unique_recipe_path(dest_dir, &recipe.title, extension))?;
+        summary.imported += 1;
+    }
+    Ok(summary)
+}
 
-                        const container = document.createElement('textarea');
-                        container.value = ingredientsText;
-                        document.body.appendChild(container);
-                        container.select();
-                        document.execCommand('copy');
-                        document.body.removeChild(container);
-                        alert('Ingredients copied to clipboard!');
-                    }}
-                </script>
-            </body>
-            </html>
-            "#,
-            list_items
-        )))
-    } else {
-        Err(Error::from(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Ingredients file not found"
-        )))
+const PAPRIKA_EXPORT_PATH: &str = "export/recipes.paprikarecipes";
+
+// Maps a recipe onto the JSON schema Paprika uses for a single .paprikarecipe.
+// Paprika has no separate prep list, so prep steps lead the directions.
+fn paprika_recipe_json(recipe: &Recipe) -> serde_json::Value {
+    let source = parse_source(&recipe.from);
+    let ingredients: Vec<&str> = recipe.ingreds.iter().map(|ingredient| ingredient.text.as_str()).collect();
+    let directions: Vec<&str> = recipe
+        .prep_steps
+        .iter()
+        .chain(recipe.instructions.iter())
+        .map(|step| step.as_str())
+        .collect();
+    let hash = format!("{:016x}", recipe_content_hash(recipe));
+    json!({
+        "uid": hash.to_uppercase(),
+        "hash": hash,
+        "name": recipe.title,
+        "servings": recipe.servings,
+        "prep_time": recipe.prep_time,
+        "cook_time": recipe.cook_time,
+        "total_time": recipe.total_time,
+        "source": source.name.unwrap_or_default(),
+        "source_url": source.url.unwrap_or_default(),
+        "ingredients": ingredients.join("\n"),
+        "directions": directions.join("\n"),
+        "notes": recipe.notes.join("\n"),
+        "categories": recipe.tags,
+        "created": chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        "rating": recipe.rating.unwrap_or(0),
+        "difficulty": recipe.difficulty.clone().unwrap_or_default(),
+        "description": "",
+        "nutritional_info": "",
+        "image_url": "",
+        "photo": null,
+        "photo_data": null,
+    })
+}
+
+// Writes a .paprikarecipes archive: a zip holding one gzipped JSON file per recipe,
+// which Paprika's "Import" accepts directly. Returns how many recipes were exported.
+fn export_paprika(recipes: &[Recipe], dest: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut used_names = HashSet::new();
+    for recipe in recipes {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(serde_json::to_string(&paprika_recipe_json(recipe))?.as_bytes())?;
+        let compressed = encoder.finish()?;
+
+        // Two recipes with the same title would otherwise overwrite each other on import
+        let stem = recipe.title.replace(" ", "_");
+        let mut file_name = format!("{}.paprikarecipe", stem);
+        let mut suffix = 2;
+        while !used_names.insert(file_name.clone()) {
+            file_name = format!("{}_{}.paprikarecipe", stem, suffix);
+            suffix += 1;
+        }
+        archive.start_file(file_name, zip::write::FileOptions::default())?;
+        archive.write_all(&compressed)?;
+    }
+    let bytes = archive.finish()?.into_inner();
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
     }
+    write_file_atomically(dest, &bytes)?;
+    Ok(recipes.len())
 }
 
-fn start_web_server() -> std::io::Result<()> {
-    println!("Starting server at http://0.0.0.0:8080");
-    let sys = actix_web::rt::System::new();
-    sys.block_on(async {
-        HttpServer::new(|| {
-            ActixApp::new()
-                .service(index)
-                .service(schedule)
-                .service(ingredients)
+// Where a recipe's PDF is written: "<Title_With_Underscores>.pdf" in the working
+// directory, or relative to it if the directory can't be resolved
+fn recipe_pdf_path(title: &str) -> PathBuf {
+    let file_name = format!("{}.pdf", title.replace(" ", "_"));
+    match env::current_dir() {
+        Ok(dir) => dir.join(file_name),
+        Err(_) => PathBuf::from(file_name),
+    }
+}
+
+// Renders a recipe as Markdown for note apps such as Obsidian. Empty fields and an
+// empty Notes section are left out, as in the PDF.
+fn export_recipe_markdown(recipe: &Recipe) -> String {
+    let mut out = format!("# {}\n\n", recipe.title);
+    let fields = [
+        ("From", &recipe.from),
+        ("Servings", &recipe.servings),
+        ("Prep Time", &recipe.prep_time),
+        ("Cook Time", &recipe.cook_time),
+        ("Total Time", &recipe.total_time),
+    ];
+    for (label, value) in fields.iter().filter(|(_, value)| !value.trim().is_empty()) {
+        out.push_str(&format!("**{}:** {}\n", label, value));
+    }
+    if !recipe.tags.is_empty() {
+        out.push_str(&format!("**Tags:** {}\n", recipe.tags.join(", ")));
+    }
+
+    out.push_str("\n## Ingredients\n\n");
+    for (group, ingredients) in recipe.grouped_ingredients() {
+        if !group.is_empty() {
+            out.push_str(&format!("\n### {}\n\n", group));
+        }
+        for ingredient in ingredients {
+            out.push_str(&format!("- {}\n", ingredient));
+        }
+    }
+
+    if !recipe.prep_steps.is_empty() {
+        out.push_str("\n## Prep\n\n");
+        for step in &recipe.prep_steps {
+            out.push_str(&format!("- {}\n", step));
+        }
+    }
+
+    out.push_str("\n## Instructions\n\n");
+    for (idx, instruction) in recipe.instructions.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n", idx + 1, strip_step_number(instruction)));
+    }
+
+    if !recipe.notes.is_empty() {
+        out.push_str("\n## Notes\n\n");
+        for note in &recipe.notes {
+            out.push_str(&format!("{}\n", note));
+        }
+    }
+    out
+}
+
+// Writes to a ".tmp" file next to the target and renames it into place once every
+// byte is on disk, so a failed write (e.g. a full disk) never leaves a truncated file
+fn write_file_atomically(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
         })
-        .bind("0.0.0.0:8080")?
-        .run()
-        .await
-    })?;
-    Ok(())
+        .and_then(|()| fs::rename(&temp_path, path));
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
 }
 
-fn main() -> eframe::Result<()> {
+// Which recipe file each generated PDF came from, kept beside the PDFs as
+// "<pdf name>\t<recipe path>" lines, so regenerating a recipe can overwrite its own PDF
+const PDF_SOURCES_FILE: &str = "pdf_sources.txt";
+
+// PDFs being written right now and the recipe each one is for, so two generations
+// running at once never settle on the same name
+static PDF_CLAIMS: Mutex<Option<HashMap<PathBuf, PathBuf>>> = Mutex::new(None);
+
+fn load_pdf_sources(dir: &Path) -> HashMap<String, PathBuf> {
+    fs::read_to_string(dir.join(PDF_SOURCES_FILE))
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .map(|(pdf, source)| (pdf.to_string(), PathBuf::from(source)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-    thread::spawn(|| {
-        if let Err(e) = start_web_server() {
-            eprintln!("Web server error: {}", e);
+// The PDF path for a recipe. A file of that name made from a different recipe (or one
+// Recipe Bot doesn't know the source of) is left alone and "_2", "_3", ... is appended
+// instead. Nothing is created here; finish_pdf_claim must be called once writing is done.
+fn claim_pdf_path(title: &str, source: &Path) -> PathBuf {
+    let base = recipe_pdf_path(title);
+    let dir = base.parent().unwrap_or(Path::new(""));
+    let stem = title.replace(" ", "_");
+    let mut claims = PDF_CLAIMS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let claims = claims.get_or_insert_with(HashMap::new);
+    let sources = load_pdf_sources(dir);
+    let mut suffix = 1;
+    loop {
+        let numbered = if suffix == 1 { stem.clone() } else { format!("{}_{}", stem, suffix) };
+        let name = recipe_file_name(&numbered, "pdf");
+        let path = dir.join(&name);
+        let owner = claims.get(&path).or_else(|| sources.get(&name));
+        let free = match owner {
+            Some(owner) => owner == source,
+            None => !path.exists(),
+        };
+        if free {
+            claims.insert(path.clone(), source.to_path_buf());
+            return path;
         }
-    });
-    let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size((400.0, 400.0)),
-        ..eframe::NativeOptions::default()
+        suffix += 1;
+    }
+}
+
+// Releases a claimed PDF path, recording which recipe it belongs to when it was written
+fn finish_pdf_claim(path: &Path, source: &Path, written: bool) -> Result<(), std::io::Error> {
+    let mut claims = PDF_CLAIMS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(claims) = claims.as_mut() {
+        claims.remove(path);
+    }
+    if !written {
+        return Ok(());
+    }
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut sources = load_pdf_sources(dir);
+    sources.insert(path.file_name().unwrap_or_default().to_string_lossy().to_string(), source.to_path_buf());
+    let mut names: Vec<&String> = sources.keys().collect();
+    names.sort();
+    let mut contents = String::new();
+    for name in names {
+        contents.push_str(&format!("{}\t{}\n", name, sources[name].display()));
+    }
+    write_file_atomically(&dir.join(PDF_SOURCES_FILE), contents.as_bytes())
+}
+
+// Lays out recipes in one PDF, each starting on a fresh page. Cover lines, when given,
+// go on a first page under the document title. Returns the bytes and the number of pages.
+fn render_recipes_pdf(recipes: &[Recipe], doc_title: &str, cover: &[String], settings: &Settings) -> Result<(Vec<u8>, usize), Box<dyn std::error::Error>> {
+    let duration_style = settings.duration_style;
+    let (page_width, page_height) = settings.page_size.dimensions();
+    // Text starts 17mm below the top edge and stops 20mm above the bottom, leaving
+    // room for the footer
+    let top = page_height - 17.0;
+
+    // Create a new PDF document
+    let (doc, page1, layer1) = PdfDocument::new(doc_title, Mm(page_width), Mm(page_height), "Layer 1");
+
+    // Use the chosen font from fonts/ when there is one, otherwise the built-in Helvetica
+    let custom_font = Some(settings.pdf_font.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| Path::new(PDF_FONTS_DIR).join(name))
+        .filter(|path| path.exists());
+    let font = load_pdf_font(&doc, custom_font.as_deref())?;
+    // Glyph widths for wrapping come from the same TTF, if one is being embedded
+    let font_bytes = custom_font.as_ref().and_then(|path| fs::read(path).ok());
+    let face = font_bytes.as_deref().and_then(|bytes| ttf_parser::Face::parse(bytes, 0).ok());
+    // Helvetica Bold only covers WinAnsi characters, so group headings stay in the
+    // embedded font when there is one
+    let bold_font = if face.is_some() { font.clone() } else { doc.add_builtin_font(BuiltinFont::HelveticaBold)? };
+
+    // Create a struct to hold the mutable state
+    struct State {
+        y_position: f32,
+        current_page: PdfPageIndex,
+        current_layer: PdfLayerIndex,
+        // Title of the recipe being laid out, for the footer of any page it spills onto
+        footer: String,
+        // Every page so far with its footer title. Footers are stamped once layout is
+        // done, since the page count isn't known until then.
+        pages: Vec<(PdfPageIndex, PdfLayerIndex, String)>,
+    }
+
+    let mut state = State {
+        y_position: top,
+        current_page: page1,
+        current_layer: layer1,
+        footer: match recipes.first() {
+            Some(recipe) if cover.is_empty() => recipe.title.clone(),
+            _ => doc_title.to_string(),
+        },
+        pages: Vec::new(),
     };
+    state.pages.push((page1, layer1, state.footer.clone()));
 
-    eframe::run_native(
-        MainScreen::name(),
-        native_options,
-        Box::new(|_cc: &CreationContext<'_>| -> Box<dyn eframe::App> {
-            Box::new(MainScreen::default())
-        }),
-    )
+    // Helper function to add text
+    let add_styled_text = |text: &str, size: f32, x: f32, font: &IndirectFontRef, state: &mut State| {
+        // Text runs from x to a 10mm right margin, so the column is 190mm at x = 10 on A4
+        let max_width = page_width - 10.0 - x;
+        let wrapped_lines = wrap_text(text, size, max_width, face.as_ref());
+
+        for line in wrapped_lines {
+            if state.y_position < 20.0 {
+                // Create a new page
+                let (new_page, new_layer) = doc.add_page(Mm(page_width), Mm(page_height), "Layer 1");
+                state.current_page = new_page;
+                state.current_layer = new_layer;
+                state.y_position = top;
+                state.pages.push((new_page, new_layer, state.footer.clone()));
+            }
+            let layer = doc.get_page(state.current_page).get_layer(state.current_layer);
+            layer.use_text(&line, size, Mm(x), Mm(state.y_position), font);
+            state.y_position -= size + 2.0; // Move down by font size plus a small gap
+        }
+    };
+    let add_text = |text: &str, size: f32, x: f32, state: &mut State| add_styled_text(text, size, x, &font, state);
+
+    let bullet = pdf_bullet(face.as_ref());
+
+    if !cover.is_empty() {
+        add_text(doc_title, 20.0, 10.0, &mut state);
+        state.y_position -= 10.0; // Add some space
+        for line in cover {
+            add_text(line, 14.0, 10.0, &mut state);
+        }
+    }
+
+    for (idx, recipe) in recipes.iter().enumerate() {
+        if idx > 0 || !cover.is_empty() {
+            let (new_page, new_layer) = doc.add_page(Mm(page_width), Mm(page_height), "Layer 1");
+            state.current_page = new_page;
+            state.current_layer = new_layer;
+            state.y_position = top;
+            state.footer = recipe.title.clone();
+            state.pages.push((new_page, new_layer, state.footer.clone()));
+        }
+
+        // Add recipe details
+        add_text(&recipe.title, 20.0, 10.0, &mut state);
+        add_text(&format!("From: {}", recipe.from), 14.0, 10.0, &mut state);
+        add_text(&format!("Servings: {}", recipe.servings), 14.0, 10.0, &mut state);
+        add_text(&format!("Prep Time: {}", display_time(&recipe.prep_time, duration_style)), 14.0, 10.0, &mut state);
+        add_text(&format!("Cook Time: {}", display_time(&recipe.cook_time, duration_style)), 14.0, 10.0, &mut state);
+        add_text(&format!("Total Time: {}", display_time(&recipe.total_time, duration_style)), 14.0, 10.0, &mut state);
+        if let Some(difficulty) = &recipe.difficulty {
+            add_text(&format!("Difficulty: {}", difficulty), 14.0, 10.0, &mut state);
+        }
+        // Written as a number since Helvetica has no star glyphs
+        if let Some(rating) = recipe.rating {
+            add_text(&format!("Rating: {}/5", rating), 14.0, 10.0, &mut state);
+        }
+
+        state.y_position -= 10.0; // Add some space
+
+        // Add ingredients
+        add_text("Ingredients:", 16.0, 10.0, &mut state);
+        for (group, ingredients) in recipe.grouped_ingredients() {
+            if !group.is_empty() {
+                add_styled_text(&group, 13.0, 12.0, &bold_font, &mut state);
+            }
+            for ingredient in ingredients {
+                add_text(&format!("{}{}", bullet, ingredient), 12.0, 15.0, &mut state);
+            }
+        }
+
+        state.y_position -= 10.0; // Add some space
+
+        // Add prep steps if any
+        if !recipe.prep_steps.is_empty() {
+            add_text("Prep:", 16.0, 10.0, &mut state);
+            for step in &recipe.prep_steps {
+                add_text(step, 12.0, 15.0, &mut state);
+            }
+
+            state.y_position -= 10.0; // Add some space
+        }
+
+        // Add instructions
+        add_text("Instructions:", 16.0, 10.0, &mut state);
+        for instruction in format_steps(&recipe.instructions, settings.bulleted_steps) {
+            let instruction = match instruction.strip_prefix("• ") {
+                Some(text) => format!("{}{}", bullet, text),
+                None => instruction,
+            };
+            add_text(&instruction, 12.0, 15.0, &mut state);
+        }
+
+        state.y_position -= 10.0; // Add some space
+
+        // Add notes if any
+        if !recipe.notes.is_empty() {
+            add_text("Notes:", 16.0, 10.0, &mut state);
+            for note in &recipe.notes {
+                add_text(note, 12.0, 15.0, &mut state);
+            }
+        }
+    }
+
+    // Recipe title bottom left and "Page N of M" centered under the text
+    let page_count = state.pages.len();
+    for (number, (page, layer, title)) in state.pages.iter().enumerate() {
+        let layer = doc.get_page(*page).get_layer(*layer);
+        // Long titles are cut at the first wrapped line so they stay clear of the page number
+        if let Some(title) = wrap_text(title, 9.0, 70.0, face.as_ref()).first() {
+            layer.use_text(title, 9.0, Mm(10.0), Mm(10.0), &font);
+        }
+        let page_label = format!("Page {} of {}", number + 1, page_count);
+        let label_x = (page_width - text_width_mm(&page_label, 9.0, face.as_ref())) / 2.0;
+        layer.use_text(&page_label, 9.0, Mm(label_x), Mm(10.0), &font);
+    }
+
+    Ok((doc.save_to_bytes()?, page_count))
+}
+
+fn generate_recipe_pdf(recipe_path: &Path, scale_factor: f32, settings: &Settings) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    // Parse the recipe file and scale it the way the view shows it
+    let recipe = parse_recipe_cached(recipe_path)?.scaled_for_output(scale_factor, settings.decimal_comma);
+    let (pdf_bytes, page_count) = render_recipes_pdf(std::slice::from_ref(&recipe), &recipe.title, &[], settings)?;
+
+    // Save the PDF to a file
+    let output_path = claim_pdf_path(&recipe.title, recipe_path);
+    let written = write_file_atomically(&output_path, &pdf_bytes);
+    finish_pdf_claim(&output_path, recipe_path, written.is_ok())?;
+    written?;
+
+    println!("PDF saved to: {:?} ({} pages)", output_path, page_count);
+
+    Ok(output_path)
+}
+
+// Opens a file or URL with the platform's default handler
+fn open_external(target: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("cmd")
+            .arg("/C")
+            .arg("start")
+            .arg("")
+            .arg(target)
+            .spawn()?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(target)
+            .spawn()?;
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        Command::new("xdg-open")
+            .arg(target)
+            .spawn()?;
+    }
+    Ok(())
+}
+
+const WEB_SERVER_ADDR_VAR: &str = "RECIPE_BOT_ADDR";
+const DEFAULT_WEB_SERVER_ADDR: &str = "0.0.0.0:8080";
+// How many ports after the configured one to try when it's already taken
+const WEB_SERVER_PORT_RETRIES: u16 = 3;
+
+// The port the web server bound, or 0 until it's up, so the UI only offers links that work
+static WEB_SERVER_PORT: AtomicU16 = AtomicU16::new(0);
+
+// The address other devices on the network can reach this machine at. Connecting a
+// UDP socket sends nothing; it just makes the OS pick the outgoing interface.
+fn local_lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    if ip.is_unspecified() {
+        None
+    } else {
+        Some(ip)
+    }
+}
+
+fn schedule_url(ip: IpAddr, port: u16) -> String {
+    match ip {
+        IpAddr::V4(ip) => format!("http://{}:{}/schedule", ip, port),
+        IpAddr::V6(ip) => format!("http://[{}]:{}/schedule", ip, port),
+    }
+}
+
+// Paints a QR code as black modules on a white square, keeping the quiet zone
+// scanners need around the edge
+fn draw_qr_code(ui: &mut egui::Ui, code: &QrCode, module_size: f32) {
+    const QUIET_ZONE: usize = 4;
+    let width = code.width();
+    let side = (width + QUIET_ZONE * 2) as f32 * module_size;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    for (idx, color) in code.to_colors().into_iter().enumerate() {
+        if color != QrColor::Dark {
+            continue;
+        }
+        let (x, y) = (idx % width + QUIET_ZONE, idx / width + QUIET_ZONE);
+        let min = rect.min + egui::vec2(x as f32 * module_size, y as f32 * module_size);
+        painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(module_size, module_size)), 0.0, egui::Color32::BLACK);
+    }
+}
+
+struct MainScreen {
+    app_state: AppState,
+    // Screens opened from the main menu; the last one is shown and "Back" pops it
+    screen_stack: Vec<Box<dyn Screen>>,
+    status_message: String,
+    // This machine's LAN address, and the link to the web schedule with its QR code
+    // once the server has picked a port
+    lan_ip: Option<IpAddr>,
+    schedule_link: Option<(String, QrCode)>,
+    // No recipes yet; re-checked whenever the main menu is shown again
+    library_empty: bool,
+    // Set while "Update and Restart" pulls and rebuilds on its worker thread
+    update_job: Option<Receiver<Result<(), String>>>,
+}
+
+impl Default for MainScreen {
+    fn default() -> Self {
+        let app_state = AppState::new();
+        let library_empty = library_is_empty(&recipe_directories(), &app_state.settings.recipe_extension);
+        Self {
+            app_state,
+            screen_stack: Vec::new(),
+            status_message: String::new(),
+            lan_ip: local_lan_ip(),
+            schedule_link: None,
+            library_empty,
+            update_job: None,
+        }
+    }
+}
+
+impl MainScreen {
+    fn name() -> &'static str {
+        "Recipe Bot"
+    }
+
+    fn push_screen(&mut self, screen: Box<dyn Screen>) {
+        self.screen_stack.push(screen);
+    }
+
+    fn pop_screen(&mut self) -> Option<Box<dyn Screen>> {
+        self.screen_stack.pop()
+    }
+
+    fn handle_dark_mode_toggle(&mut self) {
+        self.app_state.toggle_dark_mode();
+    }
+
+    fn update(&mut self, ctx: &egui::Context) {
+        ctx.set_pixels_per_point(3.0);
+        let is_dark_mode = self.app_state.is_dark_mode;
+        let background_color = if is_dark_mode {
+            egui::Color32::from_rgb(30, 30, 30)
+        } else {
+            egui::Color32::WHITE
+        };
+        self.poll_update();
+        if self.update_job.is_some() {
+            ctx.request_repaint();
+        }
+        while self.screen_stack.last().is_some_and(|screen| screen.wants_to_exit()) {
+            self.pop_screen();
+            if self.screen_stack.is_empty() {
+                self.library_empty = library_is_empty(&recipe_directories(), &self.app_state.settings.recipe_extension);
+            }
+        }
+        if let Some(screen) = self.screen_stack.last_mut() {
+            if let Some(new_screen) = screen.update(ctx, &mut self.app_state) {
+                self.push_screen(new_screen);
+            }
+            return;
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
+            ui.vertical_centered(|ui| {
+                ui.heading("Recipe Bot");
+
+                // With no recipes the planner and viewer would open empty, so point at
+                // recipe creation first
+                if self.library_empty {
+                    ui.add_space(10.0);
+                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "Your recipe library is empty.");
+                    let get_started = egui::Button::new(egui::RichText::new("Get started: create your first recipe").strong());
+                    if ui.add(get_started).clicked() {
+                        self.push_screen(Box::new(CreateRecipeManuallyScreen::default()));
+                    }
+                    ui.add_space(10.0);
+                }
+
+                if ui.add_enabled(!self.library_empty, egui::Button::new("Create Weekly Recipes")).clicked() {
+                    self.push_screen(Box::new(CreateWeeklyRecipesScreen::default()));
+                }
+
+                if ui.add_enabled(self.update_job.is_none(), egui::Button::new("Update and Restart")).clicked() {
+                    self.start_update();
+                }
+
+                if ui.button("Create New Recipe - Manual Entry").clicked() {
+                    self.push_screen(Box::new(CreateRecipeManuallyScreen::default()));
+                }
+
+                if ui.button("Light/Dark Mode Toggle").clicked() {
+                    self.handle_dark_mode_toggle();
+                }
+
+                if ui.checkbox(&mut self.app_state.settings.decimal_comma, "Comma decimal separator (0,5)").changed() {
+                    if let Err(e) = self.app_state.settings.save() {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                }
+
+                let mut duration_style = self.app_state.settings.duration_style;
+                egui::ComboBox::from_label("Time format")
+                    .selected_text(duration_style.example())
+                    .show_ui(ui, |ui| {
+                        for style in DurationStyle::ALL {
+                            ui.selectable_value(&mut duration_style, style, style.example());
+                        }
+                    });
+                if duration_style != self.app_state.settings.duration_style {
+                    self.app_state.settings.duration_style = duration_style;
+                    if let Err(e) = self.app_state.settings.save() {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                }
+
+                if ui.checkbox(&mut self.app_state.settings.bulleted_steps, "Bulleted instructions").changed() {
+                    if let Err(e) = self.app_state.settings.save() {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                }
+
+                let mut pdf_font = self.app_state.settings.pdf_font.clone();
+                let builtin_label = "Helvetica (built-in)";
+                egui::ComboBox::from_label("PDF font")
+                    .selected_text(if pdf_font.is_empty() { builtin_label } else { pdf_font.as_str() })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut pdf_font, String::new(), builtin_label);
+                        for font in list_pdf_fonts() {
+                            ui.selectable_value(&mut pdf_font, font.clone(), &font);
+                        }
+                    });
+                if pdf_font != self.app_state.settings.pdf_font {
+                    self.app_state.settings.pdf_font = pdf_font;
+                    if let Err(e) = self.app_state.settings.save() {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                }
+
+                let mut page_size = self.app_state.settings.page_size;
+                egui::ComboBox::from_label("PDF page size")
+                    .selected_text(page_size.label())
+                    .show_ui(ui, |ui| {
+                        for size in PageSize::ALL {
+                            ui.selectable_value(&mut page_size, size, size.label());
+                        }
+                    });
+                if page_size != self.app_state.settings.page_size {
+                    self.app_state.settings.page_size = page_size;
+                    if let Err(e) = self.app_state.settings.save() {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Recipe file extension");
+                    let extension_edit = ui.add(
+                        egui::TextEdit::singleline(&mut self.app_state.settings.recipe_extension).desired_width(60.0),
+                    );
+                    if extension_edit.lost_focus() {
+                        let extension = normalize_extension(&self.app_state.settings.recipe_extension);
+                        self.app_state.settings.recipe_extension = extension;
+                        if let Err(e) = self.app_state.settings.save() {
+                            eprintln!("Failed to save settings: {}", e);
+                        }
+                    }
+                });
+
+                if ui.add_enabled(!self.library_empty, egui::Button::new("View Recipe")).clicked() {
+                    self.push_screen(Box::new(RecipeSelectionScreen::default()));
+                }
+
+                if ui.button("Library Health").clicked() {
+                    self.push_screen(Box::new(LibraryHealthScreen::default()));
+                }
+
+                if ui.add_enabled(!self.library_empty, egui::Button::new("Surprise Me")).clicked() {
+                    let names: Vec<String> = group_recipes_by_category(&recipe_directories(), &self.app_state.settings.recipe_extension)
+                        .into_iter()
+                        .flat_map(|(_, recipes)| recipes)
+                        .collect();
+                    match pick_surprise_recipe(&names, &load_random_exclusions()) {
+                        Some(name) => {
+                            self.status_message.clear();
+                            let screen = RecipeSelectionScreen::with_recipe(name, &self.app_state.settings.recipe_extension);
+                            self.push_screen(Box::new(screen));
+                        }
+                        None => self.status_message = "No recipes available to pick from".to_string(),
+                    }
+                }
+
+                if ui.button("Import Recipes").clicked() {
+                    let extension = &self.app_state.settings.recipe_extension;
+                    self.status_message = match import_recipes(Path::new(IMPORT_DIR), Path::new(IMPORTED_RECIPES_DIR), extension) {
+                        Ok(summary) => {
+                            let mut message = format!(
+                                "Imported {} recipe(s), skipped {} duplicate(s)",
+                                summary.imported, summary.duplicates
+                            );
+                            for (path, error) in &summary.failed {
+                                message.push_str(&format!("\nCould not import {}: {}", path.display(), error));
+                            }
+                            message
+                        }
+                        Err(e) => format!("Error importing recipes: {}", e),
+                    };
+                    self.library_empty = library_is_empty(&recipe_directories(), &self.app_state.settings.recipe_extension);
+                }
+
+                if ui.button("Import JSON").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).pick_file() {
+                        self.status_message = match import_recipe_json(&path, &self.app_state.settings.recipe_extension) {
+                            Ok(saved) => format!("Imported recipe to {}", saved.display()),
+                            Err(e) => format!("Error importing {}: {}", path.display(), e),
+                        };
+                        self.library_empty = library_is_empty(&recipe_directories(), &self.app_state.settings.recipe_extension);
+                    }
+                }
+
+                if ui.add_enabled(!self.library_empty, egui::Button::new("Export to Paprika")).clicked() {
+                    self.status_message = match export_paprika(&load_all_recipes(&self.app_state.settings.recipe_extension), Path::new(PAPRIKA_EXPORT_PATH)) {
+                        Ok(count) => format!("Exported {} recipe(s) to {}", count, PAPRIKA_EXPORT_PATH),
+                        Err(e) => format!("Error exporting recipes: {}", e),
+                    };
+                }
+
+                if !self.status_message.is_empty() {
+                    ui.label(&self.status_message);
+                }
+
+                let port = WEB_SERVER_PORT.load(Ordering::Relaxed);
+                if port != 0 {
+                    if self.schedule_link.is_none() {
+                        self.schedule_link = self.lan_ip.and_then(|ip| {
+                            let url = schedule_url(ip, port);
+                            QrCode::new(url.as_bytes()).ok().map(|code| (url, code))
+                        });
+                    }
+                    if let Some((url, code)) = &self.schedule_link {
+                        ui.collapsing("Schedule on your phone", |ui| {
+                            ui.label(url);
+                            draw_qr_code(ui, code, 3.0);
+                        });
+                    }
+                }
+
+                // Update text color based on dark mode
+                if is_dark_mode {
+                    ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+                } else {
+                    ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+                }
+            });
+        });
+    }
+    // Pulls and rebuilds on a worker thread so the window keeps responding during the
+    // build; poll_update restarts once it succeeds
+    fn start_update(&mut self) {
+        if self.update_job.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.update_job = Some(receiver);
+        let branch = self.app_state.settings.update_branch.clone();
+        self.status_message = format!("Updating from origin/{}...", branch);
+        thread::spawn(move || {
+            let _ = sender.send(pull_and_rebuild(&branch));
+        });
+    }
+    fn poll_update(&mut self) {
+        let result = match &self.update_job {
+            Some(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err("worker stopped unexpectedly".to_string()),
+            },
+            None => return,
+        };
+        self.update_job = None;
+        if let Err(e) = result.and_then(|()| restart()) {
+            eprintln!("Failed to update and restart: {}", e);
+            self.status_message = format!("Update failed: {}", e);
+        }
+    }
+}
+
+// The blocking half of "Update and Restart": pulls the branch and rebuilds
+fn pull_and_rebuild(branch: &str) -> Result<(), String> {
+    // Make sure there is a checkout to pull into before asking git to
+    let in_repo = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if !in_repo {
+        return Err("this copy was not installed from a git checkout, so it can't update itself".to_string());
+    }
+
+    // Pull from git
+    println!("Updating from origin/{}", branch);
+    run_update_step("git", &["pull", "origin", branch])?;
+
+    // Recompile the program
+    run_update_step("cargo", &["build", "--release"])
+}
+
+// Starts the rebuilt program and exits, only once the new instance is running
+fn restart() -> Result<(), String> {
+    let current_exe = env::current_exe().map_err(|e| format!("Could not locate executable: {}", e))?;
+    Command::new(current_exe)
+        .spawn()
+        .map_err(|e| format!("Failed to restart: {}", e))?;
+    std::process::exit(0);
+}
+
+// Runs one step of the self-update, capturing its output so a failure can be
+// reported with whatever the command printed.
+fn run_update_step(program: &str, args: &[&str]) -> Result<(), String> {
+    let command = format!("{} {}", program, args.join(" "));
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("could not run `{}`: {}", command, e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+    Err(format!("`{}` failed ({}): {}", command, output.status, detail))
+}
+
+impl eframe::App for MainScreen {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame){
+        self.update(ctx);
+    }
+}
+trait Screen {
+    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>>;
+    fn wants_to_exit(&self) -> bool;
+}
+
+// Lists each meal's ingredients under a "Day Meal: Recipe" heading, without merging
+// anything across recipes, for shopping one meal at a time
+fn format_ingredients_by_recipe(meals: &[(String, String, Vec<String>)]) -> String {
+    let mut output = String::new();
+    for (day, recipe_name, ingredients) in meals {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("{}: {}\n", day, recipe_name));
+        for ingredient in ingredients {
+            output.push_str(&format!("  {}\n", ingredient));
+        }
+    }
+    output
+}
+
+// Everything processing a week of recipes would write, computed without touching the filesystem
+struct SchedulePlan {
+    copies: Vec<(PathBuf, PathBuf)>,
+    schedule: String,
+    ingredients: String,
+    // The shopping list again as CSV, with the recipes behind each item
+    ingredients_csv: String,
+    // The same ingredients, listed under the day and recipe that needs them
+    ingredients_by_recipe: String,
+    // Every day of the week in order, with no meals for days left open
+    days: Vec<PlannedDay>,
+}
+
+struct PlannedDay {
+    day: String,
+    // (meal type, recipe name) for each meal planned that day
+    meals: Vec<(String, String)>,
+    total_time: Option<Duration>,
+}
+
+// Every recipe is scaled by plan_scale before its ingredients are listed, so the
+// shopping list covers the whole week at that size. selected_recipes has a row per
+// day with an entry per meal type.
+fn build_schedule(selected_recipes: &[Vec<String>], plan_scale: f32, settings: &Settings) -> Result<SchedulePlan, std::io::Error> {
+    let mut plan = SchedulePlan {
+        copies: Vec::new(),
+        schedule: String::new(),
+        ingredients: String::new(),
+        ingredients_csv: String::new(),
+        ingredients_by_recipe: String::new(),
+        days: Vec::new(),
+    };
+    let days = week_days(settings.week_start);
+    let mut shopping_list: Vec<(String, String)> = Vec::new();
+    let mut meals: Vec<(String, String, Vec<String>)> = Vec::new();
+
+    for (i, day_meals) in selected_recipes.iter().enumerate() {
+        let mut planned = PlannedDay { day: days[i].to_string(), meals: Vec::new(), total_time: None };
+        for (meal, recipe_name) in MEAL_TYPES.iter().zip(day_meals) {
+            if recipe_name.is_empty() {
+                continue;
+            }
+            let recipe_path = find_recipe_path(recipe_name, &settings.recipe_extension).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, format!("No recipe named \"{}\"", recipe_name))
+            })?;
+            let dest_path = Path::new("schedule").join(recipe_file_name(&format!("{}_{}", days[i], meal), &settings.recipe_extension));
+            plan.schedule.push_str(&format!("{} {}: {}\n", days[i], meal, recipe_name));
+            let recipe = parse_recipe_file(&recipe_path)?.scale(plan_scale, settings.decimal_comma);
+            if let Some(time) = recipe_total_time(&recipe) {
+                planned.total_time = Some(planned.total_time.unwrap_or_default() + time);
+            }
+            planned.meals.push((meal.to_string(), recipe_name.clone()));
+            let lines: Vec<String> = recipe.ingreds.iter().map(|ingredient| ingredient.text.clone()).collect();
+            meals.push((format!("{} {}", days[i], meal), recipe_name.clone(), lines.clone()));
+            shopping_list.extend(lines.into_iter().map(|line| (line, recipe_name.clone())));
+            plan.copies.push((recipe_path, dest_path));
+        }
+        plan.days.push(planned);
+    }
+
+    let mut shopping_list = aggregate_ingredients(&shopping_list, settings);
+    if settings.sort_shopping_list {
+        // Sort by the ingredient itself so "2 eggs" files under E, not 2, with the
+        // same ingredient's prep variants kept next to each other
+        shopping_list.sort_by_key(|item| {
+            let ingredient = parse_ingredient(&item.line, settings.decimal_comma);
+            (ingredient.name, ingredient.prep)
+        });
+    }
+    for item in &shopping_list {
+        plan.ingredients.push_str(&item.line);
+        plan.ingredients.push('\n');
+    }
+    plan.ingredients_csv = shopping_list_csv(&shopping_list);
+    plan.ingredients_by_recipe = format_ingredients_by_recipe(&meals);
+
+    Ok(plan)
+}
+
+// Copies the week's recipes into schedule/ and writes the shopping lists and schedule.
+// Recipe copies from an earlier week are removed first, including the old one-per-day
+// <Day>.rec files, so a meal left open this week doesn't keep last week's recipe.
+fn write_schedule(plan: &SchedulePlan, extension: &str) -> Result<(), std::io::Error> {
+    fs::create_dir_all("schedule")?;
+    for entry in fs::read_dir("schedule")?.flatten() {
+        if is_recipe_file(&entry.path(), extension) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    for (recipe_path, dest_path) in &plan.copies {
+        fs::copy(recipe_path, dest_path)?;
+    }
+    let mut ingredients_file = File::create("schedule/ingredients.sup")?;
+    ingredients_file.write_all(plan.ingredients.as_bytes())?;
+    write_file_atomically(Path::new("schedule/ingredients.csv"), plan.ingredients_csv.as_bytes())?;
+    fs::write("schedule/ingredients_by_recipe.txt", &plan.ingredients_by_recipe)?;
+    let mut schedule_file = File::create("schedule/schedule.txt")?;
+    schedule_file.write_all(plan.schedule.as_bytes())?;
+
+    Ok(())
+}
+
+// The cover lines and the recipes, scaled by plan_scale, for the weekly PDF. Days
+// without a recipe are left out. With cooking times turned on, each day's meals are
+// followed by the day's total and the cover ends with the total for the week.
+fn weekly_pdf_contents(selected: &[Vec<String>], plan_scale: f32, settings: &Settings) -> Result<(Vec<String>, Vec<Recipe>), Box<dyn std::error::Error>> {
+    let days = week_days(settings.week_start);
+    let mut cover = Vec::new();
+    let mut recipes = Vec::new();
+    let mut week_total: Option<Duration> = None;
+    for (day, day_meals) in days.iter().zip(selected) {
+        let mut day_total: Option<Duration> = None;
+        for (meal, recipe_name) in MEAL_TYPES.iter().zip(day_meals).filter(|(_, name)| !name.is_empty()) {
+            let recipe_path = find_recipe_path(recipe_name, &settings.recipe_extension)
+                .ok_or_else(|| format!("No recipe named \"{}\"", recipe_name))?;
+            let recipe = parse_recipe_file(&recipe_path)?.scale(plan_scale, settings.decimal_comma);
+            if let Some(time) = recipe_total_time(&recipe) {
+                day_total = Some(day_total.unwrap_or_default() + time);
+            }
+            recipes.push(recipe);
+            cover.push(format!("{} {}: {}", day, meal, recipe_name));
+        }
+        if let (true, Some(day_total)) = (settings.show_times_on_cover, day_total) {
+            cover.push(format!("{} cooking time: {}", day, format_duration(day_total, settings.duration_style)));
+            week_total = Some(week_total.unwrap_or_default() + day_total);
+        }
+    }
+    if recipes.is_empty() {
+        return Err("No recipes are planned for this week".into());
+    }
+    if let Some(week_total) = week_total {
+        cover.push(format!("Cooking time this week: {}", format_duration(week_total, settings.duration_style)));
+    }
+    Ok((cover, recipes))
+}
+
+// One PDF for the week: a cover page listing each planned day, then every recipe on
+// its own pages
+fn generate_weekly_pdf(selected: &[Vec<String>], plan_scale: f32, settings: &Settings) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (cover, recipes) = weekly_pdf_contents(selected, plan_scale, settings)?;
+
+    let (pdf_bytes, page_count) = render_recipes_pdf(&recipes, "Weekly Recipes", &cover, settings)?;
+    let output_path = recipe_pdf_path(&format!("weekly_{}", chrono::Local::now().format("%Y-%m-%d")));
+    write_file_atomically(&output_path, &pdf_bytes)?;
+
+    println!("PDF saved to: {:?} ({} pages)", output_path, page_count);
+
+    Ok(output_path)
+}
+
+// Writes the week's recipes, the at-a-glance grid and the shopping checklist as three
+// PDFs under schedule/ and returns their paths
+fn generate_plan_outputs(selected_recipes: &[Vec<String>], plan_scale: f32, settings: &Settings) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let plan = build_schedule(selected_recipes, plan_scale, settings)?;
+    if plan.copies.is_empty() {
+        return Err("No recipes are planned for this week".into());
+    }
+    let mut recipes = Vec::new();
+    for (recipe_path, _) in &plan.copies {
+        recipes.push(parse_recipe_file(recipe_path)?.scale(plan_scale, settings.decimal_comma));
+    }
+    let times = if settings.show_times_on_cover { Some(settings.duration_style) } else { None };
+    let shopping_items: Vec<String> = plan.ingredients.lines().map(|line| line.to_string()).collect();
+
+    fs::create_dir_all("schedule")?;
+    let outputs = [
+        ("schedule/week_recipes.pdf", render_recipes_pdf(&recipes, "Week's Recipes", &[], settings)?.0),
+        ("schedule/week_at_a_glance.pdf", render_schedule_grid_pdf(&plan.days, times)?),
+        ("schedule/shopping_list.pdf", render_shopping_list_pdf(&shopping_items)?),
+    ];
+    let mut paths = Vec::new();
+    for (path, bytes) in outputs {
+        write_file_atomically(Path::new(path), &bytes)?;
+        paths.push(PathBuf::from(path));
+    }
+    Ok(paths)
+}
+
+const PLANS_DIR: &str = "schedule/plans";
+
+// Names of the plans saved under schedule/plans, without the .plan extension
+fn list_saved_plans() -> Vec<String> {
+    let mut plans: Vec<String> = fs::read_dir(PLANS_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "plan"))
+                .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    plans.sort();
+    plans
+}
+
+// One "Day\tMeal\tRecipeName" line per planned meal
+fn format_plan(days: &[&str], selected_recipes: &[Vec<String>]) -> String {
+    let mut contents = String::new();
+    for (day, day_meals) in days.iter().zip(selected_recipes) {
+        for (meal, recipe) in MEAL_TYPES.iter().zip(day_meals).filter(|(_, recipe)| !recipe.is_empty()) {
+            contents.push_str(&format!("{}\t{}\t{}\n", day, meal, recipe));
+        }
+    }
+    contents
+}
+
+// Reads plan lines back into (day, meal, recipe), ignoring blank lines. Plans saved
+// before meal types were added have "Day\tRecipeName" lines, which are dinners.
+fn parse_plan(contents: &str) -> Vec<(String, String, String)> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(|field| field.trim()).collect();
+            match fields.as_slice() {
+                [day, meal, recipe, ..] => (day.to_string(), meal.to_string(), recipe.to_string()),
+                [day, recipe] => (day.to_string(), MEAL_TYPES[DINNER].to_string(), recipe.to_string()),
+                _ => (line.trim().to_string(), MEAL_TYPES[DINNER].to_string(), String::new()),
+            }
+        })
+        .collect()
+}
+
+const RANDOM_EXCLUSIONS_PATH: &str = "recipes/random_exclusions.txt";
+
+// Recipe names the randomizer should never pick, one per line
+fn load_random_exclusions() -> HashSet<String> {
+    fs::read_to_string(RANDOM_EXCLUSIONS_PATH)
+        .map(|contents| {
+            contents
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_random_exclusions(excluded: &HashSet<String>) -> Result<(), std::io::Error> {
+    let mut names: Vec<&String> = excluded.iter().collect();
+    names.sort();
+    let mut contents = String::new();
+    for name in names {
+        contents.push_str(&format!("{}\n", name));
+    }
+    write_file_atomically(Path::new(RANDOM_EXCLUSIONS_PATH), contents.as_bytes())
+}
+
+const HISTORY_LOG_PATH: &str = "schedule/history.log";
+
+// Adds one "YYYY-MM-DD\tRecipe\tRecipe..." line for a processed week
+fn append_history(recipe_names: &[String], date: NaiveDate) -> Result<(), std::io::Error> {
+    let planned: Vec<&str> = recipe_names.iter().map(|name| name.as_str()).filter(|name| !name.is_empty()).collect();
+    if planned.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all("schedule")?;
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(HISTORY_LOG_PATH)?;
+    writeln!(file, "{}\t{}", date.format("%Y-%m-%d"), planned.join("\t"))
+}
+
+// Recipes from the last `weeks` weeks of the history log. Lines are grouped by the
+// week their date falls in (weeks starting on DAYS[week_start]), so processing the
+// same week twice still only counts as one week.
+fn recent_recipes(weeks: usize, week_start: usize) -> HashSet<String> {
+    let contents = fs::read_to_string(HISTORY_LOG_PATH).unwrap_or_default();
+    let mut by_week: Vec<(NaiveDate, Vec<&str>)> = Vec::new();
+    for line in contents.lines() {
+        let mut fields = line.split('\t');
+        let date = match fields.next().and_then(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()) {
+            Some(date) => date,
+            None => continue,
+        };
+        let offset = (date.weekday().num_days_from_monday() as usize + 7 - week_start % 7) % 7;
+        let week = date - chrono::Duration::days(offset as i64);
+        match by_week.iter_mut().find(|(start, _)| *start == week) {
+            Some((_, names)) => names.extend(fields),
+            None => by_week.push((week, fields.collect())),
+        }
+    }
+    by_week.sort_by_key(|(week, _)| *week);
+    by_week[by_week.len().saturating_sub(weeks)..]
+        .iter()
+        .flat_map(|(_, names)| names.iter())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+const LAST_COOKED_PATH: &str = "recipes/last_cooked.txt";
+// Recipes not cooked for this many days count as neglected
+const NEGLECTED_AFTER_DAYS: i64 = 30;
+
+// "RecipeName\tYYYY-MM-DD" lines recording when each recipe was last in a processed plan
+fn load_last_cooked() -> HashMap<String, NaiveDate> {
+    fs::read_to_string(LAST_COOKED_PATH)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .filter_map(|(name, date)| {
+                    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                        .ok()
+                        .map(|date| (name.trim().to_string(), date))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_last_cooked(last_cooked: &HashMap<String, NaiveDate>) -> Result<(), std::io::Error> {
+    let mut names: Vec<&String> = last_cooked.keys().collect();
+    names.sort();
+    let mut contents = String::new();
+    for name in names {
+        contents.push_str(&format!("{}\t{}\n", name, last_cooked[name].format("%Y-%m-%d")));
+    }
+    write_file_atomically(Path::new(LAST_COOKED_PATH), contents.as_bytes())
+}
+
+// Stamps every planned recipe with the given date
+fn record_cooked(recipe_names: &[String], date: NaiveDate) -> Result<(), std::io::Error> {
+    let mut last_cooked = load_last_cooked();
+    for name in recipe_names.iter().filter(|name| !name.is_empty()) {
+        last_cooked.insert(name.clone(), date);
+    }
+    save_last_cooked(&last_cooked)
+}
+
+// True when a recipe hasn't been cooked in the last `days` days; never cooked counts too
+fn not_cooked_within(last_cooked: Option<&NaiveDate>, today: NaiveDate, days: i64) -> bool {
+    match last_cooked {
+        Some(date) => (today - *date).num_days() >= days,
+        None => true,
+    }
+}
+
+fn describe_last_cooked(last_cooked: Option<&NaiveDate>, today: NaiveDate) -> String {
+    match last_cooked {
+        Some(date) => match (today - *date).num_days() {
+            0 => format!("Last cooked: {} (today)", date.format("%Y-%m-%d")),
+            1 => format!("Last cooked: {} (yesterday)", date.format("%Y-%m-%d")),
+            days => format!("Last cooked: {} ({} days ago)", date.format("%Y-%m-%d"), days),
+        },
+        None => "Never cooked".to_string(),
+    }
+}
+
+struct CreateWeeklyRecipesScreen{
+    wants_to_exit: bool,
+    recipes: Vec<String>,
+    // A row per day with an entry per meal type, indexed [day][meal]
+    selected_recipes: Vec<Vec<String>>,
+    // Index into MEAL_TYPES of the meal tab being planned
+    meal: usize,
+    // Recipe names grouped by category label, as shown in the recipe viewer
+    categories: Vec<(String, Vec<String>)>,
+    // Category each meal picks from; follows selected_recipes when the week start moves
+    day_categories: Vec<Vec<String>>,
+    // Meals Randomize All leaves alone
+    locked: Vec<Vec<bool>>,
+    // Name typed in for "Save Plan", and the plans available to load
+    plan_name: String,
+    saved_plans: Vec<String>,
+    processing_message: String,
+    preview: Option<SchedulePlan>,
+    excluded: HashSet<String>,
+    // First day of the week that selected_recipes[0] refers to
+    week_start: usize,
+    // Servings text for each recipe, read once so the planner badges don't hit the disk every frame
+    recipe_servings: HashMap<String, String>,
+    // Multiplier applied to every recipe in the week
+    plan_scale: f32,
+    // Set while "Generate Everything" runs on its worker thread
+    outputs_job: Option<Receiver<Result<Vec<PathBuf>, String>>>,
+    // Set while "Generate Weekly PDF" runs on its worker thread
+    weekly_pdf_job: Option<Receiver<Result<PathBuf, String>>>,
+}
+
+impl CreateWeeklyRecipesScreen {
+    fn load_recipes(categories: &[(String, Vec<String>)]) -> Vec<String> {
+        let mut recipes: Vec<String> = categories.iter().flat_map(|(_, recipes)| recipes.iter().cloned()).collect();
+        recipes.sort();
+        recipes.dedup();
+        recipes
+    }
+    // Each meal draws from the category named after it. Without one the meal has no
+    // category (and so nothing to randomize) until one is picked, rather than quietly
+    // borrowing another meal's recipes.
+    fn default_category(categories: &[(String, Vec<String>)], meal: &str) -> String {
+        categories
+            .iter()
+            .map(|(label, _)| label.clone())
+            .find(|label| label == meal)
+            .unwrap_or_default()
+    }
+    // Recipes in the category the given day's current meal draws from
+    fn category_recipes(&self, idx: usize) -> &[String] {
+        self.day_categories
+            .get(idx)
+            .and_then(|meals| meals.get(self.meal))
+            .and_then(|category| self.categories.iter().find(|(label, _)| label == category))
+            .map_or(&[][..], |(_, recipes)| recipes.as_slice())
+    }
+    // Recipes the randomizer may pick for a day; excluded ones stay manually selectable
+    fn random_pool(&self, idx: usize) -> Vec<String> {
+        self.category_recipes(idx)
+            .iter()
+            .filter(|recipe| !self.excluded.contains(*recipe))
+            .cloned()
+            .collect()
+    }
+    // Explains why nothing was picked instead of silently assigning blank days
+    fn no_recipes_message(&self, idx: usize) -> String {
+        if self.day_categories[idx][self.meal].is_empty() {
+            let meal = MEAL_TYPES[self.meal].to_lowercase();
+            format!("Warning: No recipes in {}. Add recipes to recipes/{} or pick a category first.", meal, meal)
+        } else if self.category_recipes(idx).is_empty() {
+            format!("Warning: No recipes available to randomize in {}. Add recipes to that folder first.", self.day_categories[idx][self.meal])
+        } else {
+            format!("Warning: Every {} recipe is excluded from randomize. Un-exclude some recipes first.", self.day_categories[idx][self.meal])
+        }
+    }
+    // Samples the current meal without replacement: a recipe only repeats once every
+    // recipe in that day's pool has already been used this week
+    fn randomize_all(&mut self, avoid_recent_weeks: usize) {
+        let mut rng = thread_rng();
+        let recent = recent_recipes(avoid_recent_weeks, self.week_start);
+        let meal = self.meal;
+        // Locked picks count as used so the rest of the week doesn't repeat them
+        let mut used: HashSet<String> = self
+            .selected_recipes
+            .iter()
+            .zip(&self.locked)
+            .filter(|(recipes, locked)| locked[meal] && !recipes[meal].is_empty())
+            .map(|(recipes, _)| recipes[meal].clone())
+            .collect();
+        for idx in 0..self.selected_recipes.len() {
+            if self.locked[idx][meal] {
+                continue;
+            }
+            let pool = self.random_pool(idx);
+            if pool.is_empty() {
+                self.processing_message = self.no_recipes_message(idx);
+                continue;
+            }
+            if pool.iter().all(|recipe| used.contains(recipe)) {
+                for recipe in &pool {
+                    used.remove(recipe);
+                }
+            }
+            let fresh: Vec<&String> = pool.iter().filter(|recipe| !used.contains(*recipe)).collect();
+            // Skip recent weeks' meals unless that would leave nothing to pick
+            let not_recent: Vec<&String> = fresh.iter().copied().filter(|recipe| !recent.contains(*recipe)).collect();
+            let choices = if not_recent.is_empty() { &fresh } else { &not_recent };
+            if let Some(choice) = choices.choose(&mut rng) {
+                used.insert((*choice).clone());
+                self.selected_recipes[idx][meal] = (*choice).clone();
+            }
+        }
+    }
+    fn randomize_single(&mut self, idx: usize, avoid_recent_weeks: usize) {
+        let mut rng = thread_rng();
+        let pool = self.random_pool(idx);
+        if pool.is_empty() {
+            self.processing_message = self.no_recipes_message(idx);
+            return;
+        }
+        // Prefer something not already this meal on another day, if there's anything left
+        let taken: HashSet<&String> = self
+            .selected_recipes
+            .iter()
+            .enumerate()
+            .filter(|(day, _)| *day != idx)
+            .map(|(_, recipes)| &recipes[self.meal])
+            .collect();
+        let fresh: Vec<&String> = pool.iter().filter(|recipe| !taken.contains(recipe)).collect();
+        let candidates = if fresh.is_empty() { pool.iter().collect() } else { fresh };
+        // Then skip recent weeks' meals, the same way Randomize All does
+        let recent = recent_recipes(avoid_recent_weeks, self.week_start);
+        let not_recent: Vec<&String> = candidates.iter().copied().filter(|recipe| !recent.contains(*recipe)).collect();
+        let choices = if not_recent.is_empty() { &candidates } else { &not_recent };
+        if let (Some(choice), Some(recipes)) = (choices.choose(&mut rng), self.selected_recipes.get_mut(idx)) {
+            recipes[self.meal] = (*choice).clone();
+        }
+    }
+    fn process_selected_recipes(&self, settings: &Settings) -> Result<(), std::io::Error> {
+        let plan = build_schedule(&self.selected_recipes, self.plan_scale, settings)?;
+        write_schedule(&plan, &settings.recipe_extension)?;
+        let today = chrono::Local::now().date_naive();
+        let planned: Vec<String> = self.selected_recipes.iter().flatten().cloned().collect();
+        append_history(&planned, today)?;
+        record_cooked(&planned, today)
+    }
+    fn start_generate_everything(&mut self, settings: &Settings) {
+        if self.outputs_job.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.outputs_job = Some(receiver);
+        self.processing_message = "Generating PDFs...".to_string();
+        let selected_recipes = self.selected_recipes.clone();
+        let plan_scale = self.plan_scale;
+        let settings = settings.clone();
+        thread::spawn(move || {
+            let result = generate_plan_outputs(&selected_recipes, plan_scale, &settings).map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+    }
+    fn poll_generate_everything(&mut self) {
+        let result = match &self.outputs_job {
+            Some(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err("worker stopped unexpectedly".to_string()),
+            },
+            None => return,
+        };
+        self.outputs_job = None;
+        self.processing_message = match result {
+            Ok(paths) => {
+                let listed: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                format!("Generated:\n{}", listed.join("\n"))
+            }
+            Err(e) => format!("Error generating PDFs: {}", e),
+        };
+    }
+    fn start_weekly_pdf(&mut self, settings: &Settings) {
+        if self.weekly_pdf_job.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.weekly_pdf_job = Some(receiver);
+        self.processing_message = "Generating weekly PDF...".to_string();
+        let selected_recipes = self.selected_recipes.clone();
+        let plan_scale = self.plan_scale;
+        let settings = settings.clone();
+        thread::spawn(move || {
+            let result = generate_weekly_pdf(&selected_recipes, plan_scale, &settings).map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+    }
+    fn poll_weekly_pdf(&mut self) {
+        let result = match &self.weekly_pdf_job {
+            Some(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err("worker stopped unexpectedly".to_string()),
+            },
+            None => return,
+        };
+        self.weekly_pdf_job = None;
+        self.processing_message = match result {
+            Ok(pdf_path) => match open_external(&pdf_path) {
+                Ok(()) => format!("Weekly PDF saved to {}", pdf_path.display()),
+                Err(e) => format!("Error opening weekly PDF: {}", e),
+            },
+            Err(e) => format!("Error generating weekly PDF: {}", e),
+        };
+    }
+    fn print_week_at_a_glance(&self, settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+        let plan = build_schedule(&self.selected_recipes, self.plan_scale, settings)?;
+        let times = if settings.show_times_on_cover { Some(settings.duration_style) } else { None };
+        let pdf_bytes = render_schedule_grid_pdf(&plan.days, times)?;
+        fs::create_dir_all("schedule")?;
+        let pdf_path = Path::new("schedule/week_at_a_glance.pdf");
+        write_file_atomically(pdf_path, &pdf_bytes)?;
+        open_external(pdf_path)?;
+        Ok(())
+    }
+    // Re-orders the selections so each day keeps its recipe when the week start changes
+    fn set_week_start(&mut self, week_start: usize) {
+        let shift = (week_start + 7 - self.week_start) % 7;
+        self.selected_recipes.rotate_left(shift);
+        self.day_categories.rotate_left(shift);
+        self.locked.rotate_left(shift);
+        self.week_start = week_start;
+    }
+    fn save_plan(&self, name: &str) -> Result<(), std::io::Error> {
+        fs::create_dir_all(PLANS_DIR)?;
+        let contents = format_plan(&week_days(self.week_start), &self.selected_recipes);
+        write_file_atomically(&Path::new(PLANS_DIR).join(format!("{}.plan", name)), contents.as_bytes())
+    }
+    // Fills the meals from a saved plan. Recipes that no longer exist leave their meal
+    // blank and are listed in the returned warnings.
+    fn load_plan(&mut self, name: &str) -> Result<Vec<String>, std::io::Error> {
+        let contents = fs::read_to_string(Path::new(PLANS_DIR).join(format!("{}.plan", name)))?;
+        let days = week_days(self.week_start);
+        let mut missing = Vec::new();
+        self.selected_recipes = empty_week();
+        for (day, meal, recipe) in parse_plan(&contents) {
+            let idx = match days.iter().position(|known| known.eq_ignore_ascii_case(&day)) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let meal_idx = match MEAL_TYPES.iter().position(|known| known.eq_ignore_ascii_case(&meal)) {
+                Some(meal_idx) => meal_idx,
+                None => continue,
+            };
+            if recipe.is_empty() {
+                continue;
+            }
+            if self.recipes.contains(&recipe) {
+                self.selected_recipes[idx][meal_idx] = recipe;
+            } else {
+                missing.push(format!("{} ({} {})", recipe, days[idx], MEAL_TYPES[meal_idx]));
+            }
+        }
+        Ok(missing)
+    }
+    fn clear_processing_message(&mut self) {
+        self.processing_message.clear();
+    }
+}
+
+impl Default for CreateWeeklyRecipesScreen {
+    fn default() -> Self {
+        let settings = Settings::load();
+        let categories = group_recipes_by_category(&recipe_directories(), &settings.recipe_extension);
+        let recipes = Self::load_recipes(&categories);
+        Self {
+            wants_to_exit: false,
+            recipes: recipes.clone(),
+            selected_recipes: empty_week(),
+            meal: DINNER,
+            day_categories: vec![MEAL_TYPES.iter().map(|meal| Self::default_category(&categories, meal)).collect::<Vec<String>>(); 7],
+            locked: vec![vec![false; MEAL_TYPES.len()]; 7],
+            plan_name: String::new(),
+            saved_plans: list_saved_plans(),
+            categories,
+            processing_message: String::new(),
+            preview: None,
+            excluded: load_random_exclusions(),
+            week_start: settings.week_start,
+            recipe_servings: recipes
+                .iter()
+                .filter_map(|name| {
+                    let path = find_recipe_path(name, &settings.recipe_extension)?;
+                    parse_recipe_file(&path).ok().map(|recipe| (name.clone(), recipe.servings))
+                })
+                .collect(),
+            plan_scale: 1.0,
+            outputs_job: None,
+            weekly_pdf_job: None,
+        }
+    }
+}
+
+impl Screen for CreateWeeklyRecipesScreen {
+    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
+        ctx.set_pixels_per_point(3.0);
+
+        let is_dark_mode = app_state.is_dark_mode;
+        let background_color = if is_dark_mode {
+            egui::Color32::from_rgb(30, 30, 30)
+        } else {
+            egui::Color32::WHITE
+        };
+
+        self.poll_generate_everything();
+        self.poll_weekly_pdf();
+        if self.outputs_job.is_some() || self.weekly_pdf_job.is_some() {
+            ctx.request_repaint();
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
+            ui.vertical_centered(|ui| {
+                ui.heading("Create Weekly Recipes Screen");
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 4.0);
+                    ui.label("Week starts on");
+                    let mut week_start = app_state.settings.week_start;
+                    egui::ComboBox::from_id_source("week_start_combo")
+                        .selected_text(DAYS[week_start])
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut week_start, 0, DAYS[0]);
+                            ui.selectable_value(&mut week_start, 6, DAYS[6]);
+                        });
+                    if week_start != app_state.settings.week_start {
+                        self.set_week_start(week_start);
+                        app_state.settings.week_start = week_start;
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 4.0);
+                    ui.label("Household size");
+                    let household_size = egui::DragValue::new(&mut app_state.settings.household_size).clamp_range(0..=20);
+                    if ui.add(household_size).changed() {
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 4.0);
+                    ui.label("Avoid recipes from the last");
+                    let avoid_weeks = egui::DragValue::new(&mut app_state.settings.avoid_recent_weeks).clamp_range(0..=8).suffix(" weeks");
+                    if ui.add(avoid_weeks).changed() {
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 4.0);
+                    ui.label("Scale every recipe by");
+                    ui.add(egui::DragValue::new(&mut self.plan_scale).speed(0.25).clamp_range(0.25..=10.0).suffix("x"));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 4.0);
+                    for (m, meal) in MEAL_TYPES.iter().enumerate() {
+                        ui.selectable_value(&mut self.meal, m, *meal);
+                    }
+                });
+
+                let days = week_days(self.week_start);
+                let household_size = app_state.settings.household_size;
+                let meal = self.meal;
+
+                for (i, day) in days.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_space(ui.available_width() / 4.0);
+                        ui.label(*day);
+                        egui::ComboBox::from_id_source(format!("category_combo_{}_{}", i, meal))
+                            .selected_text(if self.day_categories[i][meal].is_empty() {
+                                "Pick a category"
+                            } else {
+                                self.day_categories[i][meal].as_str()
+                            })
+                            .show_ui(ui, |ui| {
+                                for (label, _) in &self.categories {
+                                    ui.selectable_value(&mut self.day_categories[i][meal], label.clone(), label);
+                                }
+                            });
+                        let mut selected = self.selected_recipes[i][meal].clone();
+                        egui::ComboBox::from_id_source(format!("recipe_combo_{}_{}", i, meal))
+                            .selected_text(&selected)
+                            .show_ui(ui, |ui| {
+                                for recipe in self.category_recipes(i) {
+                                    ui.selectable_value(&mut selected, recipe.clone(), recipe);
+                                }
+                            });
+                        self.selected_recipes[i][meal] = selected;
+                        ui.checkbox(&mut self.locked[i][meal], "🔒");
+                        if ui.button("🎲").clicked() {
+                            self.randomize_single(i, app_state.settings.avoid_recent_weeks);
+                        }
+                        let servings = self.recipe_servings.get(&self.selected_recipes[i][meal]);
+                        match servings.and_then(|servings| servings_cover_household(servings, household_size)) {
+                            Some(true) => {
+                                ui.colored_label(egui::Color32::GREEN, "✔ enough");
+                            }
+                            Some(false) => {
+                                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⚠ scale up");
+                            }
+                            None => {}
+                        }
+                    });
+                }
+                
+                ui.add_space(10.0);
+
+                ui.vertical_centered(|ui| {
+                    if ui.button(format!("Randomize All {}", MEAL_TYPES[meal])).clicked() {
+                        self.randomize_all(app_state.settings.avoid_recent_weeks);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 4.0);
+                    ui.add(egui::TextEdit::singleline(&mut self.plan_name).hint_text("Plan name").desired_width(100.0));
+                    if ui.button("Save Plan").clicked() {
+                        let name = self.plan_name.trim().to_string();
+                        if name.is_empty() || name.contains('/') || name.contains('\\') {
+                            self.processing_message = "Error: enter a plan name without slashes".to_string();
+                        } else {
+                            match self.save_plan(&name) {
+                                Ok(_) => {
+                                    self.processing_message = format!("Saved plan \"{}\".", name);
+                                    self.saved_plans = list_saved_plans();
+                                }
+                                Err(e) => self.processing_message = format!("Error saving plan: {}", e),
+                            }
+                        }
+                    }
+                    let mut chosen_plan = None;
+                    egui::ComboBox::from_id_source("load_plan_combo")
+                        .selected_text("Load Plan")
+                        .show_ui(ui, |ui| {
+                            for plan in &self.saved_plans {
+                                if ui.selectable_label(false, plan).clicked() {
+                                    chosen_plan = Some(plan.clone());
+                                }
+                            }
+                        });
+                    if let Some(plan) = chosen_plan {
+                        self.processing_message = match self.load_plan(&plan) {
+                            Ok(missing) if missing.is_empty() => format!("Loaded plan \"{}\".", plan),
+                            Ok(missing) => format!(
+                                "Warning: loaded plan \"{}\" but these recipes no longer exist: {}",
+                                plan,
+                                missing.join(", ")
+                            ),
+                            Err(e) => format!("Error loading plan: {}", e),
+                        };
+                        self.plan_name = plan;
+                    }
+                });
+
+                ui.collapsing("Exclude From Randomize", |ui| {
+                    for recipe in &self.recipes {
+                        let mut is_excluded = self.excluded.contains(recipe);
+                        if ui.checkbox(&mut is_excluded, recipe).changed() {
+                            if is_excluded {
+                                self.excluded.insert(recipe.clone());
+                            } else {
+                                self.excluded.remove(recipe);
+                            }
+                            if let Err(e) = save_random_exclusions(&self.excluded) {
+                                self.processing_message = format!("Error saving exclusions: {}", e);
+                            }
+                        }
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    if ui.checkbox(&mut app_state.settings.sort_shopping_list, "Sort shopping list alphabetically").changed() {
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                    if ui.checkbox(&mut app_state.settings.group_shopping_by_recipe, "Group shopping list by recipe").changed() {
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                    let mut rounding = app_state.settings.shopping_rounding;
+                    egui::ComboBox::from_label("Shopping amounts")
+                        .selected_text(rounding.label())
+                        .show_ui(ui, |ui| {
+                            for mode in RoundMode::ALL {
+                                ui.selectable_value(&mut rounding, mode, mode.label());
+                            }
+                        });
+                    if rounding != app_state.settings.shopping_rounding {
+                        app_state.settings.shopping_rounding = rounding;
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                    let mut unit_system = app_state.settings.unit_system;
+                    egui::ComboBox::from_label("Shopping units")
+                        .selected_text(unit_system.label())
+                        .show_ui(ui, |ui| {
+                            for system in UnitSystem::ALL {
+                                ui.selectable_value(&mut unit_system, system, system.label());
+                            }
+                        });
+                    if unit_system != app_state.settings.unit_system {
+                        app_state.settings.unit_system = unit_system;
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                    if ui.checkbox(&mut app_state.settings.show_times_on_cover, "Show cooking times on the weekly PDF and week at a glance").changed() {
+                        if let Err(e) = app_state.settings.save() {
+                            self.processing_message = format!("Error saving settings: {}", e);
+                        }
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    if ui.button("Preview (Dry Run)").clicked() {
+                        self.clear_processing_message();
+                        match build_schedule(&self.selected_recipes, self.plan_scale, &app_state.settings) {
+                            Ok(plan) => self.preview = Some(plan),
+                            Err(e) => {
+                                self.preview = None;
+                                self.processing_message = format!("Error during preview: {}", e);
+                            }
+                        }
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    if ui.button("Print Week at a Glance").clicked() {
+                        self.clear_processing_message();
+                        match self.print_week_at_a_glance(&app_state.settings) {
+                            Ok(_) => self.processing_message = "Week at a glance PDF generated.".to_string(),
+                            Err(e) => self.processing_message = format!("Error generating week at a glance: {}", e),
+                        }
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    if ui.add_enabled(self.weekly_pdf_job.is_none(), egui::Button::new("Generate Weekly PDF")).clicked() {
+                        self.clear_processing_message();
+                        self.start_weekly_pdf(&app_state.settings);
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    let generate_button = ui.add_enabled(self.outputs_job.is_none(), egui::Button::new("Generate Everything"));
+                    if generate_button.on_hover_text("Recipes, week at a glance and shopping list PDFs").clicked() {
+                        self.clear_processing_message();
+                        self.start_generate_everything(&app_state.settings);
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    if ui.button("Process Selected Recipes").clicked() {
+                        self.clear_processing_message();
+                        self.preview = None;
+                        match self.process_selected_recipes(&app_state.settings) {
+                            Ok(_) => self.processing_message = "Processing completed successfully.".to_string(),
+                            Err(e) => self.processing_message = format!("Error during processing: {}", e),
+                        }
+                    }
+                });
+
+                ui.vertical_centered(|ui| {
+                    if ui.button("Back").clicked() {
+                        self.clear_processing_message();
+                        self.wants_to_exit = true;
+                    }
+                });
+                ui.vertical_centered(|ui|{
+                    if !self.processing_message.is_empty() {
+                        ui.colored_label(
+                            if self.processing_message.starts_with("Error") {
+                                egui::Color32::RED
+                            } else if self.processing_message.starts_with("Warning") {
+                                egui::Color32::from_rgb(255, 165, 0)
+                            } else {
+                                egui::Color32::GREEN
+                            },
+                            &self.processing_message
+                        );
+                    }
+                });
+
+                if let Some(plan) = &self.preview {
+                    ui.add_space(10.0);
+                    egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        ui.label("Files that would be copied:");
+                        for (recipe_path, dest_path) in &plan.copies {
+                            ui.label(format!("{} -> {}", recipe_path.display(), dest_path.display()));
+                        }
+                        ui.add_space(5.0);
+                        ui.label("Ingredients that would be written:");
+                        let ingredients = if app_state.settings.group_shopping_by_recipe {
+                            &plan.ingredients_by_recipe
+                        } else {
+                            &plan.ingredients
+                        };
+                        for ingredient in ingredients.lines() {
+                            ui.label(ingredient);
+                        }
+                    });
+                }
+
+                // Update text color based on dark mode
+                if is_dark_mode {
+                    ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+                } else {
+                    ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+                }
+            });
+        });
+
+        None
+    }
+
+    fn wants_to_exit(&self) -> bool {
+        self.wants_to_exit
+    }
+}
+
+// recipes/.draft.<extension>, hidden so it isn't listed as a recipe
+fn draft_path(extension: &str) -> PathBuf {
+    Path::new(RECIPES_ROOT).join(recipe_file_name(".draft", extension))
+}
+
+struct CreateRecipeManuallyScreen {
+    wants_to_exit: bool,
+    title: String,
+    from: String,
+    servings: String,
+    prep_time: String,
+    cook_time: String,
+    total_time: String,
+    ingredients: String,
+    tags: String,
+    make_ahead: bool,
+    difficulty: String,
+    rating: Option<u8>,
+    prep_steps: Vec<String>,
+    instructions: Vec<String>,
+    pasted_instructions: String,
+    notes: Vec<String>,
+    // Section layout carried over from a restored draft
+    section_order: Vec<Section>,
+    processing_message: String,
+    // Set once the Title field has been given focus, so later frames don't steal it back
+    title_focused: bool,
+    // Draft left over from a previous session, waiting for the user to restore or discard it
+    pending_draft: Option<Recipe>,
+    last_saved_draft: String,
+    last_draft_save: Instant,
+    // Category folder a new recipe is saved into, and the folders offered for it
+    category_dir: String,
+    category_options: Vec<String>,
+    // File being edited; saving overwrites it in place instead of going to category_dir
+    editing: Option<PathBuf>,
+    // Set after Save is pressed on an existing recipe, until the overwrite is confirmed
+    confirm_overwrite: bool,
+    // Extension new recipes and the draft are saved with
+    recipe_extension: String,
+}
+
+impl Default for CreateRecipeManuallyScreen {
+    fn default() -> Self {
+        let recipe_extension = Settings::load().recipe_extension;
+        Self {
+            wants_to_exit: false,
+            title: String::new(),
+            from: String::new(),
+            servings: String::new(),
+            prep_time: String::new(),
+            cook_time: String::new(),
+            total_time: String::new(),
+            ingredients: String::new(),
+            tags: String::new(),
+            make_ahead: false,
+            difficulty: String::new(),
+            rating: None,
+            prep_steps: vec![String::new()],
+            instructions: vec![String::new()],
+            pasted_instructions: String::new(),
+            notes: vec![String::new()],
+            section_order: Vec::new(),
+            processing_message: String::new(),
+            title_focused: false,
+            pending_draft: parse_recipe_file(&draft_path(&recipe_extension)).ok(),
+            last_saved_draft: String::new(),
+            last_draft_save: Instant::now(),
+            category_dir: Path::new(RECIPES_ROOT).join("generated").to_string_lossy().to_string(),
+            category_options: save_category_options(),
+            editing: None,
+            confirm_overwrite: false,
+            recipe_extension,
+        }
+    }
+}
+
+impl Screen for CreateRecipeManuallyScreen {
+    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
+        ctx.set_pixels_per_point(3.0);
+
+        let is_dark_mode = app_state.is_dark_mode;
+        let background_color = if is_dark_mode {
+            egui::Color32::from_rgb(30, 30, 30)
+        } else {
+            egui::Color32::WHITE
+        };
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
+            
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading(if self.editing.is_some() { "Edit Recipe" } else { "Create Recipe Manually" });
+
+                    ui.add_space(10.0);
+
+                    if let Some(draft) = self.pending_draft.clone() {
+                        ui.label(format!("An unsaved draft \"{}\" was found.", draft.title));
+                        ui.horizontal(|ui| {
+                            if ui.button("Restore Draft").clicked() {
+                                self.restore_draft(&draft);
+                                self.pending_draft = None;
+                            }
+                            if ui.button("Discard Draft").clicked() {
+                                let _ = fs::remove_file(draft_path(&self.recipe_extension));
+                                self.pending_draft = None;
+                            }
+                        });
+                        ui.add_space(10.0);
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Title:");
+                        let title_edit = ui.text_edit_singleline(&mut self.title);
+                        if self.take_initial_focus() {
+                            title_edit.request_focus();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("From:");
+                        ui.text_edit_singleline(&mut self.from);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Servings:");
+                        ui.text_edit_singleline(&mut self.servings);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Prep Time:");
+                        ui.text_edit_singleline(&mut self.prep_time);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Cook Time:");
+                        ui.text_edit_singleline(&mut self.cook_time);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Total Time:");
+                        ui.text_edit_singleline(&mut self.total_time);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Ingredients (one per line):");
+                        ui.text_edit_multiline(&mut self.ingredients);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Tags (comma separated):");
+                        ui.text_edit_singleline(&mut self.tags);
+                    });
+
+                    ui.checkbox(&mut self.make_ahead, "Can be made ahead");
+
+                    ui.horizontal(|ui| {
+                        ui.label("Difficulty:");
+                        ui.text_edit_singleline(&mut self.difficulty);
+                    });
+
+                    egui::ComboBox::from_label("Rating")
+                        .selected_text(self.rating.map_or("Not rated".to_string(), rating_stars))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.rating, None, "Not rated");
+                            for stars in 0..=5 {
+                                ui.selectable_value(&mut self.rating, Some(stars), rating_stars(stars));
+                            }
+                        });
+
+                    // Edits are saved back where the recipe already lives
+                    if self.editing.is_none() {
+                        egui::ComboBox::from_label("Category")
+                            .selected_text(category_label(&self.category_dir))
+                            .show_ui(ui, |ui| {
+                                for dir in &self.category_options {
+                                    ui.selectable_value(&mut self.category_dir, dir.clone(), category_label(dir));
+                                }
+                            });
+                    }
+
+                    // Suggested tags not already on the recipe; clicking one adds it
+                    let current_tags = split_tags(&self.tags);
+                    let suggestions: Vec<String> = suggest_tags(&self.to_recipe())
+                        .into_iter()
+                        .filter(|tag| !current_tags.contains(tag))
+                        .collect();
+                    if !suggestions.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label("Suggested:");
+                            for tag in suggestions {
+                                if ui.selectable_label(false, format!("+ {}", tag)).clicked() {
+                                    if !self.tags.trim().is_empty() {
+                                        self.tags.push_str(", ");
+                                    }
+                                    self.tags.push_str(&tag);
+                                }
+                            }
+                        });
+                    }
+
+                    ui.label("Prep (optional):");
+                    let mut prep_updates = Vec::new();
+                    let mut prep_to_remove: Option<usize> = None;
+                    let mut prep_to_add = false;
+
+                    // Render prep steps
+                    for (idx, step) in self.prep_steps.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}.", idx + 1));
+                            let mut step_text = step.clone();
+                            if ui.text_edit_singleline(&mut step_text).changed() {
+                                prep_updates.push((idx, step_text));
+                            }
+                            if ui.button("-").clicked() && self.prep_steps.len() > 1 {
+                                prep_to_remove = Some(idx);
+                            }
+                        });
+                    }
+
+                    // Add new prep step button
+                    if ui.button("Add Prep Step").clicked() {
+                        prep_to_add = true;
+                    }
+
+                    // Apply changes to prep steps
+                    for (idx, step_text) in prep_updates {
+                        self.prep_steps[idx] = step_text;
+                    }
+
+                    if let Some(idx) = prep_to_remove {
+                        self.prep_steps.remove(idx);
+                    }
+
+                    if prep_to_add {
+                        self.prep_steps.push(String::new());
+                    }
+
+                    ui.add_space(10.0);
+
+                    ui.label("Instructions:");
+                    let mut updates = Vec::new();
+                    let mut instruction_to_remove: Option<usize> = None;
+                    let mut instruction_to_add = false;
+                    let mut instruction_to_note: Option<usize> = None;
+
+                    // Render instructions
+                    for (idx, instruction) in self.instructions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}.", idx + 1));
+                            let mut instruction_text = instruction.clone();
+                            if ui.text_edit_singleline(&mut instruction_text).changed() {
+                                updates.push((idx, instruction_text));
+                            }
+                            if ui.button("-").clicked() && self.instructions.len() > 1 {
+                                instruction_to_remove = Some(idx);
+                            }
+                            if ui.button("To Note").clicked() {
+                                instruction_to_note = Some(idx);
+                            }
+                        });
+                    }
+
+                    // Add new instruction button
+                    if ui.button("Add Instruction").clicked() {
+                        instruction_to_add = true;
+                    }
+
+                    // Apply changes
+                    for (idx, instruction_text) in updates {
+                        self.instructions[idx] = instruction_text;
+                    }
+
+                    if let Some(idx) = instruction_to_remove {
+                        self.instructions.remove(idx);
+                    }
+
+                    if instruction_to_add {
+                        self.instructions.push(String::new());
+                    }
+
+                    if let Some(idx) = instruction_to_note {
+                        transfer_item(&mut self.instructions, idx, &mut self.notes);
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Paste instructions:");
+                        ui.text_edit_multiline(&mut self.pasted_instructions);
+                    });
+
+                    if ui.button("Import Pasted Instructions").clicked() {
+                        let lines: Vec<String> = self.pasted_instructions.lines().map(|line| line.to_string()).collect();
+                        let steps: Vec<String> = merge_wrapped_steps(&lines)
+                            .iter()
+                            .map(|step| strip_step_number(step).to_string())
+                            .collect();
+                        if !steps.is_empty() {
+                            self.instructions = steps;
+                            self.pasted_instructions.clear();
+                        }
+                    }
+
+                    ui.add_space(10.0);
+
+                    ui.label("Notes:");
+                    let mut note_updates = Vec::new();
+                    let mut note_to_remove: Option<usize> = None;
+                    let mut note_to_add = false;
+                    let mut note_to_instruction: Option<usize> = None;
+
+                    // Render notes
+                    for (idx, note) in self.notes.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}.", idx + 1));
+                            let mut note_text = note.clone();
+                            if ui.text_edit_singleline(&mut note_text).changed() {
+                                note_updates.push((idx, note_text));
+                            }
+                            if ui.button("-").clicked() && self.notes.len() > 1 {
+                                note_to_remove = Some(idx);
+                            }
+                            if ui.button("To Step").clicked() {
+                                note_to_instruction = Some(idx);
+                            }
+                        });
+                    }
+
+                    // Add new note button
+                    if ui.button("Add Note").clicked() {
+                        note_to_add = true;
+                    }
+
+                    // Apply changes to notes
+                    for (idx, note_text) in note_updates {
+                        self.notes[idx] = note_text;
+                    }
+
+                    if let Some(idx) = note_to_remove {
+                        self.notes.remove(idx);
+                    }
+
+                    if note_to_add {
+                        self.notes.push(String::new());
+                    }
+
+                    if let Some(idx) = note_to_instruction {
+                        transfer_item(&mut self.notes, idx, &mut self.instructions);
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui.button("Save Recipe").clicked() {
+                        // Titles that differ only by spaces vs underscores share a file name
+                        if self.save_path().exists() {
+                            self.confirm_overwrite = true;
+                        } else {
+                            let result = self.save_recipe();
+                            self.report_save(result);
+                        }
+                    }
+
+                    if self.confirm_overwrite {
+                        ui.label(format!("{} already exists. Overwrite it?", self.save_path().display()));
+                        ui.horizontal(|ui| {
+                            if ui.button("Overwrite").clicked() {
+                                self.confirm_overwrite = false;
+                                let result = self.save_recipe();
+                                self.report_save(result);
+                            }
+                            if self.editing.is_none() && ui.button("Save as Copy").clicked() {
+                                self.confirm_overwrite = false;
+                                let copy_path = unique_recipe_path(Path::new(&self.category_dir), &self.title, &self.recipe_extension);
+                                let result = self.save_recipe_to(&copy_path);
+                                self.report_save(result);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.confirm_overwrite = false;
+                            }
+                        });
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui.button("Back").clicked() {
+                        self.wants_to_exit = true;
+                    }
+
+                    if !self.processing_message.is_empty() {
+                        ui.colored_label(
+                            if self.processing_message.starts_with("Error") { egui::Color32::RED } else { egui::Color32::GREEN },
+                            &self.processing_message
+                        );
+                    }
+                });
+            });
+
+            if is_dark_mode {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+            } else {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+            }
+        });
+
+        if app_state.settings.draft_autosave_secs > 0 {
+            let interval = Duration::from_secs(app_state.settings.draft_autosave_secs);
+            if let Err(e) = self.autosave_draft(interval) {
+                self.processing_message = format!("Error saving draft: {}", e);
+            }
+            ctx.request_repaint_after(interval);
+        }
+
+        None
+    }
+
+    fn wants_to_exit(&self) -> bool {
+        self.wants_to_exit
+    }
+}
+
+// Moves one entry between the editor's notes and instructions lists. Both lists
+// always keep at least one (possibly blank) row to type into.
+fn transfer_item(from: &mut Vec<String>, idx: usize, to: &mut Vec<String>) -> bool {
+    if idx >= from.len() {
+        return false;
+    }
+    let item = from.remove(idx);
+    if from.is_empty() {
+        from.push(String::new());
+    }
+    if to.len() == 1 && to[0].trim().is_empty() {
+        to[0] = item;
+    } else {
+        to.push(item);
+    }
+    true
+}
+
+impl CreateRecipeManuallyScreen {
+    // True only on the first call, so the Title field is focused when the screen opens
+    fn take_initial_focus(&mut self) -> bool {
+        !std::mem::replace(&mut self.title_focused, true)
+    }
+    fn to_recipe(&self) -> Recipe {
+        // One ingredient per line, since an ingredient can itself contain commas.
+        // "For the sauce:" lines start a named group.
+        let mut ingreds = Vec::new();
+        let mut ingredient_groups = Vec::new();
+        for item in self.ingredients.lines().filter(|line| !line.trim().is_empty()) {
+            match ingredient_group_heading(item) {
+                Some(group) => ingredient_groups.push((ingreds.len(), group.to_string())),
+                None => ingreds.push(parse_ingredient(item.trim(), false)),
+            }
+        }
+        Recipe {
+            title: self.title.clone(),
+            from: self.from.clone(),
+            servings: self.servings.clone(),
+            prep_time: self.prep_time.clone(),
+            cook_time: self.cook_time.clone(),
+            total_time: self.total_time.clone(),
+            tags: split_tags(&self.tags),
+            make_ahead: self.make_ahead,
+            difficulty: Some(self.difficulty.trim().to_string()).filter(|text| !text.is_empty()),
+            rating: self.rating,
+            ingreds,
+            ingredient_groups,
+            prep_steps: self.prep_steps.iter().filter(|step| !step.trim().is_empty()).cloned().collect(),
+            instructions: self.instructions
+                .iter()
+                .enumerate()
+                .map(|(idx, instruction)| format!("{}. {}", idx + 1, instruction))
+                .collect(),
+            notes: self.notes.clone(),
+            order: self.section_order.clone(),
+        }
+    }
+
+    // Writes the form to the draft file at most once per interval, and only when it has
+    // changed since the last save. Nothing is written until the draft is restored or
+    // discarded, so an old draft isn't overwritten by a blank form.
+    fn autosave_draft(&mut self, interval: Duration) -> Result<(), std::io::Error> {
+        // Drafts are for new recipes; an edit's original is still on disk
+        if self.editing.is_some() || self.pending_draft.is_some() || self.last_draft_save.elapsed() < interval {
+            return Ok(());
+        }
+        self.last_draft_save = Instant::now();
+        let contents = recipe_to_rec(&self.to_recipe());
+        if contents == self.last_saved_draft || self.is_blank() {
+            return Ok(());
+        }
+        fs::create_dir_all("recipes")?;
+        fs::write(draft_path(&self.recipe_extension), &contents)?;
+        self.last_saved_draft = contents;
+        Ok(())
+    }
+
+    fn is_blank(&self) -> bool {
+        self.title.trim().is_empty()
+            && self.ingredients.trim().is_empty()
+            && self.instructions.iter().all(|step| step.trim().is_empty())
+    }
+
+    // Fills the form back in from a saved draft, undoing the step numbering that
+    // to_recipe applies
+    fn restore_draft(&mut self, draft: &Recipe) {
+        self.title = draft.title.clone();
+        self.from = draft.from.clone();
+        self.servings = draft.servings.clone();
+        self.prep_time = draft.prep_time.clone();
+        self.cook_time = draft.cook_time.clone();
+        self.total_time = draft.total_time.clone();
+        let mut ingredients: Vec<String> = Vec::new();
+        for (group, group_ingredients) in draft.grouped_ingredients() {
+            if !group.is_empty() {
+                ingredients.push(format!("{}:", group));
+            }
+            ingredients.extend(group_ingredients.iter().map(|ingredient| ingredient.text.clone()));
+        }
+        self.ingredients = ingredients.join("\n");
+        self.tags = draft.tags.join(", ");
+        self.make_ahead = draft.make_ahead;
+        self.difficulty = draft.difficulty.clone().unwrap_or_default();
+        self.rating = draft.rating;
+        self.prep_steps = draft.prep_steps.clone();
+        self.instructions = draft.instructions.iter().map(|step| strip_step_number(step).to_string()).collect();
+        self.notes = draft.notes.clone();
+        self.section_order = draft.order.clone();
+        for list in [&mut self.prep_steps, &mut self.instructions, &mut self.notes] {
+            if list.is_empty() {
+                list.push(String::new());
+            }
+        }
+    }
+
+    // Opens the form on an existing recipe file
+    fn edit(path: PathBuf, recipe: &Recipe) -> Self {
+        let mut screen = Self {
+            pending_draft: None,
+            editing: Some(path),
+            ..Self::default()
+        };
+        screen.restore_draft(recipe);
+        screen
+    }
+
+    // Where Save writes: the file being edited, or <category>/<Title>.rec for a new recipe
+    fn save_path(&self) -> PathBuf {
+        match &self.editing {
+            Some(path) => path.clone(),
+            None => recipe_save_path(Path::new(&self.category_dir), &self.title, &self.recipe_extension),
+        }
+    }
+
+    fn save_recipe(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_recipe_to(&self.save_path())
+    }
+
+    fn save_recipe_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        archive_recipe_version(path)?;
+        write_recipe_file(&self.to_recipe(), path)?;
+
+        Ok(())
+    }
+
+    fn report_save(&mut self, result: Result<(), Box<dyn std::error::Error>>) {
+        match result {
+            Ok(()) => {
+                // The draft only ever holds a new recipe, so an edit leaves it alone
+                if self.editing.is_none() {
+                    let _ = fs::remove_file(draft_path(&self.recipe_extension));
+                    self.last_saved_draft.clear();
+                }
+                self.processing_message = "Recipe saved successfully".to_string();
+            }
+            Err(e) => self.processing_message = format!("Error saving recipe: {}", e),
+        }
+    }
+}
+
+// Category folders a new recipe can be saved into: every existing one plus dinner
+// (what the weekly randomizer reads) and generated, even before they exist
+fn save_category_options() -> Vec<String> {
+    let mut dirs = recipe_directories();
+    for folder in ["dinner", "generated"] {
+        let dir = Path::new(RECIPES_ROOT).join(folder).to_string_lossy().to_string();
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+// <dir>/<Title>.rec with spaces in the title turned into underscores
+fn recipe_save_path(dir: &Path, title: &str, extension: &str) -> PathBuf {
+    dir.join(recipe_file_name(&title.replace(" ", "_"), extension))
+}
+
+// Like recipe_save_path, but adds _2, _3, ... until the name isn't taken
+fn unique_recipe_path(dir: &Path, title: &str, extension: &str) -> PathBuf {
+    let stem = title.replace(" ", "_");
+    let mut path = dir.join(recipe_file_name(&stem, extension));
+    let mut suffix = 2;
+    while path.exists() {
+        path = dir.join(recipe_file_name(&format!("{}_{}", stem, suffix), extension));
+        suffix += 1;
+    }
+    path
+}
+
+// Writes a recipe into <dir>, never replacing an existing file with the same name
+fn save_recipe_in(recipe: &Recipe, dir: &Path, extension: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    let file_name = unique_recipe_path(dir, &recipe.title, extension);
+    write_recipe_file(recipe, &file_name)?;
+
+    Ok(file_name)
+}
+
+// Reads a single recipe from a JSON file and saves it as a .rec
+fn import_recipe_json(path: &Path, extension: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let recipe = read_recipe_json(&fs::read_to_string(path)?)?;
+    save_recipe_in(&recipe, Path::new(IMPORTED_RECIPES_DIR), extension)
+}
+
+// Accepts either a schema.org Recipe (the JSON-LD recipe sites embed) or the
+// recipe objects /api/recipes returns
+fn read_recipe_json(contents: &str) -> Result<Recipe, Box<dyn std::error::Error>> {
+    let value: serde_json::Value = serde_json::from_str(contents)?;
+    let recipe = match find_json_ld_recipe(&value) {
+        Some(json_ld) => recipe_from_json_ld(json_ld),
+        None => serde_json::from_value(value)?,
+    };
+    if recipe.title.trim().is_empty() {
+        return Err("the recipe has no title".into());
+    }
+    Ok(recipe)
+}
+
+// The object with "@type": "Recipe", whether it is the document itself, inside an
+// array, or inside an "@graph"
+fn find_json_ld_recipe(value: &serde_json::Value) -> Option<&serde_json::Value> {
+    match value {
+        serde_json::Value::Array(items) => items.iter().find_map(find_json_ld_recipe),
+        serde_json::Value::Object(object) => {
+            let is_recipe = match object.get("@type") {
+                Some(serde_json::Value::String(kind)) => kind == "Recipe",
+                Some(serde_json::Value::Array(kinds)) => kinds.iter().any(|kind| kind == "Recipe"),
+                _ => false,
+            };
+            if is_recipe {
+                Some(value)
+            } else {
+                object.get("@graph").and_then(find_json_ld_recipe)
+            }
+        }
+        _ => None,
+    }
+}
+
+// Every string in a JSON-LD field that may be a string, a number, an object with a
+// "name" (authors) or "text" (HowToStep), an array of those, or a HowToSection
+// whose steps are under "itemListElement"
+fn json_ld_texts(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(text) => vec![text.trim().to_string()],
+        serde_json::Value::Number(number) => vec![number.to_string()],
+        serde_json::Value::Array(items) => items.iter().flat_map(json_ld_texts).collect(),
+        serde_json::Value::Object(object) => match object.get("itemListElement") {
+            Some(steps) => json_ld_texts(steps),
+            None => object.get("text").or_else(|| object.get("name")).map(json_ld_texts).unwrap_or_default(),
+        },
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .filter(|text| !text.is_empty())
+    .collect()
+}
+
+// Parses ISO 8601 durations such as "PT1H30M", the form schema.org uses for times
+fn parse_iso_duration(text: &str) -> Option<Duration> {
+    let time = text.trim().strip_prefix("PT")?;
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    for c in time.chars() {
+        match c {
+            'H' | 'M' | 'S' => {
+                let value: f64 = number.parse().ok()?;
+                minutes += match c {
+                    'H' => value * 60.0,
+                    'M' => value,
+                    _ => value / 60.0,
+                };
+                number.clear();
+            }
+            _ => number.push(c),
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs((minutes * 60.0).round() as u64))
+}
+
+// Maps a schema.org Recipe onto the .rec fields. Category, cuisine and keywords all
+// become tags, and the yield becomes Servings.
+fn recipe_from_json_ld(json_ld: &serde_json::Value) -> Recipe {
+    let field = |key: &str| json_ld.get(key).map(json_ld_texts).unwrap_or_default();
+    let time = |key: &str| match field(key).first() {
+        Some(text) => parse_iso_duration(text).map_or(text.clone(), |duration| format_duration(duration, DurationStyle::Long)),
+        None => String::new(),
+    };
+    let mut tags = Vec::new();
+    for key in ["recipeCategory", "recipeCuisine", "keywords"] {
+        for tag in field(key).iter().flat_map(|text| split_tags(text)) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    let instructions = field("recipeInstructions")
+        .iter()
+        .flat_map(|text| text.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect::<Vec<_>>())
+        .enumerate()
+        .map(|(idx, step)| format!("{}. {}", idx + 1, strip_step_number(&step)))
+        .collect();
+    Recipe {
+        title: field("name").first().cloned().unwrap_or_default(),
+        from: field("author").join(", "),
+        servings: field("recipeYield").first().cloned().unwrap_or_default(),
+        prep_time: time("prepTime"),
+        cook_time: time("cookTime"),
+        total_time: time("totalTime"),
+        tags,
+        ingreds: field("recipeIngredient").iter().map(|line| parse_ingredient(line, false)).collect(),
+        instructions,
+        ..Recipe::default()
+    }
+}
+
+struct LibraryHealthScreen {
+    wants_to_exit: bool,
+    issues: Vec<Issue>,
+}
+
+impl Default for LibraryHealthScreen {
+    fn default() -> Self {
+        Self {
+            wants_to_exit: false,
+            issues: audit_library(&recipe_directories(), &Settings::load().recipe_extension),
+        }
+    }
+}
+
+impl Screen for LibraryHealthScreen {
+    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
+        ctx.set_pixels_per_point(3.0);
+
+        let is_dark_mode = app_state.is_dark_mode;
+        let background_color = if is_dark_mode {
+            egui::Color32::from_rgb(30, 30, 30)
+        } else {
+            egui::Color32::WHITE
+        };
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Library Health");
+
+                    ui.add_space(10.0);
+
+                    if self.issues.is_empty() {
+                        ui.colored_label(egui::Color32::GREEN, "No problems found");
+                    } else {
+                        ui.label(format!("{} problem(s) found:", self.issues.len()));
+                        for issue in &self.issues {
+                            ui.label(issue.describe());
+                        }
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui.button("Rescan").clicked() {
+                        self.issues = audit_library(&recipe_directories(), &app_state.settings.recipe_extension);
+                    }
+
+                    if ui.button("Back").clicked() {
+                        self.wants_to_exit = true;
+                    }
+                });
+            });
+
+            if is_dark_mode {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+            } else {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+            }
+        });
+
+        None
+    }
+
+    fn wants_to_exit(&self) -> bool {
+        self.wants_to_exit
+    }
+}
+
+// A recipe file on the selection screen, with the category it was found under.
+// Keeping the path means two folders can hold recipes with the same name.
+#[derive(Clone, PartialEq)]
+struct RecipeEntry {
+    name: String,
+    category: String,
+    path: PathBuf,
+}
+
+// Every recipe file in the given category folders, folder by folder, sorted by name
+fn list_recipe_entries(dirs: &[String], extension: &str) -> Vec<RecipeEntry> {
+    let mut entries = Vec::new();
+    for dir in dirs {
+        let category = category_label(dir);
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(files) => files.flatten().map(|file| file.path()).filter(|path| is_recipe_file(path, extension)).collect(),
+            Err(_) => continue,
+        };
+        paths.sort();
+        for path in paths {
+            if let Some(stem) = path.file_stem() {
+                entries.push(RecipeEntry { name: stem.to_string_lossy().to_string(), category: category.clone(), path: path.clone() });
+            }
+        }
+    }
+    entries
+}
+
+struct RecipeSelectionScreen {
+    selected_recipe: Option<RecipeEntry>,
+    recipes: Vec<RecipeEntry>,
+    // Category labels in folder order, for the filter and the collapsible sections
+    categories: Vec<String>,
+    // Only this category is listed; None lists every category
+    category_filter: Option<String>,
+    // Every recipe parsed once, for searching
+    parsed_recipes: Vec<(RecipeEntry, Recipe)>,
+    search_query: String,
+    make_ahead_recipes: HashSet<PathBuf>,
+    make_ahead_only: bool,
+    // When each recipe was last in a processed plan
+    last_cooked: HashMap<String, NaiveDate>,
+    neglected_only: bool,
+    wants_to_exit: bool,
+    processing_message: String,
+    pdf_generated: bool,
+    current_pdf_path: Option<PathBuf>,
+    serve_time: String,
+    pdf_in_progress: bool,
+    pdf_job: Option<Receiver<PdfJobResult>>,
+    viewed_recipe: Option<Recipe>,
+    viewed_entry: Option<RecipeEntry>,
+    // Newest archived copy of the viewed recipe, looked up when the selection changes
+    previous_version: Option<PathBuf>,
+    scale_factor: f32,
+    guest_count: String,
+    portion_per_guest: String,
+    target_servings: String,
+    // Set after Delete Recipe is pressed, until the deletion is confirmed or cancelled
+    confirm_delete: bool,
+}
+
+// Outcome of a PDF generation running off the UI thread
+struct PdfJobResult {
+    pdf_path: Option<PathBuf>,
+    message: String,
+    generated: bool,
+}
+
+impl Default for RecipeSelectionScreen {
+    fn default() -> Self {
+        Self {
+            selected_recipe: None,
+            recipes: Vec::new(),
+            categories: Vec::new(),
+            category_filter: None,
+            parsed_recipes: Vec::new(),
+            search_query: String::new(),
+            make_ahead_recipes: HashSet::new(),
+            make_ahead_only: false,
+            last_cooked: HashMap::new(),
+            neglected_only: false,
+            wants_to_exit: false,
+            processing_message: String::new(),
+            pdf_generated: false,
+            current_pdf_path: None,
+            serve_time: "18:00".to_string(),
+            pdf_in_progress: false,
+            pdf_job: None,
+            viewed_recipe: None,
+            viewed_entry: None,
+            previous_version: None,
+            scale_factor: 1.0,
+            guest_count: String::new(),
+            portion_per_guest: "1".to_string(),
+            target_servings: String::new(),
+            confirm_delete: false,
+        }
+    }
+}
+
+impl RecipeSelectionScreen {
+    // Generates the PDF for the selected recipe; shared by the button and Ctrl+P
+    fn generate_selected_pdf(&mut self, settings: &Settings) {
+        let recipe_path = match &self.selected_recipe {
+            Some(entry) => entry.path.clone(),
+            None => return,
+        };
+        if recipe_path.exists() {
+            self.start_pdf_generation(recipe_path, self.scale_factor, settings.clone());
+        } else {
+            self.processing_message = "Recipe file not found".to_string();
+            self.pdf_generated = false;
+        }
+    }
+
+    // Ctrl+P (Cmd+P on macOS) does the same as the Generate PDF button, which is only
+    // enabled while a recipe is selected and no generation is running
+    fn handle_print_shortcut(&mut self, pressed: bool, settings: &Settings) {
+        if pressed && self.selected_recipe.is_some() && !self.pdf_in_progress {
+            self.generate_selected_pdf(settings);
+        }
+    }
+
+    // Generates and opens the PDF on a worker thread. Returns false without doing
+    // anything while a previous generation is still running.
+    fn start_pdf_generation(&mut self, recipe_path: PathBuf, scale_factor: f32, settings: Settings) -> bool {
+        if self.pdf_in_progress {
+            return false;
+        }
+        self.pdf_in_progress = true;
+        self.processing_message = "Generating PDF...".to_string();
+
+        let (sender, receiver) = mpsc::channel();
+        self.pdf_job = Some(receiver);
+        thread::spawn(move || {
+            let result = match generate_recipe_pdf(&recipe_path, scale_factor, &settings) {
+                Ok(pdf_path) => match open_external(&pdf_path) {
+                    Ok(()) => PdfJobResult {
+                        pdf_path: Some(pdf_path),
+                        message: "PDF generated and opened successfully".to_string(),
+                        generated: true,
+                    },
+                    Err(e) => PdfJobResult {
+                        pdf_path: Some(pdf_path),
+                        message: format!("Error opening PDF: {}", e),
+                        generated: false,
+                    },
+                },
+                Err(e) => PdfJobResult {
+                    pdf_path: None,
+                    message: format!("Error generating PDF: {}", e),
+                    generated: false,
+                },
+            };
+            let _ = sender.send(result);
+        });
+        true
+    }
+
+    // Picks up a finished generation and re-enables the button
+    fn poll_pdf_generation(&mut self) {
+        let result = match &self.pdf_job {
+            Some(receiver) => match receiver.try_recv() {
+                Ok(result) => Some(result),
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => None,
+            },
+            None => return,
+        };
+        self.pdf_job = None;
+        self.pdf_in_progress = false;
+        match result {
+            Some(result) => {
+                if result.pdf_path.is_some() {
+                    self.current_pdf_path = result.pdf_path;
+                }
+                self.processing_message = result.message;
+                self.pdf_generated = result.generated;
+            }
+            None => {
+                self.processing_message = "Error generating PDF: worker stopped unexpectedly".to_string();
+                self.pdf_generated = false;
+            }
+        }
+    }
+
+    // Opens the screen with a recipe already selected and shown
+    fn with_recipe(name: String, extension: &str) -> Self {
+        let mut screen = Self::default();
+        screen.load_recipes(extension);
+        screen.selected_recipe = screen.recipes.iter().find(|entry| entry.name == name).cloned();
+        screen
+    }
+
+    // Parses the selected recipe for display whenever the selection changes
+    fn refresh_viewed_recipe(&mut self) {
+        if self.viewed_entry == self.selected_recipe {
+            return;
+        }
+        self.viewed_entry = self.selected_recipe.clone();
+        self.scale_factor = 1.0;
+        self.confirm_delete = false;
+        self.viewed_recipe = None;
+        self.previous_version = self.selected_recipe.as_ref().and_then(|entry| recipe_versions(&entry.path).pop());
+        if let Some(entry) = &self.selected_recipe {
+            let path = &entry.path;
+            match parse_recipe_cached(path) {
+                Ok(recipe) => self.viewed_recipe = Some(recipe),
+                // The parse error names the line, so the file can be fixed by hand
+                Err(e) => self.processing_message = format!("Error reading {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn load_recipes(&mut self, extension: &str) {
+        self.recipes = list_recipe_entries(&recipe_directories(), extension);
+        self.categories.clear();
+        for entry in &self.recipes {
+            if !self.categories.contains(&entry.category) {
+                self.categories.push(entry.category.clone());
+            }
+        }
+        self.parsed_recipes = self
+            .recipes
+            .iter()
+            .filter_map(|entry| parse_recipe_cached(&entry.path).ok().map(|recipe| (entry.clone(), recipe)))
+            .collect();
+        self.make_ahead_recipes = self
+            .parsed_recipes
+            .iter()
+            .filter(|(_, recipe)| passes_make_ahead_filter(recipe, true))
+            .map(|(entry, _)| entry.path.clone())
+            .collect();
+        self.last_cooked = load_last_cooked();
+    }
+
+    // Whether a recipe survives the category filter and the make-ahead and
+    // "not cooked lately" checkboxes
+    fn passes_filters(&self, entry: &RecipeEntry) -> bool {
+        let today = chrono::Local::now().date_naive();
+        self.category_filter.as_ref().is_none_or(|category| *category == entry.category)
+            && (!self.make_ahead_only || self.make_ahead_recipes.contains(&entry.path))
+            && (!self.neglected_only || not_cooked_within(self.last_cooked.get(&entry.name), today, NEGLECTED_AFTER_DAYS))
+    }
+
+    // Recipes matching the search, best match first
+    fn search_results(&self) -> Vec<RecipeEntry> {
+        let mut scored: Vec<(f32, &RecipeEntry)> = self
+            .parsed_recipes
+            .iter()
+            .filter(|(entry, _)| self.passes_filters(entry))
+            .map(|(entry, recipe)| (score_match(recipe, &self.search_query), entry))
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        scored.into_iter().map(|(_, entry)| entry.clone()).collect()
+    }
+
+    fn print_pdf(&self, pdf_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            Command::new("cmd")
+                .args(&["/C", "start", "", "/B", pdf_path.to_str().unwrap()])
+                .creation_flags(CREATE_NO_WINDOW)
+                .spawn()?;
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Command::new("lpr")
+                .arg(pdf_path)
+                .spawn()?;
+        }
+        #[cfg(target_os = "linux")]
+        {
+            Command::new("lpr")
+                .arg(pdf_path)
+                .spawn()?;
+        }
+        Ok(())
+    }
+}
+
+impl Screen for RecipeSelectionScreen {
+    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
+        ctx.set_pixels_per_point(3.0);
+
+        let is_dark_mode = app_state.is_dark_mode;
+        let background_color = if is_dark_mode {
+            egui::Color32::from_rgb(30, 30, 30)
+        } else {
+            egui::Color32::WHITE
+        };
+
+        if self.categories.is_empty() {
+            self.load_recipes(&app_state.settings.recipe_extension);
+        }
+
+        self.poll_pdf_generation();
+        if self.pdf_in_progress {
+            ctx.request_repaint();
+        }
+
+        self.refresh_viewed_recipe();
+
+        let print_shortcut = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::P));
+        self.handle_print_shortcut(print_shortcut, &app_state.settings);
+
+        let decimal_comma = app_state.settings.decimal_comma;
+        let duration_style = app_state.settings.duration_style;
+        let mut next_screen: Option<Box<dyn Screen>> = None;
+        let mut edit_opened = false;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
+            
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Select Recipe to View");
+
+                    ui.add_space(10.0);
+
+                    ui.checkbox(&mut self.make_ahead_only, "Make-ahead only");
+                    ui.checkbox(&mut self.neglected_only, format!("Not cooked in {} days", NEGLECTED_AFTER_DAYS));
+
+                    egui::ComboBox::from_label("Category")
+                        .selected_text(self.category_filter.as_deref().unwrap_or("All"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.category_filter, None, "All");
+                            for category in &self.categories {
+                                ui.selectable_value(&mut self.category_filter, Some(category.clone()), category);
+                            }
+                        });
+
+                    ui.add(egui::TextEdit::singleline(&mut self.search_query).hint_text("Search recipes"));
+
+                    if !self.search_query.trim().is_empty() {
+                        let results = self.search_results();
+                        if results.is_empty() {
+                            ui.label("No recipes match");
+                        }
+                        // A single match is almost certainly the one being looked for
+                        if let [only_match] = results.as_slice() {
+                            if self.selected_recipe.as_ref() != Some(only_match) {
+                                self.selected_recipe = Some(only_match.clone());
+                            }
+                        }
+                        for entry in results {
+                            let label = format!("{} ({})", entry.name, entry.category);
+                            ui.selectable_value(&mut self.selected_recipe, Some(entry), label);
+                        }
+                    } else {
+                        // One collapsible section per recipe folder
+                        for category in &self.categories {
+                            if self.category_filter.as_ref().is_some_and(|filter| filter != category) {
+                                continue;
+                            }
+                            let shown: Vec<&RecipeEntry> = self
+                                .recipes
+                                .iter()
+                                .filter(|entry| entry.category == *category && self.passes_filters(entry))
+                                .collect();
+                            egui::CollapsingHeader::new(format!("{} ({})", category, shown.len()))
+                                .id_source(format!("category_{}", category))
+                                .show(ui, |ui| {
+                                    for entry in shown {
+                                        ui.selectable_value(&mut self.selected_recipe, Some(entry.clone()), &entry.name);
+                                    }
+                                });
+                        }
+                    }
+
+                    ui.add_space(10.0);
+
+                    if let Some(selected_recipe) = self.selected_recipe.clone() {
+                        let generate_button = ui.add_enabled(!self.pdf_in_progress, egui::Button::new("Generate PDF"));
+                        if generate_button.on_hover_text("Ctrl+P").clicked() {
+                            self.generate_selected_pdf(&app_state.settings);
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label("Serve at (HH:MM):");
+                            ui.text_edit_singleline(&mut self.serve_time);
+                        });
+
+                        if ui.button("Delete Recipe").clicked() {
+                            self.confirm_delete = true;
+                        }
+                        if self.confirm_delete {
+                            ui.label(format!("Delete \"{}\"? This can't be undone.", selected_recipe.name));
+                            ui.horizontal(|ui| {
+                                if ui.button("Delete").clicked() {
+                                    self.confirm_delete = false;
+                                    let recipe_path = selected_recipe.path.clone();
+                                    if !recipe_path.exists() {
+                                        self.processing_message = "Recipe file not found".to_string();
+                                    } else {
+                                        match fs::remove_file(&recipe_path) {
+                                            Ok(()) => {
+                                                self.processing_message = format!("Deleted {}", recipe_path.display());
+                                                self.selected_recipe = None;
+                                                self.load_recipes(&app_state.settings.recipe_extension);
+                                            }
+                                            Err(e) => self.processing_message = format!("Error deleting recipe: {}", e),
+                                        }
+                                    }
+                                }
+                                if ui.button("Cancel").clicked() {
+                                    self.confirm_delete = false;
+                                }
+                            });
+                        }
+
+                        if ui.button("Export Markdown").clicked() {
+                            match parse_recipe_cached(&selected_recipe.path) {
+                                Ok(recipe) => {
+                                    let recipe = recipe.scaled_for_output(self.scale_factor, decimal_comma);
+                                    // Saved beside the PDFs, as <title>.md
+                                    let md_path = recipe_pdf_path(&recipe.title).with_extension("md");
+                                    match write_file_atomically(&md_path, export_recipe_markdown(&recipe).as_bytes()) {
+                                        Ok(()) => self.processing_message = format!("Markdown saved to {}", md_path.display()),
+                                        Err(e) => self.processing_message = format!("Error saving Markdown: {}", e),
+                                    }
+                                }
+                                Err(e) => self.processing_message = format!("Error parsing recipe: {}", e),
+                            }
+                        }
+
+                        if ui.button("Cooking Timeline").clicked() {
+                            let recipe_path = selected_recipe.path.clone();
+                            match NaiveTime::parse_from_str(self.serve_time.trim(), "%H:%M") {
+                                Ok(serve_at) => match parse_recipe_cached(&recipe_path) {
+                                    Ok(recipe) => {
+                                        let timeline = build_timeline(&recipe, serve_at);
+                                        if timeline.is_empty() {
+                                            self.processing_message = "No timed steps found in this recipe".to_string();
+                                        } else {
+                                            self.processing_message = timeline
+                                                .iter()
+                                                .map(|entry| {
+                                                    let lead = format_duration(Duration::from_secs(entry.minutes_before as u64 * 60), duration_style);
+                                                    format!("{} (T-{}): {}", entry.start.format("%H:%M"), lead, entry.step)
+                                                })
+                                                .collect::<Vec<String>>()
+                                                .join("\n");
+                                        }
+                                    }
+                                    Err(e) => self.processing_message = format!("Error parsing recipe: {}", e),
+                                },
+                                Err(_) => self.processing_message = "Serve time must look like 18:30".to_string(),
+                            }
+                        }
+
+                        if self.pdf_generated && ui.button("Print PDF").clicked() {
+                            if let Some(pdf_path) = &self.current_pdf_path {
+                                match self.print_pdf(pdf_path) {
+                                    Ok(_) => self.processing_message = "PDF sent to printer successfully".to_string(),
+                                    Err(e) => self.processing_message = format!("Error printing PDF: {}", e),
+                                }
+                            } else {
+                                self.processing_message = "No PDF generated to print".to_string();
+                            }
+                        }
+
+                        if let Some(recipe) = &self.viewed_recipe {
+                            ui.add_space(10.0);
+                            let today = chrono::Local::now().date_naive();
+                            ui.label(describe_last_cooked(self.last_cooked.get(&selected_recipe.name), today));
+                            let recipe_path = selected_recipe.path.clone();
+                            ui.horizontal(|ui| {
+                                if ui.button("Halve").clicked() {
+                                    self.scale_factor *= 0.5;
+                                }
+                                if ui.button("Double").clicked() {
+                                    self.scale_factor *= 2.0;
+                                }
+                                if ui.button("Cook Mode").clicked() {
+                                    next_screen = Some(Box::new(CookModeScreen::new(recipe.clone(), self.scale_factor)) as Box<dyn Screen>);
+                                }
+                                if ui.button("Edit Recipe").clicked() {
+                                    next_screen = Some(Box::new(CreateRecipeManuallyScreen::edit(recipe_path.clone(), recipe)) as Box<dyn Screen>);
+                                    edit_opened = true;
+                                }
+                            });
+
+                            if self.scale_factor != 1.0 {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("Scaling: {}", describe_scale_factor(self.scale_factor)));
+                                    if ui.button("Reset").clicked() {
+                                        self.scale_factor = 1.0;
+                                        self.target_servings.clear();
+                                    }
+                                });
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.label("Servings:");
+                                ui.add(egui::TextEdit::singleline(&mut self.target_servings).desired_width(40.0));
+                                if ui.button("Scale").clicked() {
+                                    match (parse_quantity(&self.target_servings, decimal_comma), parse_servings(&recipe.servings)) {
+                                        (Some(target), Some(range)) if target > 0.0 => {
+                                            // Ranges like "4-6" scale from their lower bound
+                                            self.scale_factor = target / range.min;
+                                            let scaled = recipe.scale(self.scale_factor, decimal_comma);
+                                            let lines: Vec<String> = scaled.ingreds.iter().map(|ingredient| ingredient.to_string()).collect();
+                                            self.processing_message = format!("Scaled to {} servings:\n{}", format_amount(target), lines.join("\n"));
+                                        }
+                                        (_, None) => {
+                                            self.processing_message = format!("Can't work out how many servings \"{}\" makes", recipe.servings);
+                                        }
+                                        _ => self.processing_message = "Enter the number of servings you need".to_string(),
+                                    }
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label("Guests:");
+                                ui.add(egui::TextEdit::singleline(&mut self.guest_count).desired_width(40.0));
+                                ui.label("Servings per guest:");
+                                ui.add(egui::TextEdit::singleline(&mut self.portion_per_guest).desired_width(40.0));
+                                if ui.button("Calculate Portions").clicked() {
+                                    let guests = self.guest_count.trim().parse::<u32>();
+                                    let per_guest = parse_quantity(&self.portion_per_guest, decimal_comma);
+                                    match (guests, per_guest) {
+                                        (Ok(guests), Some(per_guest)) => {
+                                            match portions_for(recipe, guests, per_guest as f64, decimal_comma) {
+                                                Ok((factor, scaled)) => {
+                                                    self.scale_factor = factor as f32;
+                                                    let lines: Vec<String> = scaled.ingreds.iter().map(|ingredient| ingredient.to_string()).collect();
+                                                    self.processing_message = format!(
+                                                        "{} servings needed for {} guests:\n{}",
+                                                        format_quantity(guests as f32 * per_guest),
+                                                        guests,
+                                                        lines.join("\n")
+                                                    );
+                                                }
+                                                Err(e) => self.processing_message = e,
+                                            }
+                                        }
+                                        _ => self.processing_message = "Enter a whole number of guests and a portion size".to_string(),
+                                    }
+                                }
+                            });
+
+                            let scaled = recipe.scale(self.scale_factor, decimal_comma);
+                            ui.heading(&scaled.title);
+                            let source = parse_source(&scaled.from);
+                            if let Some(name) = &source.name {
+                                ui.label(format!("From: {}", name));
+                            }
+                            if let Some(url) = &source.url {
+                                ui.hyperlink_to("View original recipe", url);
+                            }
+                            match describe_scaled_servings(&scaled.servings, self.scale_factor) {
+                                Some(phrase) if self.scale_factor != 1.0 => {
+                                    ui.label(format!("Servings: {} ({})", scaled.servings, phrase));
+                                }
+                                _ => {
+                                    ui.label(format!("Servings: {}", scaled.servings));
+                                }
+                            }
+                            if scaled.make_ahead {
+                                ui.colored_label(egui::Color32::LIGHT_BLUE, "Make Ahead");
+                            }
+                            if let Some(difficulty) = &scaled.difficulty {
+                                ui.label(format!("Difficulty: {}", difficulty));
+                            }
+                            if let Some(rating) = scaled.rating {
+                                ui.label(format!("Rating: {}", rating_stars(rating)));
+                            }
+                            if !scaled.tags.is_empty() {
+                                ui.label(format!("Tags: {}", scaled.tags.join(", ")));
+                            }
+                            for (label, time) in [("Prep Time", &scaled.prep_time), ("Cook Time", &scaled.cook_time), ("Total Time", &scaled.total_time)] {
+                                if !time.trim().is_empty() {
+                                    ui.label(format!("{}: {}", label, display_time(time, duration_style)));
+                                }
+                            }
+                            if self.scale_factor == 1.0 {
+                                ui.label("Ingredients:");
+                            } else {
+                                ui.label(format!("Ingredients (scaled x{}):", format_quantity(self.scale_factor)));
+                            }
+                            for (group, ingredients) in scaled.grouped_ingredients() {
+                                if !group.is_empty() {
+                                    ui.label(egui::RichText::new(group).strong());
+                                }
+                                for ingredient in ingredients {
+                                    ui.label(format!("• {}", ingredient));
+                                }
+                            }
+                            if !scaled.instructions.is_empty() {
+                                ui.label("Instructions:");
+                                for step in format_steps(&scaled.instructions, app_state.settings.bulleted_steps) {
+                                    ui.label(step);
+                                }
+                            }
+                            // One label per note so multi-line notes keep their structure
+                            let notes: Vec<&String> = scaled.notes.iter().filter(|note| !note.trim().is_empty()).collect();
+                            if !notes.is_empty() {
+                                ui.label("Notes:");
+                                for note in notes {
+                                    ui.label(note.as_str());
+                                }
+                            }
+
+                            if let Some(previous_path) = &self.previous_version {
+                                if ui.button("Compare With Previous Version").clicked() {
+                                    match parse_recipe_file(previous_path) {
+                                        Ok(previous) => {
+                                            let diff = diff_recipes(&previous, recipe);
+                                            self.processing_message = if diff.is_empty() {
+                                                "No changes since the previous version".to_string()
+                                            } else {
+                                                diff.describe()
+                                            };
+                                        }
+                                        Err(e) => self.processing_message = format!("Error reading previous version: {}", e),
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui.button("Back").clicked() {
+                        self.wants_to_exit = true;
+                    }
+
+                    ui.add_space(10.0);
+
+                    if !self.processing_message.is_empty() {
+                        ui.label(&self.processing_message);
+                    }
+                });
+            });
+
+            if is_dark_mode {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+            } else {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+            }
+        });
+
+        // Re-read everything when coming back, since the edit may have changed the file
+        if edit_opened {
+            self.categories.clear();
+            self.viewed_entry = None;
+        }
+
+        next_screen
+    }
+
+    fn wants_to_exit(&self) -> bool {
+        self.wants_to_exit
+    }
+}
+
+// Walks through a recipe one step at a time. The servings and scale controls stay
+// pinned at the top so amounts can be changed partway through without losing the step.
+struct CookModeScreen {
+    wants_to_exit: bool,
+    recipe: Recipe,
+    step: usize,
+    scale_factor: f32,
+}
+
+impl CookModeScreen {
+    fn new(recipe: Recipe, scale_factor: f32) -> Self {
+        Self {
+            wants_to_exit: false,
+            recipe,
+            step: 0,
+            scale_factor,
+        }
+    }
+
+    fn next_step(&mut self) {
+        if self.step + 1 < self.recipe.instructions.len() {
+            self.step += 1;
+        }
+    }
+
+    fn previous_step(&mut self) {
+        self.step = self.step.saturating_sub(1);
+    }
+}
+
+impl Screen for CookModeScreen {
+    fn update(&mut self, ctx: &egui::Context, app_state: &mut AppState) -> Option<Box<dyn Screen>> {
+        ctx.set_pixels_per_point(3.0);
+
+        let is_dark_mode = app_state.is_dark_mode;
+        let background_color = if is_dark_mode {
+            egui::Color32::from_rgb(30, 30, 30)
+        } else {
+            egui::Color32::WHITE
+        };
+        let scaled = self.recipe.scale(self.scale_factor, app_state.settings.decimal_comma);
+
+        egui::TopBottomPanel::top("cook_mode_servings").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                match describe_scaled_servings(&self.recipe.servings, self.scale_factor) {
+                    Some(phrase) if self.scale_factor != 1.0 => {
+                        ui.label(format!("Servings: {} ({})", self.recipe.servings, phrase));
+                    }
+                    _ => {
+                        ui.label(format!("Servings: {}", self.recipe.servings));
+                    }
+                }
+                if ui.button("Halve").clicked() {
+                    self.scale_factor *= 0.5;
+                }
+                if ui.button("Double").clicked() {
+                    self.scale_factor *= 2.0;
+                }
+            });
+            ui.collapsing("Ingredients", |ui| {
+                for (group, ingredients) in scaled.grouped_ingredients() {
+                    if !group.is_empty() {
+                        ui.label(egui::RichText::new(group).strong());
+                    }
+                    for ingredient in ingredients {
+                        ui.label(format!("• {}", ingredient));
+                    }
+                }
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.painter().rect_filled(ui.available_rect_before_wrap(), 0.0, background_color);
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading(&self.recipe.title);
+
+                    ui.add_space(10.0);
+
+                    let steps = format_steps(&scaled.instructions, app_state.settings.bulleted_steps);
+                    match steps.get(self.step) {
+                        Some(step) => {
+                            ui.label(format!("Step {} of {}", self.step + 1, steps.len()));
+                            ui.label(step.as_str());
+                        }
+                        None => {
+                            ui.label("This recipe has no instructions.");
+                        }
+                    }
+
+                    ui.add_space(10.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(self.step > 0, egui::Button::new("Previous")).clicked() {
+                            self.previous_step();
+                        }
+                        if ui.add_enabled(self.step + 1 < steps.len(), egui::Button::new("Next")).clicked() {
+                            self.next_step();
+                        }
+                    });
+
+                    ui.add_space(10.0);
+
+                    if ui.button("Back").clicked() {
+                        self.wants_to_exit = true;
+                    }
+                });
+            });
+
+            if is_dark_mode {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+            } else {
+                ui.visuals_mut().override_text_color = Some(egui::Color32::BLACK);
+            }
+        });
+
+        None
+    }
+
+    fn wants_to_exit(&self) -> bool {
+        self.wants_to_exit
+    }
+}
+
+#[get("/")]
+async fn index() -> HttpResponse {
+    HttpResponse::Ok().body(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta charset="UTF-8">
+            <meta name="viewport" content="width=device-width, initial-scale=1.0">
+            <title>Recipe Bot Web Server</title>
+            <style>
+                body {
+                    font-family: Arial, sans-serif;
+                    background-color: #f0f0f0;
+                    margin: 0;
+                    padding: 0;
+                    display: flex;
+                    justify-content: center;
+                    align-items: center;
+                    height: 100vh;
+                }
+                .container {
+                    text-align: center;
+                    background-color: #ffffff;
+                    padding: 50px;
+                    border-radius: 8px;
+                    box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
+                }
+                h1 {
+                    color: #333333;
+                }
+                .links {
+                    margin-top: 20px;
+                }
+                .link-button {
+                    display: inline-block;
+                    margin: 10px;
+                    padding: 15px 30px;
+                    font-size: 16px;
+                    color: #ffffff;
+                    background-color: #007BFF;
+                    border: none;
+                    border-radius: 5px;
+                    text-decoration: none;
+                    transition: background-color 0.3s;
+                }
+                .link-button:hover {
+                    backgorund-color: #0056B3;
+                }
+                @media print {
+                    body {
+                        display: block;
+                        height: auto;
+                        background-color: #ffffff;
+                    }
+                    .container {
+                        box-shadow: none;
+                        padding: 0;
+                    }
+                    .links {
+                        display: none;
+                    }
+                }
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>Welcome to Recipe Bot's Web Server</h1>
+                <div class="links">
+                    <a href="/schedule" class="link-button">Weekly Food Schedule</a>
+                    <a href="/ingredients" class="link-button">Ingredients Needed</a>
+                    <a href="/pantry" class="link-button">All Ingredients</a>
+                </div>
+            </div>
+        </body>
+        </html>
+        "#
+    )
+}
+
+// Makes recipe text safe to put in a page, since titles, ingredients and the requested
+// URL can all contain "<" or "&"
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Friendly page for routes whose data hasn't been created yet. Title and message are
+// plain text and get escaped here.
+fn empty_state_page(title: &str, message: &str) -> HttpResponse {
+    HttpResponse::NotFound().body(format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta charset="UTF-8">
+            <meta name="viewport" content="width=device-width, initial-scale=1.0">
+            <title>{}</title>
+            <style>
+                body {{
+                    font-family: Arial, sans-serif;
+                    background-color: #f0f0f0;
+                    margin: 0;
+                    padding: 0;
+                    display: flex;
+                    justify-content: center;
+                    align-items: center;
+                    height: 100vh;
+                }}
+                .container {{
+                    text-align: center;
+                    background-color: #ffffff;
+                    padding: 50px;
+                    border-radius: 8px;
+                    box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
+                    max-width: 600px;
+                    width: 100%;
+                }}
+                h1 {{
+                    color: #333333;
+                }}
+                .message {{
+                    color: #555555;
+                }}
+                .link-button {{
+                    display: inline-block;
+                    margin-top: 20px;
+                    padding: 15px 30px;
+                    font-size: 16px;
+                    color: #ffffff;
+                    background-color: #007BFF;
+                    border-radius: 5px;
+                    text-decoration: none;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>{}</h1>
+                <p class="message">{}</p>
+                <a href="/" class="link-button">Back to Home</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        escape_html(title),
+        escape_html(title),
+        escape_html(message)
+    ))
+}
+
+// Orders schedule.txt lines by the configured first day, so a schedule written before
+// the week start changed still reads in week order. Lines without a day go last.
+fn order_schedule_lines(contents: &str, week_start: usize) -> Vec<&str> {
+    let days = week_days(week_start);
+    let mut lines: Vec<&str> = contents.lines().collect();
+    lines.sort_by_key(|line| {
+        let day = line.split_whitespace().next().unwrap_or_default().trim_end_matches(':');
+        days.iter().position(|known| known.eq_ignore_ascii_case(day)).unwrap_or(days.len())
+    });
+    lines
+}
+
+#[get("/schedule")]
+async fn schedule() -> Result<HttpResponse> {
+    let path = PathBuf::from("schedule/schedule.txt");
+    if let Ok(contents) = fs::read_to_string(path) {
+        let list_items: String = order_schedule_lines(&contents, Settings::load().week_start)
+            .into_iter()
+            .map(|line| {
+                let parts: Vec<&str> = line.splitn(2, ": ").collect();
+                if parts.len() == 2 {
+                    format!(
+                        "<div class=\"day\"><h2>{}</h2> <p class=\"meal\"><a href=\"{}\">{}</a></p></div>",
+                        escape_html(parts[0]),
+                        recipe_href(parts[1]),
+                        escape_html(parts[1])
+                    )
+                } else {
+                    let remaining: String = parts.join(" ");
+                    format!("<h2>{}</h2> <p class=\"meal\">{}</p>", escape_html(parts[0]), escape_html(&remaining))
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        Ok(HttpResponse::Ok().body(format!(
+            r#"
+            <!DOCTYPE html>
+            <html lang="en">
+            <head>
+                <meta charset="UTF-8">
+                <meta name="viewport" content="width=device-width, initial-scale=1.0">
+                <title>Meal Schedule</title>
+                <style>
+                    body {{
+                        font-family: Arial, sans-serif;
+                        background-color: #f0f0f0;
+                        margin: 0;
+                        padding: 0;
+                        display: flex;
+                        justify-content: center;
+                        align-items: center;
+                        height: 100vh;
+                    }}
+                    .container {{
+                        text-align: center;
+                        background-color: #ffffff;
+                        padding: 50px;
+                        border-radius: 8px;
+                        box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
+                        max-width: 600px;
+                        width: 100%;
+                    }}
+                    h1 {{
+                        color: #333333;
+                    }}
+                    .schedule {{
+                        margin-top: 20px;
+                    }}
+                    .day {{
+                        margin: 10px 0;
+                        padding: 15px;
+                        background-color: #e9ecef;
+                        border-radius: 5px;
+                        box-shadow: 0 0 5px rgba(0, 0, 0, 0.1);
+                    }}
+                    .day h2 {{
+                        margin: 0;
+                        color: #007BFF;
+                    }}
+                    .meal {{
+                        margin-top: 5px;
+                        color: #555555;
+                    }}
+                    .meal a {{
+                        color: #555555;
+                    }}
+                    /* Let the schedule flow onto as many pages as it needs */
+                    @media print {{
+                        body {{
+                            display: block;
+                            height: auto;
+                            background-color: #ffffff;
+                        }}
+                        .container {{
+                            box-shadow: none;
+                            padding: 0;
+                            max-width: none;
+                        }}
+                        .day {{
+                            background-color: transparent;
+                            box-shadow: none;
+                            padding: 5px 0;
+                            break-inside: avoid;
+                        }}
+                    }}
+                </style>
+            </head>
+            <body>
+                <div class="container">
+                    <h1>Weekly Meal Schedule</h1>
+                    <div class="schedule">
+                        {}
+                    </div>
+                </div>
+            </body>
+            </html>
+            "#,
+            list_items
+        )))
+    } else {
+        Ok(empty_state_page(
+            "No Schedule Yet",
+            "No weekly schedule has been created yet. Use \"Create Weekly Recipes\" in Recipe Bot to plan your week."
+        ))
+    }
+}
+
+// Link to a recipe's page. The name is percent-encoded so titles with spaces, "&",
+// "?" or "#" still reach /recipe/{name} intact. "+" is left alone because the router
+// doesn't decode it.
+fn recipe_href(name: &str) -> String {
+    let mut href = String::from("/recipe/");
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~+".contains(&byte) {
+            href.push(byte as char);
+        } else {
+            href.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    href
+}
+
+#[get("/recipe/{name}")]
+async fn recipe_page(name: web::Path<String>) -> HttpResponse {
+    let settings = Settings::load();
+    let recipe = match find_recipe_path(&name, &settings.recipe_extension).map(|path| parse_recipe_file(&path)) {
+        Some(Ok(recipe)) => recipe,
+        _ => {
+            return empty_state_page(
+                "Recipe Not Found",
+                &format!("There is no recipe called \"{}\" in Recipe Bot.", name)
+            )
+        }
+    };
+
+    let times: String = [("Prep Time", &recipe.prep_time), ("Cook Time", &recipe.cook_time), ("Total Time", &recipe.total_time)]
+        .iter()
+        .filter(|(_, time)| !time.trim().is_empty())
+        .map(|(label, time)| format!("<p class=\"time\">{}: {}</p>", label, escape_html(&display_time(time, settings.duration_style))))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let ingredients: String = recipe
+        .grouped_ingredients()
+        .iter()
+        .map(|(group, ingredients)| {
+            let items: String =
+                ingredients.iter().map(|ingredient| format!("<li>{}</li>", escape_html(&ingredient.to_string()))).collect();
+            if group.is_empty() {
+                format!("<ul>{}</ul>", items)
+            } else {
+                format!("<h3>{}</h3>\n<ul>{}</ul>", escape_html(group), items)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let prep: String = recipe
+        .prep_steps
+        .iter()
+        .filter(|step| !step.trim().is_empty())
+        .map(|step| format!("<li>{}</li>", escape_html(step)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let prep_section = if prep.is_empty() { String::new() } else { format!("<h2>Prep</h2>\n<ul>{}</ul>", prep) };
+    let list_tag = if settings.bulleted_steps { "ul" } else { "ol" };
+    let instructions: String = recipe
+        .instructions
+        .iter()
+        .map(|step| strip_step_number(step).trim())
+        // A trailing blank step from manual entry would show as an empty number
+        .filter(|step| !step.is_empty())
+        .map(|step| format!("<li>{}</li>", escape_html(step)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let notes: String = recipe
+        .notes
+        .iter()
+        .map(|note| format!("<p class=\"note\">{}</p>", escape_html(note)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let notes_section = if notes.is_empty() { String::new() } else { format!("<h2>Notes</h2>\n{}", notes) };
+
+    HttpResponse::Ok().body(format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta charset="UTF-8">
+            <meta name="viewport" content="width=device-width, initial-scale=1.0">
+            <title>{}</title>
+            <style>
+                body {{
+                    font-family: Arial, sans-serif;
+                    background-color: #f0f0f0;
+                    margin: 0;
+                    padding: 0;
+                    display: flex;
+                    justify-content: center;
+                }}
+                .container {{
+                    background-color: #ffffff;
+                    padding: 50px;
+                    margin: 20px;
+                    border-radius: 8px;
+                    box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
+                    max-width: 600px;
+                    width: 100%;
+                }}
+                h1 {{
+                    color: #333333;
+                    text-align: center;
+                }}
+                h2 {{
+                    color: #007BFF;
+                }}
+                h3 {{
+                    margin-bottom: 0;
+                    color: #333333;
+                }}
+                .time {{
+                    margin: 5px 0;
+                    color: #555555;
+                }}
+                li {{
+                    margin: 5px 0;
+                    color: #555555;
+                }}
+                .note {{
+                    padding: 10px;
+                    background-color: #e9ecef;
+                    border-radius: 5px;
+                    color: #555555;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>{}</h1>
+                <p class="time">Servings: {}</p>
+                {}
+                <h2>Ingredients</h2>
+                {}
+                {}
+                <h2>Instructions</h2>
+                <{}>
+                    {}
+                </{}>
+                {}
+            </div>
+        </body>
+        </html>
+        "#,
+        escape_html(&recipe.title),
+        escape_html(&recipe.title),
+        escape_html(&recipe.servings),
+        times,
+        ingredients,
+        prep_section,
+        list_tag,
+        instructions,
+        list_tag,
+        notes_section
+    ))
+}
+
+#[get("/ingredients")]
+async fn ingredients_page() -> Result<HttpResponse> {
+    let path = PathBuf::from("schedule/ingredients.sup");
+    if let Ok(contents) = fs::read_to_string(path) {
+        // The list is already merged by build_schedule; here it's only split into aisles
+        let mut by_aisle: HashMap<&str, Vec<&str>> = HashMap::new();
+        for line in contents.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            by_aisle.entry(grocery_aisle(line)).or_default().push(line);
+        }
+        let aisle_order = GROCERY_AISLES.iter().map(|(aisle, _)| *aisle).chain(std::iter::once("Other"));
+        let list_items: String = aisle_order
+            .filter_map(|aisle| {
+                by_aisle.get(aisle).map(|lines| {
+                    let items: Vec<String> = lines.iter().map(|line| format!("<p class=\"item\">{}</p>", escape_html(line))).collect();
+                    format!("<h2 class=\"aisle\">{}</h2>\n{}", aisle, items.join("\n"))
+                })
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(HttpResponse::Ok().body(format!(
+            r#"
+            <!DOCTYPE html>
+            <html lang="en">
+            <head>
+                <meta charset="UTF-8">
+                <meta name="viewport" content="width=device-width, initial-scale=1.0">
+                <title>Ingredients</title>
+                <style>
+                    body {{
+                        font-family: Arial, sans-serif;
+                        background-color: #f0f0f0;
+                        margin: 0;
+                        padding: 0;
+                        display: flex;
+                        justify-content: center;
+                        align-items: center;
+                        height: 100vh;
+                    }}
+                    .container {{
+                        text-align: center;
+                        background-color: #ffffff;
+                        padding: 50px;
+                        border-radius: 8px;
+                        box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
+                        max-width: 600px;
+                        width: 100%;
+                    }}
+                    h1 {{
+                        color: #333333;
+                    }}
+                    .ingredients {{
+                        margin-top: 20px;
+                        text-align: left;
+                        max-height: 400px;
+                        overflow-y: auto;
+                        padding-right: 10px; /* to avoid hiding the last item */
+                    }}
+                    .item {{
+                        margin: 10px 0;
+                        padding: 15px;
+                        background-color: #e9ecef;
+                        border-radius: 5px;
+                        box-shadow: 0 0 5px rgba(0, 0, 0, 0.1);
+                    }}
+                    .aisle {{
+                        color: #555555;
+                        font-size: 18px;
+                        margin: 20px 0 5px;
+                    }}
+                    .copy-button {{
+                        display: inline-block;
+                        margin-top: 20px;
+                        padding: 15px 30px;
+                        font-size: 16px;
+                        color: #ffffff;
+                        background-color: #28a745;
+                        border: none;
+                        border-radius: 5px;
+                        cursor: pointer;
+                        text-decoration: none;
+                        transition: background-color 0.3s;
+                    }}
+                    .copy-button:hover {{
+                        background-color: #218838;
+                    }}
+                    /* A plain checklist on paper, without the scroll box or buttons */
+                    @media print {{
+                        body {{
+                            display: block;
+                            height: auto;
+                            background-color: #ffffff;
+                        }}
+                        .container {{
+                            box-shadow: none;
+                            padding: 0;
+                            max-width: none;
+                        }}
+                        .ingredients {{
+                            max-height: none;
+                            overflow: visible;
+                        }}
+                        .item {{
+                            margin: 4px 0;
+                            padding: 0;
+                            background-color: transparent;
+                            box-shadow: none;
+                        }}
+                        .item::before {{
+                            content: "\2610  ";
+                        }}
+                        .copy-button {{
+                            display: none;
+                        }}
+                    }}
+                </style>
+            </head>
+            <body>
+                <div class="container">
+                    <h1>Ingredients List</h1>
+                    <div class="ingredients" id="ingredients-list">
+                        {}
+                    </div>
+                    <button class="copy-button" onclick="copyToClipboard()">Copy to Clipboard</button>
+                    <a class="copy-button" href="/ingredients.pdf">Download PDF</a>
+                </div>
+                <script>
+                    function copyToClipboard() {{
+                        const ingredientsElement = document.getElementById('ingredients-list');
+                        const ingredientsText = Array.from(ingredientsElement.getElementsByClassName('item'))
+                            .map(item => item.innerText.trim()) // Remove extra whitespace
+                            .join('\n'); // Use actual newline character
+
+                        const container = document.createElement('textarea');
+                        container.value = ingredientsText;
+                        document.body.appendChild(container);
+                        container.select();
+                        document.execCommand('copy');
+                        document.body.removeChild(container);
+                        alert('Ingredients copied to clipboard!');
+                    }}
+                </script>
+            </body>
+            </html>
+            "#,
+            list_items
+        )))
+    } else {
+        Ok(empty_state_page(
+            "No Ingredients Yet",
+            "No shopping list has been created yet. Process a weekly schedule in Recipe Bot to build one."
+        ))
+    }
+}
+
+#[get("/ingredients.pdf")]
+async fn ingredients_pdf() -> HttpResponse {
+    let contents = match fs::read_to_string("schedule/ingredients.sup") {
+        Ok(contents) => contents,
+        Err(_) => {
+            return empty_state_page(
+                "No Ingredients Yet",
+                "No shopping list has been created yet. Process a weekly schedule in Recipe Bot to build one."
+            )
+        }
+    };
+    let items: Vec<String> = contents
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    match render_shopping_list_pdf(&items) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header(("Content-Disposition", "attachment; filename=\"shopping_list.pdf\""))
+            .body(bytes),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error generating shopping list PDF: {}", e)),
+    }
+}
+
+// Body of POST /api/plan: dinner recipe names in week order, with "" for days left
+// open. The week starts on the configured first day, so recipes[0] is Sunday's dinner
+// when week_start is Sunday; the response's "days" lists the order used. Breakfast and
+// lunch stay open.
+#[derive(Deserialize)]
+struct PlanRequest {
+    recipes: Vec<String>,
+    scale: Option<f32>,
+}
+
+#[post("/api/plan")]
+async fn api_plan(request: web::Json<PlanRequest>) -> HttpResponse {
+    if request.recipes.len() > 7 {
+        return HttpResponse::BadRequest().json(json!({ "error": "A plan can have at most 7 days" }));
+    }
+    let settings = Settings::load();
+    let mut week = empty_week();
+    for (day_meals, recipe) in week.iter_mut().zip(&request.recipes) {
+        day_meals[DINNER] = recipe.clone();
+    }
+    let plan = match build_schedule(&week, request.scale.unwrap_or(1.0), &settings) {
+        Ok(plan) => plan,
+        Err(e) => return HttpResponse::BadRequest().json(json!({ "error": format!("Error building plan: {}", e) })),
+    };
+    if let Err(e) = write_schedule(&plan, &settings.recipe_extension) {
+        return HttpResponse::InternalServerError().json(json!({ "error": format!("Error writing schedule: {}", e) }));
+    }
+    let today = chrono::Local::now().date_naive();
+    if let Err(e) = append_history(&request.recipes, today) {
+        return HttpResponse::InternalServerError().json(json!({ "error": format!("Error recording plan history: {}", e) }));
+    }
+    if let Err(e) = record_cooked(&request.recipes, today) {
+        return HttpResponse::InternalServerError().json(json!({ "error": format!("Error recording cook dates: {}", e) }));
+    }
+    let ingredients: Vec<&str> = plan.ingredients.lines().collect();
+    HttpResponse::Ok().json(json!({ "days": week_days(settings.week_start), "ingredients": ingredients }))
+}
+
+#[get("/api/recipes")]
+async fn api_recipes() -> HttpResponse {
+    HttpResponse::Ok().json(load_all_recipes(&Settings::load().recipe_extension))
+}
+
+#[get("/api/recipes/{name}")]
+async fn api_recipe(name: web::Path<String>) -> HttpResponse {
+    let path = match find_recipe_path(&name, &Settings::load().recipe_extension) {
+        Some(path) => path,
+        None => return HttpResponse::NotFound().json(json!({ "error": format!("No recipe named \"{}\"", name) })),
+    };
+    match parse_recipe_file(&path) {
+        Ok(recipe) => HttpResponse::Ok().json(recipe),
+        Err(e) => HttpResponse::InternalServerError().json(json!({ "error": format!("Error reading recipe: {}", e) })),
+    }
+}
+
+#[get("/pantry")]
+async fn pantry() -> HttpResponse {
+    let frequencies = ingredient_frequencies(&load_all_recipes(&Settings::load().recipe_extension));
+    if frequencies.is_empty() {
+        return empty_state_page("No Ingredients Yet", "No recipes were found to build the ingredient list from.");
+    }
+    let list_items: String = frequencies
+        .iter()
+        .map(|(name, count)| {
+            format!(
+                "<p class=\"item\">{} <span class=\"count\">{} recipe{}</span></p>",
+                escape_html(name),
+                count,
+                if *count == 1 { "" } else { "s" }
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    HttpResponse::Ok().body(format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta charset="UTF-8">
+            <meta name="viewport" content="width=device-width, initial-scale=1.0">
+            <title>All Ingredients</title>
+            <style>
+                body {{
+                    font-family: Arial, sans-serif;
+                    background-color: #f0f0f0;
+                    margin: 0;
+                    padding: 0;
+                    display: flex;
+                    justify-content: center;
+                    align-items: center;
+                    height: 100vh;
+                }}
+                .container {{
+                    text-align: center;
+                    background-color: #ffffff;
+                    padding: 50px;
+                    border-radius: 8px;
+                    box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);
+                    max-width: 600px;
+                    width: 100%;
+                }}
+                h1 {{
+                    color: #333333;
+                }}
+                .ingredients {{
+                    margin-top: 20px;
+                    text-align: left;
+                    max-height: 400px;
+                    overflow-y: auto;
+                    padding-right: 10px;
+                }}
+                .item {{
+                    margin: 10px 0;
+                    padding: 15px;
+                    background-color: #e9ecef;
+                    border-radius: 5px;
+                    box-shadow: 0 0 5px rgba(0, 0, 0, 0.1);
+                }}
+                .count {{
+                    float: right;
+                    color: #555555;
+                }}
+            </style>
+        </head>
+        <body>
+            <div class="container">
+                <h1>All Ingredients</h1>
+                <div class="ingredients">
+                    {}
+                </div>
+            </div>
+        </body>
+        </html>
+        "#,
+        list_items
+    ))
+}
+
+// Binds to RECIPE_BOT_ADDR (default 0.0.0.0:8080). If that port is taken the next
+// few ports are tried, so a clash with another service doesn't kill the server.
+// Every route the web server serves
+fn web_routes(config: &mut web::ServiceConfig) {
+    config
+        .service(index)
+        .service(schedule)
+        .service(ingredients_page)
+        .service(ingredients_pdf)
+        .service(pantry)
+        .service(api_plan)
+        .service(api_recipes)
+        .service(api_recipe)
+        .service(recipe_page);
+}
+
+fn start_web_server() -> std::io::Result<()> {
+    let configured = env::var(WEB_SERVER_ADDR_VAR).unwrap_or_else(|_| DEFAULT_WEB_SERVER_ADDR.to_string());
+    let addr: SocketAddr = configured.trim().parse().map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} must look like 0.0.0.0:8080, got \"{}\": {}", WEB_SERVER_ADDR_VAR, configured, e),
+        )
+    })?;
+    let app = || ActixApp::new().configure(web_routes);
+
+    let sys = actix_web::rt::System::new();
+    sys.block_on(async {
+        let mut last_error = None;
+        for offset in 0..=WEB_SERVER_PORT_RETRIES {
+            let port = match addr.port().checked_add(offset) {
+                Some(port) => port,
+                None => break,
+            };
+            let candidate = SocketAddr::new(addr.ip(), port);
+            match HttpServer::new(app).bind(candidate) {
+                Ok(server) => {
+                    println!("Starting server at http://{}", candidate);
+                    WEB_SERVER_PORT.store(port, Ordering::Relaxed);
+                    return server.run().await;
+                }
+                Err(e) => {
+                    eprintln!("Could not bind web server to {}: {}", candidate, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::AddrInUse, "No free port found for the web server")
+        }))
+    })?;
+    Ok(())
+}
+
+fn main() -> eframe::Result<()> {
+
+    thread::spawn(|| {
+        if let Err(e) = start_web_server() {
+            eprintln!("Web server error: {}", e);
+        }
+    });
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size((400.0, 400.0)),
+        ..eframe::NativeOptions::default()
+    };
+
+    eframe::run_native(
+        MainScreen::name(),
+        native_options,
+        Box::new(|_cc: &CreationContext<'_>| -> Box<dyn eframe::App> {
+            Box::new(MainScreen::default())
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh, empty directory under the system temp dir for one test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("recipe_bot_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn ingredient_texts(recipe: &Recipe) -> Vec<String> {
+        recipe.ingreds.iter().map(|ingredient| ingredient.text.clone()).collect()
+    }
+
+    #[test]
+    fn editing_keeps_ingredients_that_contain_commas() {
+        let dir = test_dir("edit_commas");
+        let path = dir.join("Chili.rec");
+        let recipe = Recipe {
+            title: "Chili".to_string(),
+            ingreds: vec![parse_ingredient("1 onion, diced", false), parse_ingredient("2 cloves garlic, minced", false)],
+            ingredient_groups: vec![(1, "For the sauce".to_string())],
+            instructions: vec!["1. Cook everything".to_string()],
+            ..Recipe::default()
+        };
+        write_recipe_file(&recipe, &path).unwrap();
+
+        let screen = CreateRecipeManuallyScreen::edit(path.clone(), &parse_recipe_file(&path).unwrap());
+        let saved_path = dir.join("Chili_saved.rec");
+        screen.save_recipe_to(&saved_path).unwrap();
+        let saved = parse_recipe_file(&saved_path).unwrap();
+
+        assert_eq!(ingredient_texts(&saved), vec!["1 onion, diced", "2 cloves garlic, minced"]);
+        assert_eq!(saved.ingredient_groups, recipe.ingredient_groups);
+        assert_eq!(saved.instructions, recipe.instructions);
+    }
+
+    // Writes .rec text to a file of its own and parses it back
+    fn parse_rec(name: &str, contents: &str) -> Result<Recipe, RecipeParseError> {
+        let path = test_dir(name).join(recipe_file_name(name, DEFAULT_RECIPE_EXTENSION));
+        fs::write(&path, contents).unwrap();
+        parse_recipe_file(&path)
+    }
+
+    fn group_texts(recipe: &Recipe) -> Vec<(String, Vec<String>)> {
+        recipe
+            .grouped_ingredients()
+            .into_iter()
+            .map(|(name, ingredients)| (name, ingredients.iter().map(|ingredient| ingredient.text.clone()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn ingredient_groups_split_the_list_under_their_headings() {
+        let recipe = parse_rec(
+            "grouped",
+            "Title\tTacos\nIngredients Start\n1 lb beef\nFor the salsa:\n2 tomatoes\n1 onion\nTo serve:\n8 tortillas\nIngredients End\n",
+        )
+        .unwrap();
+        assert_eq!(
+            group_texts(&recipe),
+            vec![
+                (String::new(), vec!["1 lb beef".to_string()]),
+                ("For the salsa".to_string(), vec!["2 tomatoes".to_string(), "1 onion".to_string()]),
+                ("To serve".to_string(), vec!["8 tortillas".to_string()]),
+            ]
+        );
+        assert!(recipe_to_rec(&recipe).contains("1 lb beef\nFor the salsa:\n2 tomatoes\n1 onion\nTo serve:\n8 tortillas\n"));
+    }
+
+    #[test]
+    fn recipes_without_groups_are_one_unnamed_group() {
+        let recipe = parse_rec("ungrouped", "Title\tToast\nIngredients Start\n2 slices bread\nbutter\nIngredients End\n").unwrap();
+        assert!(recipe.ingredient_groups.is_empty());
+        assert_eq!(group_texts(&recipe), vec![(String::new(), vec!["2 slices bread".to_string(), "butter".to_string()])]);
+    }
+
+    #[test]
+    fn draft_round_trip_keeps_groups_and_steps() {
+        let draft = Recipe {
+            title: "Curry".to_string(),
+            servings: "4".to_string(),
+            tags: vec!["spicy".to_string()],
+            ingreds: vec![parse_ingredient("1 cup rice", false), parse_ingredient("2 tbsp curry paste", false), parse_ingredient("1 can coconut milk", false)],
+            ingredient_groups: vec![(1, "For the sauce".to_string())],
+            prep_steps: vec!["Rinse the rice".to_string()],
+            instructions: vec!["1. Cook the rice".to_string(), "2. Simmer the sauce".to_string()],
+            notes: vec!["Freezes well".to_string()],
+            ..Recipe::default()
+        };
+        let mut screen = CreateRecipeManuallyScreen { pending_draft: None, ..CreateRecipeManuallyScreen::default() };
+        screen.restore_draft(&draft);
+        assert_eq!(screen.ingredients, "1 cup rice\nFor the sauce:\n2 tbsp curry paste\n1 can coconut milk");
+        assert_eq!(screen.instructions, vec!["Cook the rice", "Simmer the sauce"]);
+
+        let recipe = screen.to_recipe();
+        assert_eq!(ingredient_texts(&recipe), ingredient_texts(&draft));
+        assert_eq!(recipe.ingredient_groups, draft.ingredient_groups);
+        assert_eq!(recipe.instructions, draft.instructions);
+        assert_eq!(recipe.prep_steps, draft.prep_steps);
+        assert_eq!(recipe.notes, draft.notes);
+        assert_eq!(recipe.tags, draft.tags);
+        assert_eq!(recipe.servings, draft.servings);
+    }
+
+    const JSON_LD_RECIPE: &str = r#"{
+        "@context": "https://schema.org",
+        "@graph": [
+            {"@type": "WebPage", "name": "Weeknight Ragu | Example Kitchen"},
+            {
+                "@type": ["Recipe"],
+                "name": "Weeknight Ragu",
+                "author": {"@type": "Person", "name": "Ada Cook"},
+                "recipeYield": ["4", "4 servings"],
+                "prepTime": "PT15M",
+                "cookTime": "PT1H",
+                "totalTime": "PT1H15M",
+                "recipeCategory": "Dinner",
+                "recipeCuisine": ["Italian"],
+                "keywords": "pasta, make ahead",
+                "recipeIngredient": ["1 onion, diced", "2 cups crushed tomatoes", "1 lb pasta"],
+                "recipeInstructions": [
+                    {"@type": "HowToSection", "name": "Sauce", "itemListElement": [
+                        {"@type": "HowToStep", "text": "Soften the onion."},
+                        {"@type": "HowToStep", "text": "Simmer with the tomatoes."}
+                    ]},
+                    "Cook the pasta and toss with the sauce."
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn json_ld_import_round_trips_through_rec() {
+        let imported = read_recipe_json(JSON_LD_RECIPE).unwrap();
+        let path = save_recipe_in(&imported, &test_dir("json_ld"), DEFAULT_RECIPE_EXTENSION).unwrap();
+        let parsed = parse_recipe_file(&path).unwrap();
+
+        assert_eq!(parsed.title, "Weeknight Ragu");
+        assert_eq!(parsed.from, "Ada Cook");
+        assert_eq!(parsed.servings, "4");
+        assert_eq!(parsed.prep_time, "15 min");
+        assert_eq!(parsed.cook_time, "1 hr");
+        assert_eq!(parsed.total_time, "1 hr 15 min");
+        assert_eq!(parsed.tags, vec!["dinner", "italian", "pasta", "make ahead"]);
+        assert_eq!(ingredient_texts(&parsed), vec!["1 onion, diced", "2 cups crushed tomatoes", "1 lb pasta"]);
+        assert_eq!(
+            parsed.instructions,
+            vec!["1. Soften the onion.", "2. Simmer with the tomatoes.", "3. Cook the pasta and toss with the sauce."]
+        );
+        assert_eq!(recipe_to_rec(&parsed), recipe_to_rec(&imported));
+    }
+
+    #[test]
+    fn api_json_still_imports() {
+        let recipe = read_recipe_json(r#"{"title": "Toast", "ingreds": ["2 slices bread"], "instructions": ["1. Toast"]}"#).unwrap();
+        assert_eq!(recipe.title, "Toast");
+        assert_eq!(ingredient_texts(&recipe), vec!["2 slices bread"]);
+        assert!(read_recipe_json(r#"{"@type": "Recipe", "name": ""}"#).is_err());
+    }
+
+    // Handlers and the planner read recipes/ and schedule/ relative to the working
+    // directory, so tests that need files there take turns in a fresh one
+    static WORKING_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    struct WorkingDir {
+        previous: PathBuf,
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl Drop for WorkingDir {
+        fn drop(&mut self) {
+            env::set_current_dir(&self.previous).unwrap();
+        }
+    }
+
+    fn enter_test_dir(name: &str) -> WorkingDir {
+        let lock = WORKING_DIR_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = env::current_dir().unwrap();
+        env::set_current_dir(test_dir(name)).unwrap();
+        WorkingDir { previous, _lock: lock }
+    }
+
+    // Writes recipes/<category>/<name>.rec, relative to the working directory
+    fn add_recipe(category: &str, name: &str, contents: &str) -> PathBuf {
+        let dir = Path::new(RECIPES_ROOT).join(category);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(recipe_file_name(name, DEFAULT_RECIPE_EXTENSION));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    async fn get_page(uri: &str) -> (actix_web::http::StatusCode, String) {
+        let app = actix_web::test::init_service(ActixApp::new().configure(web_routes)).await;
+        let response = actix_web::test::call_service(&app, actix_web::test::TestRequest::get().uri(uri).to_request()).await;
+        let status = response.status();
+        let body = actix_web::test::read_body(response).await;
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn post_json(uri: &str, body: serde_json::Value) -> (actix_web::http::StatusCode, serde_json::Value) {
+        let app = actix_web::test::init_service(ActixApp::new().configure(web_routes)).await;
+        let request = actix_web::test::TestRequest::post().uri(uri).set_json(body).to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        let status = response.status();
+        let body = actix_web::test::read_body(response).await;
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    const PASTA_REC: &str = "Title\tPasta\nServings\t2\nIngredients Start\n200 g spaghetti\nIngredients End\n\
+        Prep Start\nBoil a big pot of water\nPrep End\nInstructions Start\n1. Cook the pasta\nInstructions End\n\
+        Notes Start\nUse plenty of salt\nLeftovers keep two days\nNotes End\n";
+
+    #[actix_web::test]
+    async fn recipe_page_renders_each_note_separately() {
+        let _dir = enter_test_dir("web_notes");
+        add_recipe("dinner", "Pasta", PASTA_REC);
+        let (status, body) = get_page("/recipe/Pasta").await;
+        assert!(status.is_success());
+        assert_eq!(body.matches("<p class=\"note\">").count(), 2);
+        assert!(body.contains("<p class=\"note\">Use plenty of salt</p>"));
+        assert!(body.contains("<p class=\"note\">Leftovers keep two days</p>"));
+    }
+
+    #[actix_web::test]
+    async fn recipe_page_lists_prep_before_instructions() {
+        let _dir = enter_test_dir("web_prep");
+        add_recipe("dinner", "Pasta", PASTA_REC);
+        let (_, body) = get_page("/recipe/Pasta").await;
+        let prep = body.find("<li>Boil a big pot of water</li>").expect("prep step missing");
+        assert!(prep > body.find("<h2>Prep</h2>").unwrap());
+        assert!(prep < body.find("<h2>Instructions</h2>").unwrap());
+    }
+
+    #[actix_web::test]
+    async fn schedule_links_reach_recipes_with_unsafe_names() {
+        let _dir = enter_test_dir("web_links");
+        add_recipe("dinner", "Mac & Cheese?", &PASTA_REC.replace("Pasta", "Mac & Cheese?"));
+        fs::create_dir_all("schedule").unwrap();
+        fs::write("schedule/schedule.txt", "Monday Dinner: Mac & Cheese?\n").unwrap();
+
+        let (_, body) = get_page("/schedule").await;
+        let href = recipe_href("Mac & Cheese?");
+        assert_eq!(href, "/recipe/Mac%20%26%20Cheese%3F");
+        assert!(body.contains(&format!("href=\"{}\"", href)));
+
+        let (status, recipe) = get_page(&href).await;
+        assert!(status.is_success());
+        assert!(recipe.contains("<h1>Mac &amp; Cheese?</h1>"));
+    }
+
+    const SOUP_REC: &str = "Title\tSoup\nIngredients Start\n1 l stock\nIngredients End\nInstructions Start\n1. Heat\nInstructions End\n";
+
+    #[test]
+    fn importing_the_same_recipe_twice_keeps_one_copy() {
+        let _dir = enter_test_dir("import_twice");
+        fs::create_dir_all(IMPORT_DIR).unwrap();
+        fs::write(Path::new(IMPORT_DIR).join("soup.rec"), SOUP_REC).unwrap();
+        // Same recipe, different spacing and case
+        fs::write(Path::new(IMPORT_DIR).join("soup_copy.rec"), SOUP_REC.replace("Soup", "SOUP").replace("1. Heat", "1.  heat")).unwrap();
+
+        let first = import_recipes(Path::new(IMPORT_DIR), Path::new(IMPORTED_RECIPES_DIR), DEFAULT_RECIPE_EXTENSION).unwrap();
+        assert_eq!((first.imported, first.duplicates), (1, 1));
+        let second = import_recipes(Path::new(IMPORT_DIR), Path::new(IMPORTED_RECIPES_DIR), DEFAULT_RECIPE_EXTENSION).unwrap();
+        assert_eq!((second.imported, second.duplicates), (0, 2));
+        assert_eq!(fs::read_dir(IMPORTED_RECIPES_DIR).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn a_broken_file_does_not_stop_the_import() {
+        let _dir = enter_test_dir("import_broken");
+        fs::create_dir_all(IMPORT_DIR).unwrap();
+        fs::write(Path::new(IMPORT_DIR).join("a_broken.rec"), "Ingredients Start\n1 egg\n").unwrap();
+        fs::write(Path::new(IMPORT_DIR).join("b_soup.rec"), SOUP_REC).unwrap();
+
+        let summary = import_recipes(Path::new(IMPORT_DIR), Path::new(IMPORTED_RECIPES_DIR), DEFAULT_RECIPE_EXTENSION).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.failed.len(), 1);
+        assert!(summary.failed[0].0.ends_with("a_broken.rec"));
+        assert!(Path::new(IMPORTED_RECIPES_DIR).join("Soup.rec").exists());
+    }
+
+    const TYPO_REC: &str = "Title\tSalad\nServigns\t2\nIngredients Start\n1 head lettuce\nIngredients End\n\
+        just toss it\nInstructions Start\n1. Toss\nInstructions End\n";
+
+    #[test]
+    fn lenient_parse_skips_unknown_headers_and_stray_lines() {
+        let recipe = parse_rec("lenient", TYPO_REC).unwrap();
+        assert_eq!(recipe.title, "Salad");
+        assert_eq!(recipe.servings, "");
+        assert_eq!(ingredient_texts(&recipe), vec!["1 head lettuce"]);
+        assert_eq!(recipe.instructions, vec!["1. Toss"]);
+    }
+
+    #[test]
+    fn strict_parse_reports_the_offending_line() {
+        let path = test_dir("strict").join("Salad.rec");
+        fs::write(&path, TYPO_REC).unwrap();
+        match parse_recipe_file_strict(&path) {
+            Err(RecipeParseError::UnknownHeader { line, key }) => assert_eq!((line, key.as_str()), (2, "Servigns")),
+            other => panic!("expected an unknown header, got {:?}", other.map(|recipe| recipe.title)),
+        }
+
+        fs::write(&path, TYPO_REC.replace("Servigns\t2\n", "")).unwrap();
+        match parse_recipe_file_strict(&path) {
+            Err(RecipeParseError::StrayLine { line, text }) => assert_eq!((line, text.as_str()), (5, "just toss it")),
+            other => panic!("expected a stray line, got {:?}", other.map(|recipe| recipe.title)),
+        }
+    }
+
+    #[test]
+    fn structural_errors_fail_in_both_modes() {
+        let contents = "Title\tSalad\nIngredients Start\n1 head lettuce\n";
+        let path = test_dir("unterminated").join("Salad.rec");
+        fs::write(&path, contents).unwrap();
+        for result in [parse_recipe_file(&path), parse_recipe_file_strict(&path)] {
+            assert!(matches!(result, Err(RecipeParseError::UnterminatedSection { line: 2, .. })));
+        }
+    }
+
+    #[test]
+    fn library_health_flags_what_loading_skips() {
+        let dir = test_dir("audit_strict");
+        fs::write(dir.join("Salad.rec"), TYPO_REC).unwrap();
+        let issues = audit_library(&[dir.to_string_lossy().to_string()], DEFAULT_RECIPE_EXTENSION);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].describe().contains("line 2: unknown header \"Servigns\""));
+    }
+
+    fn recipe_with(ingredients: &[&str], steps: &[&str]) -> Recipe {
+        Recipe {
+            title: "Stew".to_string(),
+            ingreds: ingredients.iter().map(|line| parse_ingredient(line, false)).collect(),
+            instructions: steps.iter().map(|step| step.to_string()).collect(),
+            ..Recipe::default()
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_entries() {
+        let old = recipe_with(&["1 onion", "2 carrots"], &["1. Chop", "2. Simmer 1 hr", "3. Serve"]);
+        let new = recipe_with(&["1 onion", "1 lb beef"], &["1. Chop", "2. Simmer 2 hr"]);
+        let diff = diff_recipes(&old, &new);
+        assert_eq!(diff.added_ingredients, vec!["1 lb beef"]);
+        assert_eq!(diff.removed_ingredients, vec!["2 carrots"]);
+        assert_eq!(diff.changed_steps, vec![(1, "2. Simmer 1 hr".to_string(), "2. Simmer 2 hr".to_string())]);
+        assert!(diff.added_steps.is_empty());
+        assert_eq!(diff.removed_steps, vec!["3. Serve"]);
+        assert!(diff_recipes(&old, &old).is_empty());
+
+        assert_eq!(
+            diff.describe(),
+            "(- previous version, + current version)\n+ 1 lb beef\n- 2 carrots\n\
+             - Step 2: 2. Simmer 1 hr\n+ Step 2: 2. Simmer 2 hr\n- 3. Serve"
+        );
+    }
+
+    #[test]
+    fn previous_version_is_looked_up_when_the_selection_changes() {
+        let _dir = enter_test_dir("versions");
+        let path = add_recipe("dinner", "Pasta", PASTA_REC);
+        let mut screen = RecipeSelectionScreen::with_recipe("Pasta".to_string(), DEFAULT_RECIPE_EXTENSION);
+        screen.refresh_viewed_recipe();
+        assert_eq!(screen.previous_version, None);
+
+        archive_recipe_version(&path).unwrap();
+        screen.refresh_viewed_recipe();
+        // Same selection, so the history isn't read again
+        assert_eq!(screen.previous_version, None);
+        screen.viewed_entry = None;
+        screen.refresh_viewed_recipe();
+        assert_eq!(screen.previous_version, recipe_versions(&path).pop());
+        assert!(screen.previous_version.is_some());
+    }
+
+    // Counts how often each recipe was picked for the current meal this week
+    fn pick_counts(screen: &CreateWeeklyRecipesScreen) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for day in &screen.selected_recipes {
+            *counts.entry(day[screen.meal].clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn randomize_all_spreads_a_small_pool_evenly() {
+        let _dir = enter_test_dir("randomize_small");
+        for name in ["Chili", "Curry", "Tacos"] {
+            add_recipe("dinner", name, &SOUP_REC.replace("Soup", name));
+        }
+        for _ in 0..20 {
+            let mut screen = CreateWeeklyRecipesScreen::default();
+            screen.randomize_all(0);
+            let counts = pick_counts(&screen);
+            assert_eq!(counts.len(), 3, "{:?}", counts);
+            assert!(counts.values().all(|count| (2..=3).contains(count)), "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn randomize_single_skips_recent_meals_while_it_can() {
+        let _dir = enter_test_dir("randomize_recent");
+        for name in ["Chili", "Curry", "Tacos"] {
+            add_recipe("dinner", name, &SOUP_REC.replace("Soup", name));
+        }
+        let date = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+        append_history(&["Chili".to_string(), "Curry".to_string()], date).unwrap();
+
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        for _ in 0..20 {
+            screen.randomize_single(0, 1);
+            assert_eq!(screen.selected_recipes[0][DINNER], "Tacos");
+        }
+
+        // Once everything was cooked recently it falls back to the whole pool
+        append_history(&["Tacos".to_string()], date).unwrap();
+        screen.randomize_single(0, 2);
+        assert!(!screen.selected_recipes[0][DINNER].is_empty());
+    }
+
+    #[test]
+    fn randomize_all_skips_recent_meals_while_it_can() {
+        let _dir = enter_test_dir("randomize_all_recent");
+        for name in ["Chili", "Curry", "Tacos"] {
+            add_recipe("dinner", name, &SOUP_REC.replace("Soup", name));
+        }
+        append_history(&["Chili".to_string(), "Curry".to_string()], NaiveDate::from_ymd_opt(2026, 1, 5).unwrap()).unwrap();
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        screen.randomize_all(1);
+        // Without repeats Tacos can only be the first pick; the rest falls back to recent meals
+        assert_eq!(screen.selected_recipes[0][DINNER], "Tacos");
+    }
+
+    #[test]
+    fn weekly_pdf_uses_the_plan_scale() {
+        let _dir = enter_test_dir("weekly_scale");
+        add_recipe("dinner", "Pasta", PASTA_REC);
+        let mut selected = empty_week();
+        selected[0][DINNER] = "Pasta".to_string();
+        selected[2][DINNER] = "Pasta".to_string();
+
+        let (cover, recipes) = weekly_pdf_contents(&selected, 2.0, &Settings::default()).unwrap();
+        assert_eq!(cover, vec!["Monday Dinner: Pasta", "Wednesday Dinner: Pasta"]);
+        assert_eq!(recipes.len(), 2);
+        assert_eq!(ingredient_texts(&recipes[0]), vec!["400 g spaghetti"]);
+        assert!(weekly_pdf_contents(&empty_week(), 2.0, &Settings::default()).is_err());
+    }
+
+    #[test]
+    fn meals_without_a_category_have_nothing_to_randomize() {
+        let _dir = enter_test_dir("meal_categories");
+        add_recipe("dinner", "Chili", &SOUP_REC.replace("Soup", "Chili"));
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        assert_eq!(screen.day_categories[0], vec!["", "", "Dinner"]);
+
+        screen.meal = 0;
+        screen.randomize_all(0);
+        assert!(screen.selected_recipes.iter().all(|day| day[0].is_empty()));
+        assert!(screen.processing_message.starts_with("Warning: No recipes in breakfast."));
+
+        screen.meal = DINNER;
+        screen.randomize_all(0);
+        assert!(screen.selected_recipes.iter().all(|day| day[DINNER] == "Chili"));
+    }
+
+    #[test]
+    fn writing_a_schedule_clears_last_weeks_recipe_copies() {
+        let _dir = enter_test_dir("schedule_copies");
+        add_recipe("dinner", "Chili", &SOUP_REC.replace("Soup", "Chili"));
+        fs::create_dir_all("schedule/plans").unwrap();
+        fs::write("schedule/Monday.rec", SOUP_REC).unwrap();
+        fs::write("schedule/Tuesday_Lunch.rec", SOUP_REC).unwrap();
+        fs::write("schedule/plans/week.plan", "Monday\tDinner\tChili\n").unwrap();
+
+        let mut selected = empty_week();
+        selected[0][DINNER] = "Chili".to_string();
+        write_schedule(&build_schedule(&selected, 1.0, &Settings::default()).unwrap(), DEFAULT_RECIPE_EXTENSION).unwrap();
+
+        let mut recipe_files: Vec<String> = fs::read_dir("schedule")
+            .unwrap()
+            .flatten()
+            .filter(|entry| is_recipe_file(&entry.path(), DEFAULT_RECIPE_EXTENSION))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        recipe_files.sort();
+        assert_eq!(recipe_files, vec!["Monday_Dinner.rec"]);
+        assert!(Path::new("schedule/plans/week.plan").exists());
+        assert_eq!(fs::read_to_string("schedule/schedule.txt").unwrap(), "Monday Dinner: Chili\n");
+    }
+
+    #[test]
+    fn recipe_cache_rereads_changed_files() {
+        let path = test_dir("cache_mtime").join("Pasta.rec");
+        fs::write(&path, PASTA_REC).unwrap();
+        let mut cache = RecipeCache::default();
+        assert_eq!(cache.get(&path).unwrap().servings, "2");
+
+        // Rewritten with a new modified time, so the cached copy is stale
+        fs::write(&path, PASTA_REC.replace("Servings\t2", "Servings\t6")).unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert_eq!(cache.get(&path).unwrap().servings, "6");
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn recipe_cache_drops_the_oldest_entry_when_full() {
+        let dir = test_dir("cache_capacity");
+        let mut cache = RecipeCache::with_capacity(2);
+        let paths: Vec<PathBuf> = ["A", "B", "C"].iter().map(|name| dir.join(recipe_file_name(name, DEFAULT_RECIPE_EXTENSION))).collect();
+        for path in &paths {
+            fs::write(path, PASTA_REC).unwrap();
+            cache.get(path).unwrap();
+        }
+        assert_eq!(cache.entries.len(), 2);
+        assert!(!cache.entries.contains_key(&paths[0]));
+        assert_eq!(cache.order, vec![paths[1].clone(), paths[2].clone()]);
+    }
+
+    #[test]
+    fn recipe_cache_is_faster_than_parsing() {
+        let path = test_dir("cache_speed").join("Big.rec");
+        let ingredients: String = (1..=500).map(|n| format!("{} g ingredient number {}\n", n, n)).collect();
+        fs::write(&path, PASTA_REC.replace("200 g spaghetti\n", &ingredients)).unwrap();
+        let mut cache = RecipeCache::default();
+        cache.get(&path).unwrap();
+
+        const RUNS: u32 = 50;
+        let started = Instant::now();
+        for _ in 0..RUNS {
+            parse_recipe_file(&path).unwrap();
+        }
+        let parsing = started.elapsed();
+        let started = Instant::now();
+        for _ in 0..RUNS {
+            cache.get(&path).unwrap();
+        }
+        let cached = started.elapsed();
+        println!("{} reads: parsing {:?}, cached {:?} ({:.1}x faster)", RUNS, parsing, cached, parsing.as_secs_f64() / cached.as_secs_f64());
+        assert!(cached < parsing);
+    }
+
+    #[actix_web::test]
+    async fn scans_use_the_configured_extension() {
+        let _dir = enter_test_dir("custom_extension");
+        fs::write(SETTINGS_PATH, "recipe_extension=.Recipe\n").unwrap();
+        let extension = Settings::load().recipe_extension;
+        assert_eq!(extension, "recipe");
+        add_recipe("dinner", "Chili", "Title\tChili\n");
+        fs::rename("recipes/dinner/Chili.rec", "recipes/dinner/Chili.recipe").unwrap();
+        add_recipe("dinner", "Old", "Title\tOld\n");
+
+        let names: Vec<String> = list_recipe_entries(&recipe_directories(), &extension).into_iter().map(|entry| entry.name).collect();
+        assert_eq!(names, vec!["Chili"]);
+        assert_eq!(find_recipe_path("Chili", &extension), Some(PathBuf::from("recipes/dinner/Chili.recipe")));
+        assert_eq!(find_recipe_path("Old", &extension), None);
+        assert!(!library_is_empty(&recipe_directories(), &extension));
+        assert!(library_is_empty(&recipe_directories(), "txt"));
+        assert_eq!(draft_path(&extension), PathBuf::from("recipes/.draft.recipe"));
+
+        let (_, body) = get_page("/api/recipes").await;
+        assert!(body.contains("\"title\":\"Chili\""));
+        assert!(!body.contains("\"title\":\"Old\""));
+    }
+
+    #[test]
+    fn saved_plans_load_back_and_report_missing_recipes() {
+        let _dir = enter_test_dir("plans");
+        for name in ["Chili", "Curry"] {
+            add_recipe("dinner", name, &SOUP_REC.replace("Soup", name));
+        }
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        screen.selected_recipes[0][DINNER] = "Chili".to_string();
+        screen.selected_recipes[4][DINNER] = "Curry".to_string();
+        screen.save_plan("week").unwrap();
+        assert!(!Path::new(PLANS_DIR).join("week.plan.tmp").exists());
+
+        fs::remove_file("recipes/dinner/Curry.rec").unwrap();
+        let mut reloaded = CreateWeeklyRecipesScreen::default();
+        let missing = reloaded.load_plan("week").unwrap();
+        assert_eq!(reloaded.selected_recipes[0][DINNER], "Chili");
+        assert!(reloaded.selected_recipes[4][DINNER].is_empty());
+        assert_eq!(missing, vec!["Curry (Friday Dinner)"]);
+    }
+
+    #[test]
+    fn neglected_filter_hides_recently_cooked_recipes() {
+        let _dir = enter_test_dir("neglected");
+        for name in ["Chili", "Curry", "Tacos"] {
+            add_recipe("dinner", name, &SOUP_REC.replace("Soup", name));
+        }
+        let today = chrono::Local::now().date_naive();
+        record_cooked(&["Chili".to_string()], today - chrono::Duration::days(3)).unwrap();
+        record_cooked(&["Curry".to_string(), String::new()], today - chrono::Duration::days(NEGLECTED_AFTER_DAYS)).unwrap();
+        assert_eq!(fs::read_to_string(LAST_COOKED_PATH).unwrap().lines().count(), 2);
+
+        let mut screen = RecipeSelectionScreen::default();
+        screen.load_recipes(DEFAULT_RECIPE_EXTENSION);
+        let listed = |screen: &RecipeSelectionScreen| -> Vec<String> {
+            screen.recipes.iter().filter(|entry| screen.passes_filters(entry)).map(|entry| entry.name.clone()).collect()
+        };
+        assert_eq!(listed(&screen), vec!["Chili", "Curry", "Tacos"]);
+        screen.neglected_only = true;
+        assert_eq!(listed(&screen), vec!["Curry", "Tacos"]);
+    }
+
+    #[test]
+    fn shopping_list_csv_quotes_special_fields() {
+        let item = |quantity: &str, unit: &str, name: &str, recipes: &[&str]| ShoppingItem {
+            line: String::new(),
+            quantity: quantity.to_string(),
+            unit: unit.to_string(),
+            name: name.to_string(),
+            recipes: recipes.iter().map(|recipe| recipe.to_string()).collect(),
+        };
+        let csv = shopping_list_csv(&[
+            item("2", "cups", "flour", &["Bread"]),
+            item("1,5", "kg", "tomatoes, diced", &["Soup", "Chili"]),
+            item("", "", "12\" tortillas", &["Tacos"]),
+            item("1", "", "onion\nor shallot", &["Soup"]),
+        ]);
+        assert_eq!(
+            csv,
+            "quantity,unit,name,recipes\n\
+             2,cups,flour,Bread\n\
+             \"1,5\",kg,\"tomatoes, diced\",Soup; Chili\n\
+             ,,\"12\"\" tortillas\",Tacos\n\
+             1,,\"onion\nor shallot\",Soup\n"
+        );
+    }
+
+    #[test]
+    fn decimal_comma_amounts_parse_and_add_up() {
+        let ingredient = parse_ingredient("1,5 cups milk (warm)", true);
+        assert_eq!(ingredient.quantity, Some(1.5));
+        assert_eq!(ingredient.unit.as_deref(), Some("cups"));
+        assert_eq!(ingredient.name, "milk");
+        assert_eq!(ingredient.prep.as_deref(), Some("warm"));
+        assert_eq!(parse_ingredient("1,5 cups milk", false).quantity, None);
+        assert_eq!(parse_ingredient("1 1/2 cups milk", true).quantity, Some(1.5));
+
+        let settings = Settings { decimal_comma: true, ..Settings::default() };
+        let lines = [("1,5 cups milk".to_string(), "Pancakes".to_string()), ("1/2 cup milk".to_string(), "Cocoa".to_string())];
+        let items = aggregate_ingredients(&lines, &settings);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].line, "2 cup milk");
+        assert_eq!(items[0].recipes, vec!["Pancakes", "Cocoa"]);
+    }
+
+    #[test]
+    fn scaling_multiplies_amounts_and_keeps_the_rest() {
+        let recipe = recipe_with(&["3/8 cup sugar", "2 eggs", "salt to taste", "1 1/2 cups milk (warm)"], &[]);
+        let doubled = recipe.scale(2.0, false);
+        assert_eq!(ingredient_texts(&doubled), vec!["3/4 cup sugar", "4 eggs", "salt to taste", "3 cups milk (warm)"]);
+        assert_eq!(doubled.ingreds[0].quantity, Some(0.75));
+        assert_eq!(doubled.ingreds[3].prep.as_deref(), Some("warm"));
+
+        let recipe = recipe_with(&["1,5 cups milk", "0,6 l cream"], &[]);
+        assert_eq!(ingredient_texts(&recipe.scale(1.5, true)), vec!["2 1/4 cups milk", "0,9 l cream"]);
+    }
+
+    #[test]
+    fn halve_and_double_match_the_generic_scale() {
+        let recipe = recipe_with(&["3/4 cup sugar", "1 1/2 tsp salt", "½ cup butter", "0.6 l stock", "pepper"], &[]);
+        assert_eq!(ingredient_texts(&recipe.scale(1.0, false)), ingredient_texts(&recipe));
+
+        let halved = recipe.scale(0.5, false);
+        assert_eq!(ingredient_texts(&halved), vec!["3/8 cup sugar", "3/4 tsp salt", "1/4 cup butter", "0.3 l stock", "pepper"]);
+        let doubled = recipe.scale(2.0, false);
+        assert_eq!(ingredient_texts(&doubled), vec!["1 1/2 cup sugar", "3 tsp salt", "1 cup butter", "1.2 l stock", "pepper"]);
+        // Scaling a doubled recipe down lands on the same lines as halving the original
+        assert_eq!(ingredient_texts(&doubled.scale(0.25, false)), ingredient_texts(&halved));
+    }
+
+    #[test]
+    fn portions_scale_what_gets_printed_and_exported() {
+        let recipe = parse_rec("Pasta.rec", PASTA_REC).unwrap();
+        let (factor, scaled) = portions_for(&recipe, 6, 1.0, false).unwrap();
+        assert_eq!(factor, 3.0);
+        assert_eq!(ingredient_texts(&scaled), vec!["600 g spaghetti"]);
+
+        let output = recipe.scaled_for_output(factor as f32, false);
+        assert_eq!(ingredient_texts(&output), ingredient_texts(&scaled));
+        assert_eq!(output.servings, "2 (scaled to 6 servings)");
+        assert!(export_recipe_markdown(&output).contains("600 g spaghetti"));
+        assert_eq!(recipe.scaled_for_output(1.0, false).servings, "2");
+
+        let unparseable = Recipe { servings: "a crowd".to_string(), ..recipe };
+        match portions_for(&unparseable, 6, 1.0, false) {
+            Err(e) => assert!(e.contains("a crowd"), "{}", e),
+            Ok(_) => panic!("servings of \"a crowd\" can't be scaled"),
+        }
+    }
+
+    #[test]
+    fn convert_unit_covers_common_volumes_and_weights() {
+        let table = [
+            (1.0, "tbsp", "tsp", Some(3.0)),
+            (1.0, "cup", "tbsp", Some(16.0)),
+            (1.0, "cup", "ml", Some(236.6)),
+            (1.0, "tsp", "ml", Some(4.93)),
+            (1.0, "l", "ml", Some(1000.0)),
+            (2.0, "Cups", "l", Some(0.473)),
+            (1.0, "lb", "oz", Some(16.0)),
+            (1.0, "kg", "g", Some(1000.0)),
+            (1.0, "oz", "g", Some(28.35)),
+            (1.0, "kg", "lb", Some(2.205)),
+            (1.0, "cup", "g", None),
+            (100.0, "g", "ml", None),
+            (1.0, "can", "ml", None),
+        ];
+        for (quantity, from, to, expected) in table {
+            let converted = convert_unit(quantity, from, to);
+            match (converted, expected) {
+                (Some(converted), Some(expected)) => {
+                    assert!((converted - expected).abs() < expected * 0.001, "{} {} -> {}: {}", quantity, from, to, converted)
+                }
+                _ => assert_eq!(converted, expected, "{} {} -> {}", quantity, from, to),
+            }
+        }
+    }
+
+    #[test]
+    fn metric_shopping_amounts_are_rounded_and_localized() {
+        let metric = |rounding: RoundMode, decimal_comma: bool| Settings {
+            unit_system: UnitSystem::Metric,
+            shopping_rounding: rounding,
+            decimal_comma,
+            ..Settings::default()
+        };
+        let teaspoon = CanonicalUnit::Teaspoon;
+        let ounce = CanonicalUnit::Ounce;
+        let parts = |quantity: f32, unit: &CanonicalUnit, settings: Settings| {
+            let (amount, unit) = canonical_parts(quantity, unit, &settings);
+            format!("{} {}", amount, unit)
+        };
+        assert_eq!(parts(0.01, &teaspoon, metric(RoundMode::Exact, false)), "1 ml");
+        assert_eq!(parts(1.0, &teaspoon, metric(RoundMode::Exact, false)), "5 ml");
+        assert_eq!(parts(0.4, &ounce, metric(RoundMode::Exact, false)), "11 g");
+        assert_eq!(parts(0.4, &ounce, metric(RoundMode::WholeUp, false)), "12 g");
+        assert_eq!(parts(48.0 * 5.0, &teaspoon, metric(RoundMode::Exact, false)), "1.18 l");
+        assert_eq!(parts(48.0 * 5.0, &teaspoon, metric(RoundMode::Exact, true)), "1,18 l");
+        assert_eq!(parts(48.0 * 5.0, &teaspoon, metric(RoundMode::QuarterUp, true)), "1,25 l");
+        assert_eq!(parts(48.0 * 5.0, &teaspoon, metric(RoundMode::WholeUp, true)), "2 l");
+        assert_eq!(parts(36.0, &ounce, metric(RoundMode::QuarterUp, false)), "1.25 kg");
+        assert_eq!(parts(0.3, &ounce, Settings { decimal_comma: true, ..Settings::default() }), "0,3 oz");
+    }
+
+    #[test]
+    fn colliding_titles_get_distinct_pdfs() {
+        let _dir = enter_test_dir("pdf_collision");
+        let first = add_recipe("dinner", "Pasta Salad", &PASTA_REC.replace("Pasta", "Pasta Salad"));
+        let second = add_recipe("lunch", "Pasta_Salad", &PASTA_REC.replace("Pasta", "Pasta_Salad"));
+        fs::write("Pasta_Salad.pdf", "left over from an earlier run").unwrap();
+
+        let settings = Settings::default();
+        let first_pdf = generate_recipe_pdf(&first, 1.0, &settings).unwrap();
+        let second_pdf = generate_recipe_pdf(&second, 1.0, &settings).unwrap();
+        assert_eq!(first_pdf.file_name().unwrap(), "Pasta_Salad_2.pdf");
+        assert_eq!(second_pdf.file_name().unwrap(), "Pasta_Salad_3.pdf");
+        for pdf in [&first_pdf, &second_pdf] {
+            assert!(fs::read(pdf).unwrap().starts_with(b"%PDF"));
+        }
+        assert_eq!(fs::read_to_string("Pasta_Salad.pdf").unwrap(), "left over from an earlier run");
+
+        // Printing the same recipe again replaces its own PDF instead of adding another
+        fs::write(&first_pdf, "stale").unwrap();
+        assert_eq!(generate_recipe_pdf(&first, 2.0, &settings).unwrap(), first_pdf);
+        assert_eq!(generate_recipe_pdf(&second, 1.0, &settings).unwrap(), second_pdf);
+        assert!(fs::read(&first_pdf).unwrap().starts_with(b"%PDF"));
+        assert!(!Path::new("Pasta_Salad_4.pdf").exists());
+    }
+
+    #[test]
+    fn week_days_rotate_to_each_start_day() {
+        for (start, first) in DAYS.iter().enumerate() {
+            let days = week_days(start);
+            assert_eq!(days[0], *first);
+            for (idx, day) in days.iter().enumerate() {
+                assert_eq!(*day, DAYS[(start + idx) % 7]);
+            }
+        }
+        assert_eq!(week_days(6), ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"]);
+    }
+
+    #[actix_web::test]
+    async fn web_schedule_and_plan_api_follow_the_week_start() {
+        let _dir = enter_test_dir("web_week_start");
+        add_recipe("dinner", "Chili", &SOUP_REC.replace("Soup", "Chili"));
+        Settings { week_start: 6, ..Settings::default() }.save().unwrap();
+        fs::create_dir_all("schedule").unwrap();
+        fs::write("schedule/schedule.txt", "Monday Dinner: Chili\nSunday Dinner: Chili\n").unwrap();
+
+        let (_, page) = get_page("/schedule").await;
+        assert!(page.find("Sunday Dinner").unwrap() < page.find("Monday Dinner").unwrap());
+
+        let (status, response) = post_json("/api/plan", json!({ "recipes": ["Chili"] })).await;
+        assert!(status.is_success(), "{}", response);
+        assert_eq!(response["days"][0], "Sunday");
+        assert_eq!(fs::read_to_string("schedule/schedule.txt").unwrap(), "Sunday Dinner: Chili\n");
+    }
+
+    #[actix_web::test]
+    async fn web_routes_explain_missing_data_in_an_empty_directory() {
+        let _dir = enter_test_dir("web_empty");
+        for (uri, message) in [
+            ("/schedule", "No Schedule Yet"),
+            ("/ingredients", "No Ingredients Yet"),
+            ("/ingredients.pdf", "No Ingredients Yet"),
+            ("/pantry", "No Ingredients Yet"),
+            ("/recipe/Pasta", "Recipe Not Found"),
+        ] {
+            let (status, page) = get_page(uri).await;
+            assert_eq!(status, actix_web::http::StatusCode::NOT_FOUND, "{}", uri);
+            assert!(page.contains(message), "{}: {}", uri, page);
+        }
+        let (status, page) = get_page("/").await;
+        assert!(status.is_success());
+        assert!(page.contains("Recipe Bot"));
+        let (status, body) = get_page("/api/recipes").await;
+        assert!(status.is_success());
+        assert_eq!(body, "[]");
+    }
+
+    #[actix_web::test]
+    async fn ingredients_pdf_downloads_the_shopping_list() {
+        let _dir = enter_test_dir("ingredients_pdf");
+        fs::create_dir_all("schedule").unwrap();
+        fs::write("schedule/ingredients.sup", "6 eggs\n2 cups flour\n").unwrap();
+
+        let app = actix_web::test::init_service(ActixApp::new().configure(web_routes)).await;
+        let request = actix_web::test::TestRequest::get().uri("/ingredients.pdf").to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+        let header = |name: &str| response.headers().get(name).unwrap().to_str().unwrap().to_string();
+        assert_eq!(header("content-type"), "application/pdf");
+        assert!(header("content-disposition").starts_with("attachment;"));
+        let body = actix_web::test::read_body(response).await;
+        assert!(body.starts_with(b"%PDF"));
+    }
+
+    #[actix_web::test]
+    async fn plan_api_returns_the_merged_shopping_list() {
+        let _dir = enter_test_dir("api_plan");
+        add_recipe("dinner", "Pasta", PASTA_REC);
+        add_recipe("dinner", "Omelette", "Title\tOmelette\nIngredients Start\n3 eggs\n50 g spaghetti\nIngredients End\n");
+        Settings { unit_system: UnitSystem::Metric, ..Settings::default() }.save().unwrap();
+
+        let (status, response) = post_json("/api/plan", json!({ "recipes": ["Pasta", "", "Omelette"], "scale": 2 })).await;
+        assert!(status.is_success(), "{}", response);
+        assert_eq!(response["ingredients"], json!(["500 g spaghetti", "6 eggs"]));
+        assert_eq!(
+            fs::read_to_string("schedule/schedule.txt").unwrap(),
+            "Monday Dinner: Pasta\nWednesday Dinner: Omelette\n"
+        );
+        assert!(load_last_cooked().contains_key("Omelette"));
+
+        let (status, response) = post_json("/api/plan", json!({ "recipes": ["Lasagna"] })).await;
+        assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+        assert!(response["error"].as_str().unwrap().contains("Lasagna"));
+        let (status, _) = post_json("/api/plan", json!({ "recipes": vec![""; 8] })).await;
+        assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    // The shopping list lines for the given ingredient lines, with default settings
+    fn shopping_lines(lines: &[&str]) -> Vec<String> {
+        let lines: Vec<(String, String)> = lines.iter().map(|line| (line.to_string(), "Recipe".to_string())).collect();
+        aggregate_ingredients(&lines, &Settings::default()).into_iter().map(|item| item.line).collect()
+    }
+
+    #[test]
+    fn units_normalize_to_teaspoons_and_ounces() {
+        assert_eq!(normalize_unit("Tbsp."), CanonicalUnit::Teaspoon);
+        assert_eq!(normalize_unit("cups"), CanonicalUnit::Teaspoon);
+        assert_eq!(normalize_unit("lbs"), CanonicalUnit::Ounce);
+        assert_eq!(normalize_unit("Cans"), CanonicalUnit::Other("can".to_string()));
+        assert_eq!(to_canonical(2.0, "tbsp"), (6.0, CanonicalUnit::Teaspoon));
+        assert_eq!(to_canonical(1.0, "lb"), (16.0, CanonicalUnit::Ounce));
+
+        assert_eq!(shopping_lines(&["1 tbsp sugar", "1 tsp sugar"]), vec!["4 tsp sugar"]);
+        assert_eq!(shopping_lines(&["1 tbsp oil", "2 tbsp oil"]), vec!["3 tbsp oil"]);
+        assert_eq!(shopping_lines(&["1 lb butter", "8 oz butter"]), vec!["1 1/2 lb butter"]);
+        assert_eq!(shopping_lines(&["1 cup flour", "4 oz flour"]), vec!["1 cup flour", "4 oz flour"]);
+    }
+
+    #[test]
+    fn duplicate_ingredients_merge_into_one_line() {
+        assert_eq!(shopping_lines(&["2 eggs", "4 Eggs", "2 eggs."]), vec!["8 eggs"]);
+        // A unit and no unit, or amounts and none, can't be added together
+        assert_eq!(shopping_lines(&["1 can tomatoes", "2 tomatoes"]), vec!["1 can tomatoes", "2 tomatoes"]);
+        assert_eq!(shopping_lines(&["salt to taste", "1 tsp salt", "Salt to taste"]), vec!["salt to taste", "1 tsp salt"]);
+        // Prep notes are kept with the merged line
+        assert_eq!(shopping_lines(&["1 onion (diced)", "1 onion (sliced)"]), vec!["2 onion (diced, sliced)"]);
+
+        let lines = [("2 eggs", "Cake"), ("3 eggs", "Quiche"), ("2 eggs", "Cake")].map(|(line, recipe)| (line.to_string(), recipe.to_string()));
+        let items = aggregate_ingredients(&lines, &Settings::default());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].line, "7 eggs");
+        assert_eq!(items[0].recipes, vec!["Cake", "Quiche"]);
+    }
+
+    #[test]
+    fn schedule_url_brackets_ipv6_addresses() {
+        assert_eq!(schedule_url("192.168.1.20".parse().unwrap(), 8080), "http://192.168.1.20:8080/schedule");
+        assert_eq!(schedule_url("fe80::1".parse().unwrap(), 9000), "http://[fe80::1]:9000/schedule");
+    }
+
+    #[test]
+    fn title_matches_outrank_ingredient_and_instruction_matches() {
+        let titled = Recipe { title: "Chicken Soup".to_string(), ..recipe_with(&["1 l stock"], &["Simmer"]) };
+        let ingredient = recipe_with(&["2 chicken thighs"], &["Simmer"]);
+        let instruction = recipe_with(&["1 l stock"], &["Add the chicken at the end"]);
+        let inside_word = Recipe { title: "Chickenpea Curry".to_string(), ..recipe_with(&[], &[]) };
+        let unrelated = recipe_with(&["1 l stock"], &["Simmer"]);
+
+        let score = |recipe: &Recipe| score_match(recipe, " Chicken ");
+        assert!(score(&titled) > score(&ingredient));
+        assert!(score(&ingredient) > score(&instruction));
+        assert!(score(&instruction) > 0.0);
+        assert!(score(&titled) > score(&inside_word));
+        assert_eq!(score(&unrelated), 0.0);
+        assert_eq!(score_match(&titled, "  "), 0.0);
+    }
+
+    #[test]
+    fn library_is_empty_until_a_recipe_file_exists() {
+        let _dir = enter_test_dir("empty_library");
+        assert!(library_is_empty(&recipe_directories(), DEFAULT_RECIPE_EXTENSION));
+        fs::create_dir_all("recipes/dinner").unwrap();
+        fs::write("recipes/dinner/notes.txt", "not a recipe").unwrap();
+        fs::create_dir_all("recipes/.history").unwrap();
+        fs::write("recipes/.history/Old.rec", SOUP_REC).unwrap();
+        assert!(library_is_empty(&recipe_directories(), DEFAULT_RECIPE_EXTENSION));
+        add_recipe("dinner", "Soup", SOUP_REC);
+        assert!(!library_is_empty(&recipe_directories(), DEFAULT_RECIPE_EXTENSION));
+    }
+
+    #[test]
+    fn round_quantity_follows_each_mode() {
+        let cases = [
+            (RoundMode::Exact, [(2.67, 2.67), (2.0, 2.0), (0.1, 0.1)]),
+            (RoundMode::QuarterUp, [(2.67, 2.75), (2.0, 2.0), (0.1, 0.25)]),
+            (RoundMode::WholeUp, [(2.25, 3.0), (2.0, 2.0), (0.1, 1.0)]),
+        ];
+        for (mode, amounts) in cases {
+            for (amount, expected) in amounts {
+                assert!((round_quantity(amount, mode) - expected).abs() < 1e-9, "{} {} -> {}", mode.key(), amount, round_quantity(amount, mode));
+            }
+            assert!(RoundMode::from_key(mode.key()) == Some(mode));
+        }
+        // Float noise from summing thirds doesn't push an exact amount up a step
+        assert_eq!(round_quantity(1.0 / 3.0 * 3.0, RoundMode::WholeUp), 1.0);
+
+        let rounded = |mode: RoundMode| {
+            let settings = Settings { shopping_rounding: mode, ..Settings::default() };
+            let lines = [("2/3 cup rice".to_string(), "Pilaf".to_string()), ("2 cups rice".to_string(), "Stew".to_string())];
+            aggregate_ingredients(&lines, &settings).remove(0).line
+        };
+        assert_eq!(rounded(RoundMode::Exact), "2 2/3 cup rice");
+        assert_eq!(rounded(RoundMode::QuarterUp), "2 3/4 cup rice");
+        assert_eq!(rounded(RoundMode::WholeUp), "3 cup rice");
+    }
+
+    const TWO_RECIPES_MD: &str = "My favourites\n\n# Pancakes\n**Servings:** 4\n\n## Ingredients\n- 2 eggs\n- 1 cup flour\n\n\
+        ## Instructions\n1. Whisk\n2. Fry\n\n# Guacamole\nTags: dip, vegetarian\n\n## Ingredients\n- 3 avocados\n\n\
+        ## Notes\n- Best eaten fresh\n";
+
+    #[test]
+    fn markdown_file_splits_into_one_recipe_per_title() {
+        let sections = split_markdown_recipes(TWO_RECIPES_MD);
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].starts_with("# Pancakes\n"));
+        assert!(sections[1].starts_with("# Guacamole\n"));
+
+        let recipes: Vec<Recipe> = sections.iter().filter_map(|md| parse_markdown_recipe(md)).collect();
+        assert_eq!(recipes[0].title, "Pancakes");
+        assert_eq!(recipes[0].servings, "4");
+        assert_eq!(ingredient_texts(&recipes[0]), vec!["2 eggs", "1 cup flour"]);
+        assert_eq!(recipes[0].instructions, vec!["1. Whisk", "2. Fry"]);
+        assert_eq!(recipes[1].title, "Guacamole");
+        assert_eq!(recipes[1].tags, vec!["dip", "vegetarian"]);
+        assert_eq!(recipes[1].notes, vec!["Best eaten fresh"]);
+        assert!(recipes[1].instructions.is_empty());
+    }
+
+    #[test]
+    fn importing_a_markdown_file_saves_each_recipe() {
+        let import_dir = test_dir("markdown_import");
+        let dest_dir = import_dir.join("imported");
+        fs::write(import_dir.join("favourites.md"), TWO_RECIPES_MD).unwrap();
+
+        let summary = import_recipes(&import_dir, &dest_dir, DEFAULT_RECIPE_EXTENSION).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert!(summary.failed.is_empty());
+        let pancakes = parse_recipe_file(&dest_dir.join("Pancakes.rec")).unwrap();
+        assert_eq!(ingredient_texts(&pancakes), vec!["2 eggs", "1 cup flour"]);
+        assert!(dest_dir.join("Guacamole.rec").exists());
+    }
+
+    #[test]
+    fn print_shortcut_needs_a_selected_recipe() {
+        let settings = Settings::default();
+        let mut screen = RecipeSelectionScreen::default();
+        screen.handle_print_shortcut(true, &settings);
+        assert!(screen.processing_message.is_empty());
+
+        // A recipe whose file is gone reports it instead of starting a generation
+        screen.selected_recipe = Some(RecipeEntry {
+            name: "Gone".to_string(),
+            category: "Dinner".to_string(),
+            path: test_dir("print_shortcut").join("Gone.rec"),
+        });
+        screen.handle_print_shortcut(false, &settings);
+        assert!(screen.processing_message.is_empty());
+        screen.handle_print_shortcut(true, &settings);
+        assert_eq!(screen.processing_message, "Recipe file not found");
+
+        screen.processing_message.clear();
+        screen.pdf_in_progress = true;
+        screen.handle_print_shortcut(true, &settings);
+        assert!(screen.processing_message.is_empty());
+    }
+
+    // A piece of text drawn in a generated PDF: font size in points, x and y in mm
+    struct TextRun {
+        size: f32,
+        x: f32,
+        y: f32,
+        text: String,
+    }
+
+    // The text a generated PDF draws, in drawing order. printpdf writes each use_text as
+    // its own "BT /Font size Tf x y Td <hex> Tj ET" block, uncompressed and in WinAnsi,
+    // so that's all this decodes; the bullet may be written at 0x95 or at 0x7F, which
+    // WinAnsi also shows as a bullet.
+    fn pdf_text_runs(bytes: &[u8]) -> Vec<TextRun> {
+        let contents = String::from_utf8_lossy(bytes);
+        let mut runs = Vec::new();
+        for block in contents.split("BT\n").skip(1) {
+            let block = &block[..block.find("ET").unwrap_or(block.len())];
+            let mut run = TextRun { size: 0.0, x: 0.0, y: 0.0, text: String::new() };
+            for line in block.lines() {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match words.as_slice() {
+                    [_, size, "Tf"] => run.size = size.parse().unwrap(),
+                    [x, y, "Td"] => {
+                        run.x = x.parse::<f32>().unwrap() * POINTS_TO_MM;
+                        run.y = y.parse::<f32>().unwrap() * POINTS_TO_MM;
+                    }
+                    [hex, "Tj"] => {
+                        let hex = hex.trim_start_matches('<').trim_end_matches('>');
+                        run.text = (0..hex.len())
+                            .step_by(2)
+                            .map(|idx| match u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap() {
+                                0x7F | 0x95 => '•',
+                                byte => byte as char,
+                            })
+                            .collect();
+                    }
+                    _ => {}
+                }
+            }
+            runs.push(run);
+        }
+        runs
+    }
+
+    fn pdf_text(bytes: &[u8]) -> Vec<String> {
+        pdf_text_runs(bytes).into_iter().map(|run| run.text).collect()
+    }
+
+    #[test]
+    fn recipe_pdf_text_matches_the_golden_copy() {
+        let recipe = Recipe { servings: "4".to_string(), ..recipe_with(&["1 onion", "2 carrots"], &["Chop", "Simmer 1 hr"]) };
+        let (bytes, pages) = render_recipes_pdf(std::slice::from_ref(&recipe), "Stew", &[], &Settings::default()).unwrap();
+        assert_eq!(pages, 1);
+        assert_eq!(
+            pdf_text(&bytes),
+            vec![
+                "Stew", "From:", "Servings: 4", "Prep Time:", "Cook Time:", "Total Time:",
+                "Ingredients:", "• 1 onion", "• 2 carrots",
+                "Instructions:", "1. Chop", "2. Simmer 1 hr",
+                "Stew", "Page 1 of 1",
+            ]
+        );
+
+        let bulleted = Settings { bulleted_steps: true, ..Settings::default() };
+        let (bytes, _) = render_recipes_pdf(&[recipe], "Stew", &[], &bulleted).unwrap();
+        let text = pdf_text(&bytes);
+        assert!(text.contains(&"• Chop".to_string()), "{:?}", text);
+        assert!(!text.iter().any(|line| line.contains('â')), "{:?}", text);
+    }
+
+    #[test]
+    fn saving_keeps_the_section_order_of_the_file() {
+        let contents = "Title\tSoup\nFrom\tGran\nServings\t4\nPrep Time\t10 min\nCook Time\t30 min\nTotal Time\t40 min\n\
+            Notes Start\nFreezes well\nNotes End\nIngredients Start\n1 onion\n1 l stock\nIngredients End\n\
+            Instructions Start\n1. Simmer\nInstructions End\n";
+        let recipe = parse_rec("section_order", contents).unwrap();
+        assert_eq!(recipe.order, vec![Section::Notes, Section::Ingredients, Section::Instructions]);
+        assert_eq!(recipe_to_rec(&recipe), contents);
+
+        // Without a recorded order the sections come out in the default one
+        let fresh = Recipe { order: Vec::new(), ..recipe };
+        let saved = recipe_to_rec(&fresh);
+        assert!(saved.find("Ingredients Start").unwrap() < saved.find("Instructions Start").unwrap());
+        assert!(saved.find("Instructions Start").unwrap() < saved.find("Notes Start").unwrap());
+    }
+
+    #[test]
+    fn generate_everything_writes_all_three_pdfs() {
+        let _dir = enter_test_dir("generate_everything");
+        add_recipe("dinner", "Pasta", PASTA_REC);
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        screen.selected_recipes[0][DINNER] = "Pasta".to_string();
+        screen.selected_recipes[3][DINNER] = "Pasta".to_string();
+
+        screen.start_generate_everything(&Settings::default());
+        while screen.outputs_job.is_some() {
+            thread::sleep(Duration::from_millis(10));
+            screen.poll_generate_everything();
+        }
+        let expected = ["schedule/week_recipes.pdf", "schedule/week_at_a_glance.pdf", "schedule/shopping_list.pdf"];
+        assert_eq!(screen.processing_message, format!("Generated:\n{}", expected.join("\n")));
+        for path in expected {
+            assert!(fs::read(path).unwrap().starts_with(b"%PDF"), "{}", path);
+        }
+        assert!(pdf_text(&fs::read("schedule/shopping_list.pdf").unwrap()).iter().any(|line| line.contains("spaghetti")));
+
+        // An empty week reports the problem instead of writing anything
+        let mut empty = CreateWeeklyRecipesScreen::default();
+        empty.start_generate_everything(&Settings::default());
+        while empty.outputs_job.is_some() {
+            thread::sleep(Duration::from_millis(10));
+            empty.poll_generate_everything();
+        }
+        assert_eq!(empty.processing_message, "Error generating PDFs: No recipes are planned for this week");
+    }
+
+    // A recipe as Notepad saves it: a UTF-8 BOM up front and CRLF line endings
+    const WINDOWS_REC: &str = "\u{feff}Title\tPancakes\r\nServings\t4\r\nIngredients Start\r\n2 eggs\r\n1 cup milk\r\n\
+        Ingredients End\r\nInstructions Start\r\n1. Whisk\r\n2. Fry\r\nInstructions End\r\nNotes Start\r\nServe hot\r\nNotes End\r\n";
+
+    #[test]
+    fn windows_line_endings_and_bom_parse_cleanly() {
+        let recipe = parse_rec("windows_rec", WINDOWS_REC).unwrap();
+        assert_eq!(recipe.title, "Pancakes");
+        assert_eq!(recipe.servings, "4");
+        assert_eq!(ingredient_texts(&recipe), vec!["2 eggs", "1 cup milk"]);
+        assert_eq!(recipe.instructions, vec!["1. Whisk", "2. Fry"]);
+        assert_eq!(recipe.notes, vec!["Serve hot"]);
+
+        let path = test_dir("windows_rec_strict").join("Pancakes.rec");
+        fs::write(&path, WINDOWS_REC).unwrap();
+        assert_eq!(parse_recipe_file_strict(&path).unwrap().title, "Pancakes");
+    }
+
+    fn servings_parts(servings: &str) -> Option<(f32, f32, Option<String>)> {
+        parse_servings(servings).map(|range| (range.min, range.max, range.unit))
+    }
+
+    #[test]
+    fn servings_text_parses_into_a_range() {
+        assert_eq!(servings_parts("4"), Some((4.0, 4.0, None)));
+        assert_eq!(servings_parts("Serves 4-6"), Some((4.0, 6.0, None)));
+        assert_eq!(servings_parts("6 to 4"), Some((4.0, 6.0, None)));
+        assert_eq!(servings_parts("makes a dozen"), Some((12.0, 12.0, None)));
+        assert_eq!(servings_parts("2 dozen cookies"), Some((24.0, 24.0, Some("cookies".to_string()))));
+        assert_eq!(servings_parts("4 bowls"), Some((4.0, 4.0, Some("bowls".to_string()))));
+        assert_eq!(servings_parts("Serves 4 (about 2 cups)"), Some((4.0, 4.0, None)));
+        assert_eq!(servings_parts("Serves 4 – 6"), Some((4.0, 6.0, None)));
+        assert_eq!(servings_parts("a crowd"), None);
+        assert_eq!(servings_parts(""), None);
+    }
+
+    #[actix_web::test]
+    async fn pages_escape_recipe_text_and_the_requested_name() {
+        let _dir = enter_test_dir("web_escape");
+        add_recipe(
+            "dinner",
+            "Tags",
+            "Title\tTags <b>\nServings\t2 \"big\"\nIngredients Start\n1 <script>alert(1)</script>\nIngredients End\n\
+             Instructions Start\n1. Stir & serve\nInstructions End\nNotes Start\nUse <i>fresh</i>\nNotes End\n",
+        );
+        let (_, body) = get_page("/recipe/Tags").await;
+        assert!(body.contains("<h1>Tags &lt;b&gt;</h1>"));
+        assert!(body.contains("Servings: 2 &quot;big&quot;"));
+        assert!(body.contains("<li>1 &lt;script&gt;alert(1)&lt;/script&gt;</li>"));
+        assert!(body.contains("<li>Stir &amp; serve</li>"));
+        assert!(body.contains("Use &lt;i&gt;fresh&lt;/i&gt;"));
+        assert!(!body.contains("<script>"));
+
+        let (status, body) = get_page("/recipe/%3Cscript%3Ealert(1)%3C%2Fscript%3E").await;
+        assert_eq!(status, actix_web::http::StatusCode::NOT_FOUND);
+        assert!(body.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!body.contains("<script>"));
+
+        fs::create_dir_all("schedule").unwrap();
+        fs::write("schedule/schedule.txt", "Monday Dinner: Tags <b>\n").unwrap();
+        fs::write("schedule/ingredients.sup", "1 <script>\n").unwrap();
+        for page in ["/schedule", "/ingredients", "/pantry"] {
+            let (_, body) = get_page(page).await;
+            assert!(!body.contains("<script>alert") && !body.contains("1 <script>") && !body.contains("Tags <b>"), "{}", page);
+        }
+    }
+
+    #[test]
+    fn failed_pdf_write_leaves_nothing_at_the_final_path() {
+        let _dir = enter_test_dir("pdf_write_failure");
+        let recipe = add_recipe("dinner", "Pasta", PASTA_REC);
+        // A directory where the temp file goes makes the write fail before the rename
+        fs::create_dir("Pasta.pdf.tmp").unwrap();
+        assert!(generate_recipe_pdf(&recipe, 1.0, &Settings::default()).is_err());
+        assert!(!Path::new("Pasta.pdf").exists());
+
+        // An earlier good copy survives a failed rewrite untouched
+        fs::remove_dir("Pasta.pdf.tmp").unwrap();
+        let pdf = generate_recipe_pdf(&recipe, 1.0, &Settings::default()).unwrap();
+        assert_eq!(pdf.file_name().unwrap(), "Pasta.pdf");
+        let good = fs::read(&pdf).unwrap();
+        fs::create_dir("Pasta.pdf.tmp").unwrap();
+        assert!(generate_recipe_pdf(&recipe, 2.0, &Settings::default()).is_err());
+        assert_eq!(fs::read(&pdf).unwrap(), good);
+
+        // A failed rename doesn't leave the temp file behind either
+        fs::remove_dir("Pasta.pdf.tmp").unwrap();
+        fs::create_dir_all("blocked.pdf/inside").unwrap();
+        assert!(write_file_atomically(Path::new("blocked.pdf"), b"%PDF").is_err());
+        assert!(!Path::new("blocked.pdf.tmp").exists());
+    }
+
+    #[test]
+    fn update_failures_show_on_the_main_screen() {
+        let _dir = enter_test_dir("update_outside_git");
+        let error = pull_and_rebuild("main").unwrap_err();
+        assert!(error.contains("not installed from a git checkout"), "{}", error);
+
+        let mut screen = MainScreen::default();
+        let (sender, receiver) = mpsc::channel();
+        screen.update_job = Some(receiver);
+        screen.poll_update();
+        assert!(screen.update_job.is_some(), "still running until the worker reports");
+
+        sender.send(Err("`git pull origin main` failed".to_string())).unwrap();
+        screen.poll_update();
+        assert!(screen.update_job.is_none());
+        assert_eq!(screen.status_message, "Update failed: `git pull origin main` failed");
+    }
+
+    #[test]
+    fn weekly_pdf_cover_shows_each_days_time_and_the_week_total() {
+        let _dir = enter_test_dir("weekly_cover_times");
+        add_recipe("dinner", "Pasta", &PASTA_REC.replace("Servings\t2\n", "Servings\t2\nTotal Time\t30 min\n"));
+        add_recipe("lunch", "Soup", &SOUP_REC.replace("Title\tSoup\n", "Title\tSoup\nPrep Time\t10 min\nCook Time\t1 hr\n"));
+        add_recipe("dinner", "Toast", "Title\tToast\nIngredients Start\n1 slice bread\nIngredients End\n");
+        let mut selected = empty_week();
+        selected[0][1] = "Soup".to_string();
+        selected[0][DINNER] = "Pasta".to_string();
+        selected[2][DINNER] = "Pasta".to_string();
+        selected[4][DINNER] = "Toast".to_string();
+
+        let settings = Settings { duration_style: DurationStyle::Minutes, ..Settings::default() };
+        let (cover, _) = weekly_pdf_contents(&selected, 1.0, &settings).unwrap();
+        assert_eq!(
+            cover,
+            vec![
+                "Monday Lunch: Soup",
+                "Monday Dinner: Pasta",
+                "Monday cooking time: 100 min",
+                "Wednesday Dinner: Pasta",
+                "Wednesday cooking time: 30 min",
+                "Friday Dinner: Toast",
+                "Cooking time this week: 130 min",
+            ]
+        );
+
+        let plain = Settings { show_times_on_cover: false, ..settings };
+        let (cover, _) = weekly_pdf_contents(&selected, 1.0, &plain).unwrap();
+        assert_eq!(cover.len(), 4);
+        assert!(cover.iter().all(|line| !line.contains("cooking time") && !line.contains("Cooking time")));
+    }
+
+    #[test]
+    fn spelled_out_units_parse_and_merge_with_abbreviations() {
+        let flour = parse_ingredient("500 grams flour", false);
+        assert_eq!((flour.quantity, flour.unit.as_deref(), flour.name.as_str()), (Some(500.0), Some("grams"), "flour"));
+        for (line, unit) in [("2 tsps salt", "tsps"), ("1 Tbsps. oil", "tbsps"), ("2 c milk", "c"), ("1 litre stock", "litre"), ("3 pinches salt", "pinches")] {
+            assert_eq!(parse_ingredient(line, false).unit.as_deref(), Some(unit), "{}", line);
+        }
+        assert_eq!(ingredient_name("250 milliliters cream"), "cream");
+
+        let metric = Settings { unit_system: UnitSystem::Metric, ..Settings::default() };
+        let merge = |lines: &[&str]| -> Vec<String> {
+            let lines: Vec<(String, String)> = lines.iter().map(|line| (line.to_string(), "Recipe".to_string())).collect();
+            aggregate_ingredients(&lines, &metric).into_iter().map(|item| item.line).collect()
+        };
+        assert_eq!(merge(&["500 grams flour", "200 g flour"]), vec!["700 g flour"]);
+        assert_eq!(merge(&["1 liter stock", "500 milliliters stock"]), vec!["1.5 l stock"]);
+        assert_eq!(shopping_lines(&["2 tsps sugar", "1 tablespoons sugar"]), vec!["5 tsp sugar"]);
+    }
+
+    #[test]
+    fn chicken_recipes_get_poultry_and_veggie_recipes_get_vegetarian() {
+        let chicken = recipe_with(&["2 chicken breasts", "1 cup flour", "2 eggs"], &["Bread and fry"]);
+        assert_eq!(suggest_tags(&chicken), vec!["poultry", "baking"]);
+
+        let veggie = recipe_with(&["1 cup hummus", "2 tomatoes", "4 graham crackers", "1 can chickpeas"], &["Serve"]);
+        assert_eq!(suggest_tags(&veggie), vec!["vegetarian"]);
+
+        let checks = [
+            ("2 lb lamb shoulder", "lamb"),
+            ("1 lb ground veal", "beef"),
+            ("4 oz chorizo", "pork"),
+            ("12 slices pepperoni", "pork"),
+            ("6 anchovies", "seafood"),
+            ("2 sea bass fillets", "seafood"),
+            ("1 lb venison", "game"),
+            ("500 g beef mince", "beef"),
+        ];
+        for (line, tag) in checks {
+            let tags = suggest_tags(&recipe_with(&[line], &["Cook"]));
+            assert!(tags.contains(&tag.to_string()), "{}: {:?}", line, tags);
+            assert!(!tags.contains(&"vegetarian".to_string()), "{}: {:?}", line, tags);
+        }
+        assert!(suggest_tags(&Recipe::default()).is_empty());
+    }
+
+    #[test]
+    fn versions_saved_together_are_kept_and_categories_have_their_own_history() {
+        let _dir = enter_test_dir("version_keys");
+        let dinner = add_recipe("dinner", "Chili", &SOUP_REC.replace("Soup", "Chili"));
+        let sides = add_recipe("sides", "Chili", &SOUP_REC.replace("Soup", "Chili").replace("1 l stock", "1 can beans"));
+
+        archive_recipe_version(&dinner).unwrap();
+        fs::write(&dinner, SOUP_REC.replace("Soup", "Chili").replace("Heat", "Simmer")).unwrap();
+        archive_recipe_version(&dinner).unwrap();
+        let versions = recipe_versions(&dinner);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(parse_recipe_file(&versions[0]).unwrap().instructions, vec!["1. Heat"]);
+        assert_eq!(parse_recipe_file(&versions[1]).unwrap().instructions, vec!["1. Simmer"]);
+
+        assert!(recipe_versions(&sides).is_empty());
+        archive_recipe_version(&sides).unwrap();
+        let side_versions = recipe_versions(&sides);
+        assert_eq!(side_versions.len(), 1);
+        assert_eq!(ingredient_texts(&parse_recipe_file(&side_versions[0]).unwrap()), vec!["1 can beans"]);
+        assert_eq!(recipe_versions(&dinner).len(), 2);
+    }
+
+    #[test]
+    fn recent_recipes_count_weeks_not_history_lines() {
+        let _dir = enter_test_dir("recent_weeks");
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 1, d).unwrap();
+        // Mon 5th, then the week of the 12th processed twice (Mon and Wed)
+        append_history(&["Chili".to_string()], day(5)).unwrap();
+        append_history(&["Curry".to_string()], day(12)).unwrap();
+        append_history(&["Tacos".to_string()], day(14)).unwrap();
+
+        let names = |set: HashSet<String>| -> Vec<String> {
+            let mut names: Vec<String> = set.into_iter().collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(recent_recipes(1, 0)), vec!["Curry", "Tacos"]);
+        assert_eq!(names(recent_recipes(2, 0)), vec!["Chili", "Curry", "Tacos"]);
+        assert!(recent_recipes(0, 0).is_empty());
+        // With weeks starting on Wednesday the 14th begins a week of its own
+        assert_eq!(names(recent_recipes(1, 2)), vec!["Tacos"]);
+    }
+
+    #[test]
+    fn long_shopping_items_wrap_inside_the_page() {
+        let long = "2 cups extra-virgin olive oil from the good bottle at the back of the pantry, not the cheap one by the stove".to_string();
+        let bytes = render_shopping_list_pdf(&[long.clone(), "6 eggs".to_string()]).unwrap();
+        let runs = pdf_text_runs(&bytes);
+        let item_runs: Vec<&TextRun> = runs.iter().filter(|run| run.text != "Shopping List").collect();
+        assert!(item_runs.len() > 2, "the long item should take more than one line");
+        for run in &item_runs {
+            assert!(run.x + text_width_mm(&run.text, run.size, None) <= 200.0, "{:?} runs off the page", run.text);
+        }
+        let wrapped: Vec<&str> = item_runs[..item_runs.len() - 1].iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(wrapped.join(" "), long);
+        assert_eq!(item_runs.last().unwrap().text, "6 eggs");
+    }
+
+    #[test]
+    fn week_grid_keeps_long_meal_names_inside_their_column() {
+        let long = "Slow-Roasted Pork Shoulder With Apples, Fennel and a Cider Gravy";
+        let days: Vec<PlannedDay> = DAYS
+            .iter()
+            .map(|day| PlannedDay {
+                day: day.to_string(),
+                meals: MEAL_TYPES.iter().map(|meal| (meal.to_string(), long.to_string())).collect(),
+                total_time: Some(Duration::from_secs(90 * 60)),
+            })
+            .collect();
+        let bytes = render_schedule_grid_pdf(&days, Some(DurationStyle::Long)).unwrap();
+        let column_width = (287.0 - 10.0) / 7.0;
+        for run in pdf_text_runs(&bytes).iter().filter(|run| run.y < 173.0 && !run.text.starts_with("Cooking time this week")) {
+            let column = ((run.x - 10.0) / column_width).floor();
+            let right_edge = 10.0 + column_width * (column + 1.0);
+            assert!(run.x + text_width_mm(&run.text, run.size, None) <= right_edge, "{:?} leaves its column", run.text);
+            assert!(run.y >= 15.0 + 3.0 - 0.01, "{:?} drops below the bottom rule", run.text);
+        }
+        // Every meal still makes it onto the page, just smaller
+        let text = pdf_text(&bytes).join(" ");
+        assert_eq!(text.matches("Dinner:").count(), 7);
+        assert_eq!(text.matches("1 hr 30 min").count(), 7);
+    }
+
+    #[test]
+    fn prep_section_parses_and_stays_optional() {
+        let with_prep = parse_rec("prep_section", PASTA_REC).unwrap();
+        assert_eq!(with_prep.prep_steps, vec!["Boil a big pot of water"]);
+        assert_eq!(with_prep.instructions, vec!["1. Cook the pasta"]);
+        let saved = recipe_to_rec(&with_prep);
+        assert!(saved.contains("Prep Start\nBoil a big pot of water\nPrep End\n"));
+        assert!(saved.find("Prep Start").unwrap() < saved.find("Instructions Start").unwrap());
+
+        let without_prep = parse_rec("no_prep_section", SOUP_REC).unwrap();
+        assert_eq!(without_prep.title, "Soup");
+        assert!(without_prep.prep_steps.is_empty());
+        assert_eq!(without_prep.instructions, vec!["1. Heat"]);
+        assert!(!recipe_to_rec(&without_prep).contains("Prep Start"));
+    }
+
+    // Every file under a directory with its contents, for checking nothing was written
+    fn snapshot(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(snapshot(&path));
+            } else {
+                files.push((path.clone(), fs::read(&path).unwrap()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn dry_run_plans_the_week_without_writing_anything() {
+        let _dir = enter_test_dir("dry_run");
+        let chili = add_recipe("dinner", "Chili", &SOUP_REC.replace("Soup", "Chili"));
+        add_recipe("lunch", "Pasta", PASTA_REC);
+        fs::create_dir_all("schedule").unwrap();
+        fs::write("schedule/schedule.txt", "Monday Dinner: Tacos\n").unwrap();
+        let before = snapshot(Path::new("."));
+
+        let mut selected = empty_week();
+        selected[0][DINNER] = "Chili".to_string();
+        selected[1][1] = "Pasta".to_string();
+        let plan = build_schedule(&selected, 1.0, &Settings::default()).unwrap();
+
+        assert_eq!(snapshot(Path::new(".")), before);
+        assert_eq!(plan.schedule, "Monday Dinner: Chili\nTuesday Lunch: Pasta\n");
+        assert_eq!(plan.copies[0], (chili, PathBuf::from("schedule/Monday_Dinner.rec")));
+        assert_eq!(plan.copies[1].1, PathBuf::from("schedule/Tuesday_Lunch.rec"));
+        let shopping: Vec<&str> = plan.ingredients.lines().collect();
+        assert_eq!(shopping.len(), 2);
+        assert!(shopping.iter().any(|line| line.contains("stock")) && shopping.iter().any(|line| line.contains("spaghetti")));
+    }
+
+    #[test]
+    fn headers_and_section_markers_ignore_case() {
+        let recipe = parse_rec(
+            "mixed_case",
+            "title\tPancakes\nSERVINGS\t4\n  Prep time \t5 min\nCOOK TIME\t10 min\ntotal Time\t15 min\nfrom\tGran\n\
+             ingredients start\n2 eggs\nINGREDIENTS END\nPrep START\nHeat the pan\nprep end\n\
+             Instructions Start\n1. Fry\ninstructions END\nnotes start\nServe hot\nNotes End\n",
+        )
+        .unwrap();
+        assert_eq!(recipe.title, "Pancakes");
+        assert_eq!(recipe.servings, "4");
+        assert_eq!((recipe.prep_time.as_str(), recipe.cook_time.as_str(), recipe.total_time.as_str()), ("5 min", "10 min", "15 min"));
+        assert_eq!(recipe.from, "Gran");
+        assert_eq!(ingredient_texts(&recipe), vec!["2 eggs"]);
+        assert_eq!(recipe.prep_steps, vec!["Heat the pan"]);
+        assert_eq!(recipe.instructions, vec!["1. Fry"]);
+        assert_eq!(recipe.notes, vec!["Serve hot"]);
+
+        // Saving writes the canonical spelling back
+        let saved = recipe_to_rec(&recipe);
+        assert!(saved.starts_with("Title\tPancakes\nFrom\tGran\nServings\t4\nPrep Time\t5 min\n"));
+        assert!(saved.contains("Ingredients Start\n2 eggs\nIngredients End\n"));
+    }
+
+    #[test]
+    fn timeline_counts_back_from_the_serve_time() {
+        let recipe = recipe_with(&[], &["1. Simmer the sauce for 20 minutes", "2. Chop the parsley", "3. Bake 30 min"]);
+        let serve_at = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let timeline: Vec<(String, i64, String)> = build_timeline(&recipe, serve_at)
+            .into_iter()
+            .map(|entry| (entry.start.format("%H:%M").to_string(), entry.minutes_before, entry.step))
+            .collect();
+        assert_eq!(
+            timeline,
+            vec![
+                ("17:10".to_string(), 50, "1. Simmer the sauce for 20 minutes".to_string()),
+                ("17:30".to_string(), 30, "3. Bake 30 min".to_string()),
+            ]
+        );
+        assert!(build_timeline(&recipe_with(&[], &["Chop", "Serve"]), serve_at).is_empty());
+    }
+
+    #[test]
+    fn wrapped_steps_join_the_step_they_continue() {
+        let lines = |lines: &[&str]| -> Vec<String> { lines.iter().map(|line| line.to_string()).collect() };
+        assert_eq!(
+            merge_wrapped_steps(&lines(&["1. Heat the oil in a large pan and", "   add the onions.", "", "2) Stir in 350 g rice", "3. Serve"])),
+            vec!["1. Heat the oil in a large pan and add the onions.", "2) Stir in 350 g rice", "3. Serve"]
+        );
+        // Unnumbered lists are left as they are, since nothing marks where steps start
+        assert_eq!(merge_wrapped_steps(&lines(&["Chop", "Fry"])), vec!["Chop", "Fry"]);
+        assert!(merge_wrapped_steps(&[]).is_empty());
+    }
+
+    #[test]
+    fn excluded_recipes_are_never_randomized() {
+        let _dir = enter_test_dir("random_exclusions");
+        for name in ["Chili", "Curry", "Feast"] {
+            add_recipe("dinner", name, &SOUP_REC.replace("Soup", name));
+        }
+        save_random_exclusions(&HashSet::from(["Feast".to_string()])).unwrap();
+        assert_eq!(load_random_exclusions(), HashSet::from(["Feast".to_string()]));
+
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        for _ in 0..50 {
+            screen.randomize_all(0);
+            screen.randomize_single(3, 0);
+            assert!(screen.selected_recipes.iter().all(|day| day[DINNER] != "Feast"));
+            assert!(screen.selected_recipes.iter().all(|day| !day[DINNER].is_empty()));
+        }
+
+        // Excluding everything leaves the days alone and says why
+        save_random_exclusions(&HashSet::from(["Chili".to_string(), "Curry".to_string(), "Feast".to_string()])).unwrap();
+        let mut screen = CreateWeeklyRecipesScreen::default();
+        screen.randomize_all(0);
+        assert!(screen.selected_recipes.iter().all(|day| day[DINNER].is_empty()));
+        assert!(screen.processing_message.starts_with("Warning: Every Dinner recipe is excluded"), "{}", screen.processing_message);
+    }
+
+    #[test]
+    fn ingredient_frequencies_count_recipes_most_used_first() {
+        let recipes = [
+            recipe_with(&["2 eggs", "1 cup flour", "2 eggs (beaten, for brushing)"], &[]),
+            recipe_with(&["3 eggs", "1 tbsp butter"], &[]),
+            recipe_with(&["200 g flour", "3 Eggs", "salt to taste"], &[]),
+        ];
+        let frequencies = ingredient_frequencies(&recipes);
+        let count = |name: &str| frequencies.iter().find(|(ingredient, _)| ingredient == name).map(|(_, count)| *count);
+        // Eggs listed twice in the first recipe still count once for it
+        assert_eq!(frequencies[0], ("eggs".to_string(), 3));
+        assert_eq!(count("flour"), Some(2));
+        assert_eq!(count("butter"), Some(1));
+        assert!(frequencies.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert!(ingredient_frequencies(&[]).is_empty());
+    }
+
+    #[test]
+    fn pdf_generation_refuses_to_start_twice_at_once() {
+        // A missing file fails fast and never opens a viewer
+        let missing = test_dir("pdf_guard").join("Missing.rec");
+        let mut screen = RecipeSelectionScreen::default();
+        assert!(screen.start_pdf_generation(missing.clone(), 1.0, Settings::default()));
+        assert!(!screen.start_pdf_generation(missing.clone(), 1.0, Settings::default()));
+        assert!(screen.pdf_in_progress);
+
+        while screen.pdf_in_progress {
+            thread::sleep(Duration::from_millis(10));
+            screen.poll_pdf_generation();
+        }
+        assert!(screen.processing_message.starts_with("Error generating PDF"), "{}", screen.processing_message);
+        assert!(screen.pdf_job.is_none());
+        // Once the first run is picked up the button works again
+        assert!(screen.start_pdf_generation(missing, 1.0, Settings::default()));
+    }
+
+    #[test]
+    fn transfer_item_moves_between_notes_and_instructions() {
+        let texts = |items: &[&str]| -> Vec<String> { items.iter().map(|item| item.to_string()).collect() };
+        let mut notes = texts(&["Rest the dough overnight", "Freezes well"]);
+        let mut steps = texts(&["1. Mix", "2. Bake"]);
+
+        assert!(!transfer_item(&mut notes, 5, &mut steps));
+        assert_eq!((notes.len(), steps.len()), (2, 2));
+
+        assert!(transfer_item(&mut notes, 0, &mut steps));
+        assert_eq!(notes, texts(&["Freezes well"]));
+        assert_eq!(steps, texts(&["1. Mix", "2. Bake", "Rest the dough overnight"]));
+
+        // Emptying a list leaves one blank row to type into, and a blank row in the
+        // target is filled instead of kept
+        let mut blank = texts(&[""]);
+        assert!(transfer_item(&mut notes, 0, &mut blank));
+        assert_eq!(notes, texts(&[""]));
+        assert_eq!(blank, texts(&["Freezes well"]));
+    }
+
+    // A screen that only records how often it was drawn and can be told to close
+    struct StubScreen {
+        exit: bool,
+        drawn: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl Screen for StubScreen {
+        fn update(&mut self, _ctx: &egui::Context, _app_state: &mut AppState) -> Option<Box<dyn Screen>> {
+            self.drawn.set(self.drawn.get() + 1);
+            None
+        }
+        fn wants_to_exit(&self) -> bool {
+            self.exit
+        }
+    }
+
+    #[test]
+    fn back_returns_to_the_previous_screen() {
+        let _dir = enter_test_dir("screen_stack");
+        let ctx = egui::Context::default();
+        let selection_drawn = std::rc::Rc::new(std::cell::Cell::new(0));
+        let view_drawn = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut main = MainScreen::default();
+        main.push_screen(Box::new(StubScreen { exit: false, drawn: selection_drawn.clone() }));
+        main.push_screen(Box::new(StubScreen { exit: true, drawn: view_drawn.clone() }));
+        assert_eq!(main.screen_stack.len(), 2);
+
+        // The top screen asked to go back, so the one under it is shown, not the main menu
+        let _ = ctx.run(egui::RawInput::default(), |ctx| main.update(ctx));
+        assert_eq!(main.screen_stack.len(), 1);
+        assert_eq!((selection_drawn.get(), view_drawn.get()), (1, 0));
+
+        main.push_screen(Box::new(StubScreen { exit: false, drawn: view_drawn.clone() }));
+        let popped = main.pop_screen().unwrap();
+        assert!(!popped.wants_to_exit());
+        assert_eq!(main.screen_stack.len(), 1);
+        assert!(main.pop_screen().is_some());
+        assert!(main.pop_screen().is_none());
+    }
+
+    #[test]
+    fn sorted_shopping_list_ignores_case_and_quantities() {
+        let _dir = enter_test_dir("sorted_shopping");
+        let ingredients = "2 Zucchini\n1 cup flour\n3 Eggs\nsalt\n1 tbsp Brown sugar\n";
+        add_recipe("dinner", "Bake", &SOUP_REC.replace("Soup", "Bake").replace("1 l stock\n", ingredients));
+        let mut selected = empty_week();
+        selected[0][DINNER] = "Bake".to_string();
+
+        let mut settings = Settings::default();
+        let plan = build_schedule(&selected, 1.0, &settings).unwrap();
+        assert_eq!(plan.ingredients, ingredients);
+
+        settings.sort_shopping_list = true;
+        let plan = build_schedule(&selected, 1.0, &settings).unwrap();
+        assert_eq!(plan.ingredients, "1 tbsp Brown sugar\n3 Eggs\n1 cup flour\nsalt\n2 Zucchini\n");
+    }
+
+    #[test]
+    fn sources_split_into_attribution_and_link() {
+        let parts = |from: &str| {
+            let source = parse_source(from);
+            (source.name, source.url)
+        };
+        let some = |text: &str| Some(text.to_string());
+
+        assert_eq!(parts("Grandma's Cookbook"), (some("Grandma's Cookbook"), None));
+        assert_eq!(parts("https://example.com/pie"), (None, some("https://example.com/pie")));
+        assert_eq!(
+            parts("Grandma's Cookbook (https://example.com/pie)"),
+            (some("Grandma's Cookbook"), some("https://example.com/pie"))
+        );
+        assert_eq!(parts("Serious Eats - http://example.com/a?b=1"), (some("Serious Eats"), some("http://example.com/a?b=1")));
+        assert_eq!(parts("[https://example.com] NYT Cooking"), (some("NYT Cooking"), some("https://example.com")));
+        assert_eq!(parts("  "), (None, None));
+    }
+
+    #[test]
+    fn broken_font_file_falls_back_to_helvetica() {
+        let _dir = enter_test_dir("broken_font");
+        fs::create_dir_all(PDF_FONTS_DIR).unwrap();
+        fs::write(Path::new(PDF_FONTS_DIR).join("broken.ttf"), "not a font").unwrap();
+        let settings = Settings { pdf_font: "broken.ttf".to_string(), ..Settings::default() };
+
+        let (bytes, _) = render_recipes_pdf(&[parse_rec("pasta", PASTA_REC).unwrap()], "Pasta", &[], &settings).unwrap();
+        let raw = String::from_utf8_lossy(&bytes);
+        assert!(raw.contains("/Helvetica"));
+        assert!(!raw.contains("FontFile2"));
+        assert!(pdf_text(&bytes).iter().any(|text| text.contains("Pasta")));
+    }
+
+    #[test]
+    fn labelled_servings_survive_saving_and_phrase_the_scaling() {
+        let recipe = parse_rec("bowls", &SOUP_REC.replace("Title\tSoup\n", "Title\tSoup\nServings\t4 bowls\n")).unwrap();
+        assert_eq!(recipe.servings, "4 bowls");
+        let reparsed = parse_rec("bowls_again", &recipe_to_rec(&recipe)).unwrap();
+        assert_eq!(reparsed.servings, "4 bowls");
+
+        assert_eq!(describe_scaled_servings("4 bowls", 1.5).as_deref(), Some("scaled to 6 bowls"));
+        assert_eq!(describe_scaled_servings("2-3 pieces", 2.0).as_deref(), Some("scaled to 4-6 pieces"));
+        assert_eq!(describe_scaled_servings("4", 0.5).as_deref(), Some("scaled to 2 servings"));
+        assert_eq!(describe_scaled_servings("a few", 2.0), None);
+    }
+
+    #[test]
+    fn week_grid_shows_every_day_and_its_meals() {
+        let recipes = ["Chili", "Tacos", "Pasta", "Soup", "Curry", "Pizza", "Roast"];
+        let days: Vec<PlannedDay> = DAYS
+            .iter()
+            .zip(recipes)
+            .map(|(day, recipe)| PlannedDay {
+                day: day.to_string(),
+                meals: vec![("Dinner".to_string(), recipe.to_string())],
+                total_time: None,
+            })
+            .collect();
+        let bytes = render_schedule_grid_pdf(&days, None).unwrap();
+
+        let text = pdf_text(&bytes);
+        assert_eq!(text[0], "Week at a Glance");
+        for (day, recipe) in DAYS.iter().zip(recipes) {
+            assert!(text.iter().any(|run| run == day), "{} is missing", day);
+            assert!(text.contains(&format!("Dinner: {}", recipe)), "{} is missing", recipe);
+        }
+        assert!(!text.iter().any(|run| run.starts_with("Cooking time")));
+    }
+
+    #[test]
+    fn list_markers_are_stripped_from_ingredients() {
+        assert_eq!(strip_list_marker("- 2 eggs"), "2 eggs");
+        assert_eq!(strip_list_marker("*   1 cup flour"), "1 cup flour");
+        assert_eq!(strip_list_marker("• salt"), "salt");
+        assert_eq!(strip_list_marker("-1 tsp pepper"), "-1 tsp pepper");
+        assert_eq!(strip_list_marker("2 eggs"), "2 eggs");
+
+        let recipe = parse_rec("markers", &SOUP_REC.replace("1 l stock\n", "- 1 l stock\n* 2 carrots\n• salt\n")).unwrap();
+        assert_eq!(ingredient_texts(&recipe), vec!["1 l stock", "2 carrots", "salt"]);
+    }
+
+    // Only Unix lets a process remove the directory it's working in
+    #[cfg(unix)]
+    #[test]
+    fn pdf_path_falls_back_to_relative_when_the_working_directory_is_gone() {
+        let _dir = enter_test_dir("pdf_path");
+        let here = env::current_dir().unwrap();
+        assert_eq!(recipe_pdf_path("Mac and Cheese"), here.join("Mac_and_Cheese.pdf"));
+
+        fs::create_dir("removed").unwrap();
+        env::set_current_dir("removed").unwrap();
+        fs::remove_dir(here.join("removed")).unwrap();
+        assert!(env::current_dir().is_err());
+        assert_eq!(recipe_pdf_path("Mac and Cheese"), PathBuf::from("Mac_and_Cheese.pdf"));
+    }
+
+    #[test]
+    fn title_field_is_focused_only_when_the_form_opens() {
+        let _dir = enter_test_dir("initial_focus");
+        let mut screen = CreateRecipeManuallyScreen::default();
+        assert!(screen.take_initial_focus());
+        assert!(!screen.take_initial_focus());
+        assert!(!screen.take_initial_focus());
+
+        // Opening the form again focuses the title again
+        assert!(CreateRecipeManuallyScreen::default().take_initial_focus());
+    }
+
+    #[test]
+    fn recipes_are_grouped_by_their_category_folder() {
+        let _dir = enter_test_dir("category_groups");
+        add_recipe("dinner", "Tacos", SOUP_REC);
+        add_recipe("dinner", "Chili", SOUP_REC);
+        add_recipe("sides", "Slaw", SOUP_REC);
+        add_recipe("desert", "Pie", SOUP_REC);
+        add_recipe("dessert", "Cake", SOUP_REC);
+        fs::create_dir_all("recipes/lunch").unwrap();
+        fs::write("recipes/sides/notes.txt", "not a recipe").unwrap();
+
+        let dirs: Vec<String> = ["dinner", "lunch", "sides", "desert", "dessert", "breakfast"]
+            .iter()
+            .map(|category| format!("{}/{}", RECIPES_ROOT, category))
+            .collect();
+        let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            group_recipes_by_category(&dirs, DEFAULT_RECIPE_EXTENSION),
+            vec![
+                ("Dinner".to_string(), strings(&["Chili", "Tacos"])),
+                ("Sides".to_string(), strings(&["Slaw"])),
+                ("Dessert".to_string(), strings(&["Cake", "Pie"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn servings_coverage_compares_against_the_household() {
+        assert_eq!(servings_cover_household("4", 4), Some(true));
+        assert_eq!(servings_cover_household("6 bowls", 4), Some(true));
+        assert_eq!(servings_cover_household("2", 4), Some(false));
+        // A range only covers the household if its smallest count does
+        assert_eq!(servings_cover_household("3-5", 4), Some(false));
+        assert_eq!(servings_cover_household("4", 0), None);
+        assert_eq!(servings_cover_household("a crowd", 4), None);
+        assert_eq!(servings_cover_household("", 4), None);
+    }
+
+    #[test]
+    fn surprise_pick_comes_from_the_collection_and_skips_exclusions() {
+        let names: Vec<String> = ["Chili", "Tacos", "Soup"].iter().map(|name| name.to_string()).collect();
+        let excluded: HashSet<String> = ["Tacos".to_string()].into_iter().collect();
+        for _ in 0..50 {
+            let pick = pick_surprise_recipe(&names, &excluded).unwrap();
+            assert!(pick == "Chili" || pick == "Soup", "{} was picked", pick);
+        }
+        assert_eq!(pick_surprise_recipe(&names, &HashSet::new()).map(|pick| names.contains(&pick)), Some(true));
+
+        let everything: HashSet<String> = names.iter().cloned().collect();
+        assert_eq!(pick_surprise_recipe(&names, &everything), None);
+        assert_eq!(pick_surprise_recipe(&[], &HashSet::new()), None);
+    }
+
+    #[test]
+    fn grouped_shopping_list_keeps_each_recipes_ingredients_under_it() {
+        let lines = |items: &[&str]| items.iter().map(|item| item.to_string()).collect::<Vec<String>>();
+        let meals = vec![
+            ("Monday Dinner".to_string(), "Chili".to_string(), lines(&["1 lb beef", "1 can beans"])),
+            ("Tuesday Lunch".to_string(), "Soup".to_string(), lines(&["1 l stock", "1 can beans"])),
+        ];
+        assert_eq!(
+            format_ingredients_by_recipe(&meals),
+            "Monday Dinner: Chili\n  1 lb beef\n  1 can beans\n\nTuesday Lunch: Soup\n  1 l stock\n  1 can beans\n"
+        );
+        assert_eq!(format_ingredients_by_recipe(&[]), "");
+    }
+
+    #[test]
+    fn durations_format_in_each_style() {
+        let ninety = Duration::from_secs(90 * 60);
+        assert_eq!(format_duration(ninety, DurationStyle::Long), "1 hr 30 min");
+        assert_eq!(format_duration(ninety, DurationStyle::Short), "1 h 30 min");
+        assert_eq!(format_duration(ninety, DurationStyle::Minutes), "90 min");
+
+        let two_hours = Duration::from_secs(120 * 60);
+        assert_eq!(format_duration(two_hours, DurationStyle::Long), "2 hr");
+        assert_eq!(format_duration(two_hours, DurationStyle::Short), "2 h");
+        assert_eq!(format_duration(Duration::from_secs(45 * 60), DurationStyle::Long), "45 min");
+    }
+
+    #[test]
+    fn library_health_reports_each_kind_of_issue() {
+        let root = test_dir("audit_kinds");
+        let (main, extra) = (root.join("main"), root.join("extra"));
+        fs::create_dir_all(&main).unwrap();
+        fs::create_dir_all(&extra).unwrap();
+        fs::write(main.join("Salad.rec"), TYPO_REC).unwrap();
+        fs::write(main.join("Untitled.rec"), SOUP_REC.replace("Title\tSoup\n", "")).unwrap();
+        fs::write(main.join("Bare.rec"), "Title\tBare\n").unwrap();
+        fs::write(main.join("Slow.rec"), SOUP_REC.replace("Title\tSoup\n", "Title\tSlow\nCook Time\tuntil done\n")).unwrap();
+        fs::write(main.join("Soup.rec"), SOUP_REC).unwrap();
+        fs::write(extra.join("soup.rec"), SOUP_REC).unwrap();
+
+        let dirs = [main.to_string_lossy().to_string(), extra.to_string_lossy().to_string()];
+        let found: Vec<(String, String)> = audit_library(&dirs, DEFAULT_RECIPE_EXTENSION)
+            .iter()
+            .map(|issue| {
+                let file = issue.path.file_name().unwrap().to_string_lossy().to_string();
+                let kind = match &issue.kind {
+                    IssueKind::Unreadable(_) => "unreadable".to_string(),
+                    IssueKind::MissingTitle => "missing title".to_string(),
+                    IssueKind::EmptySection(section) => format!("empty {}", section),
+                    IssueKind::UnparseableTime(field, value) => format!("{}: {}", field, value),
+                    IssueKind::DuplicateFilename(others) => format!("duplicate of {}", others.len()),
+                };
+                (file, kind)
+            })
+            .collect();
+        let expected: Vec<(String, String)> = [
+            ("Bare.rec", "empty ingredients"),
+            ("Bare.rec", "empty instructions"),
+            ("Salad.rec", "unreadable"),
+            ("Slow.rec", "Cook Time: until done"),
+            ("Untitled.rec", "missing title"),
+            ("Soup.rec", "duplicate of 1"),
+            ("soup.rec", "duplicate of 1"),
+        ]
+        .iter()
+        .map(|(file, kind)| (file.to_string(), kind.to_string()))
+        .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn flour_with_different_notes_merges_into_one_line() {
+        let sifted = parse_ingredient("2 cups flour (sifted)", false);
+        assert_eq!(sifted.name, "flour");
+        assert_eq!(sifted.prep.as_deref(), Some("sifted"));
+        assert_eq!(parse_ingredient("1 cup flour (spooned and leveled)", false).name, "flour");
+
+        assert_eq!(
+            shopping_lines(&["2 cups flour (sifted)", "1 cup flour (spooned and leveled)"]),
+            vec!["3 cup flour (sifted, spooned and leveled)"]
+        );
+        assert_eq!(shopping_lines(&["2 cups flour (sifted)", "1 cup flour"]), vec!["3 cup flour (sifted)"]);
+    }
+
+    #[actix_web::test]
+    async fn chosen_step_style_shows_in_the_output() {
+        let steps = vec!["1. Chop".to_string(), "2) Simmer".to_string(), "Serve".to_string()];
+        assert_eq!(format_steps(&steps, false), vec!["1. Chop", "2. Simmer", "3. Serve"]);
+        assert_eq!(format_steps(&steps, true), vec!["• Chop", "• Simmer", "• Serve"]);
+
+        let _dir = enter_test_dir("step_style");
+        add_recipe("dinner", "Pasta", PASTA_REC);
+        let squashed = |body: String| body.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let (_, body) = get_page("/recipe/Pasta").await;
+        assert!(squashed(body).contains("<ol> <li>Cook the pasta</li> </ol>"));
+
+        Settings { bulleted_steps: true, ..Settings::default() }.save().unwrap();
+        let (_, body) = get_page("/recipe/Pasta").await;
+        assert!(squashed(body).contains("<ul> <li>Cook the pasta</li> </ul>"));
+    }
+
+    #[test]
+    fn make_ahead_flag_parses_saves_and_filters() {
+        let with_flag = |value: &str| SOUP_REC.replace("Title\tSoup\n", &format!("Title\tSoup\nMakeAhead\t{}\n", value));
+        for value in ["true", "Yes", "y", "1"] {
+            assert!(parse_rec("make_ahead", &with_flag(value)).unwrap().make_ahead, "{} should mark it", value);
+        }
+        assert!(!parse_rec("make_ahead", &with_flag("no")).unwrap().make_ahead);
+        let plain = parse_rec("make_ahead", SOUP_REC).unwrap();
+        assert!(!plain.make_ahead);
+        assert!(!recipe_to_rec(&plain).contains("MakeAhead"));
+
+        let marked = parse_rec("make_ahead", &with_flag("yes")).unwrap();
+        assert!(recipe_to_rec(&marked).contains("MakeAhead\ttrue\n"));
+        assert!(parse_rec("make_ahead_again", &recipe_to_rec(&marked)).unwrap().make_ahead);
+
+        assert!(passes_make_ahead_filter(&marked, true));
+        assert!(passes_make_ahead_filter(&marked, false));
+        assert!(!passes_make_ahead_filter(&plain, true));
+        assert!(passes_make_ahead_filter(&plain, false));
+    }
+
+    #[test]
+    fn cook_mode_keeps_the_scale_while_moving_between_steps() {
+        let recipe = recipe_with(&["2 carrots"], &["1. Chop", "2. Simmer", "3. Serve"]);
+        let mut screen = CookModeScreen::new(recipe, 1.5);
+        screen.next_step();
+        screen.next_step();
+        screen.next_step();
+        assert_eq!((screen.step, screen.scale_factor), (2, 1.5));
+
+        // Changing the scale partway through leaves the current step alone
+        screen.scale_factor = 2.0;
+        screen.previous_step();
+        assert_eq!((screen.step, screen.scale_factor), (1, 2.0));
+        screen.previous_step();
+        screen.previous_step();
+        assert_eq!((screen.step, screen.scale_factor), (0, 2.0));
+        assert_eq!(ingredient_texts(&screen.recipe.scale(screen.scale_factor, false)), vec!["4 carrots"]);
+    }
+
+    #[test]
+    fn paprika_archive_holds_gzipped_json_for_each_recipe() {
+        use std::io::Read;
+
+        let mut recipe = parse_rec("paprika", PASTA_REC).unwrap();
+        recipe.from = "Gran's Cookbook (https://example.com/pasta)".to_string();
+        recipe.tags = vec!["quick".to_string()];
+        let dest = test_dir("paprika_export").join("recipes.paprikarecipes");
+        assert_eq!(export_paprika(&[recipe.clone(), recipe], &dest).unwrap(), 2);
+
+        let mut archive = zip::ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let mut names: Vec<String> = archive.file_names().map(|name| name.to_string()).collect();
+        names.sort();
+        assert_eq!(names, vec!["Pasta.paprikarecipe", "Pasta_2.paprikarecipe"]);
+
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(archive.by_name("Pasta.paprikarecipe").unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        let exported: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(exported["name"], "Pasta");
+        assert_eq!(exported["servings"], "2");
+        assert_eq!(exported["source"], "Gran's Cookbook");
+        assert_eq!(exported["source_url"], "https://example.com/pasta");
+        assert_eq!(exported["ingredients"], "200 g spaghetti");
+        assert_eq!(exported["directions"], "Boil a big pot of water\n1. Cook the pasta");
+        assert_eq!(exported["notes"], "Use plenty of salt\nLeftovers keep two days");
+        assert_eq!(exported["categories"], json!(["quick"]));
+        assert_eq!(exported["hash"].as_str().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn scale_factor_display_follows_the_servings() {
+        // Mirrors the view: scaling to a servings count divides by the recipe's lower bound
+        let min = parse_servings("4-6 bowls").unwrap().min;
+        let mut factor = 1.0;
+        assert_eq!(describe_scale_factor(factor), "1x → 1x");
+        factor = 6.0 / min;
+        assert_eq!(describe_scale_factor(factor), "1x → 1.5x");
+        factor *= 0.5;
+        assert_eq!(describe_scale_factor(factor), "1x → 0.75x");
+        factor *= 2.0;
+        factor *= 2.0;
+        assert_eq!(describe_scale_factor(factor), "1x → 3x");
+        factor = 2.0 / min;
+        assert_eq!(describe_scale_factor(factor), "1x → 0.5x");
+    }
 }